@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BatteryThreshold } from "./BatteryThreshold";
+
+export interface BatteryAlertConfig { thresholds: Record<string, BatteryThreshold>, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface BatteryThreshold { low: number, critical: number | null, critical_max_level: number | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ChatboxConfig { enabled: boolean, quiet: boolean, cooldown_ms: number, toy_connected: string | null, toy_disconnected: string | null, battery_low: string | null, vibecheck_enabled: string | null, vibecheck_disabled: string | null, }
\ No newline at end of file
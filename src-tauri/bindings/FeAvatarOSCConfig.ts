@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeAvatarParameter } from "./FeAvatarParameter";
+import type { FeAvatarToySuggestion } from "./FeAvatarToySuggestion";
+
+export interface FeAvatarOSCConfig { user_id: string, avatar_id: string, avatar_name: string, parameters: Array<FeAvatarParameter>, suggestions: Array<FeAvatarToySuggestion>, }
\ No newline at end of file
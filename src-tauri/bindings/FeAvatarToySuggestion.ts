@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeVCToyAnatomy } from "./FeVCToyAnatomy";
+import type { PenetrationSystemType } from "./PenetrationSystemType";
+
+export interface FeAvatarToySuggestion { anatomy: FeVCToyAnatomy, pen_system_type: PenetrationSystemType, object: string | null, parameters: Array<string>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BatteryAlertLevel } from "./BatteryAlertLevel";
+
+export interface FeBatteryEvent { toy_id: number, toy_name: string, sub_id: number, level: number, alert: BatteryAlertLevel, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BatteryAlertLevel } from "./BatteryAlertLevel";
+import type { FeBatterySample } from "./FeBatterySample";
+
+export interface FeBatteryHistory { toy_name: string, sub_id: number, alert: BatteryAlertLevel, estimated_remaining_secs: number | null, samples: Array<FeBatterySample>, }
\ No newline at end of file
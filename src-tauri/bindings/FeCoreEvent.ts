@@ -1,5 +1,6 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeBatteryEvent } from "./FeBatteryEvent";
 import type { FeScanEvent } from "./FeScanEvent";
 import type { FeStateEvent } from "./FeStateEvent";
 
-export type FeCoreEvent = { "kind": "Scan", "data": FeScanEvent } | { "kind": "State", "data": FeStateEvent };
\ No newline at end of file
+export type FeCoreEvent = { "kind": "Scan", "data": FeScanEvent } | { "kind": "State", "data": FeStateEvent } | { "kind": "Battery", "data": FeBatteryEvent };
\ No newline at end of file
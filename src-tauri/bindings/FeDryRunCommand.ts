@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeVCFeatureType } from "./FeVCFeatureType";
+
+export interface FeDryRunCommand { timestamp_ms: number, toy_name: string, toy_id: number | null, feature_index: number, feature_type: FeVCFeatureType, actuator: string, level: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeProcessingMode } from "./FeProcessingMode";
+
+export interface FeLearnProposal { addr: string, processing_mode: FeProcessingMode, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeLearnProposal } from "./FeLearnProposal";
+import type { FeToyFeatureRef } from "./FeToyFeatureRef";
+
+export interface FeLearnResult { feature: FeToyFeatureRef, proposal: FeLearnProposal | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeOSCConfigAvatar } from "./FeOSCConfigAvatar";
+
+export interface FeOSCConfigUser { user_id: string, avatars: Array<FeOSCConfigAvatar>, }
\ No newline at end of file
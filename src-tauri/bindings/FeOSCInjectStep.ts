@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ModeProcessorInputType } from "./ModeProcessorInputType";
+
+export interface FeOSCInjectStep { delay_ms: number, addr: string, value: ModeProcessorInputType, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OSCAddressRewrite } from "./OSCAddressRewrite";
+import type { OSCTransport } from "./OSCTransport";
+
+export interface FeOSCListener { name: string, address: string, allowlist: Array<string>, api_secret: string | null, rewrite: OSCAddressRewrite | null, transport: OSCTransport, }
\ No newline at end of file
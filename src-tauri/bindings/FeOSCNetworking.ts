@@ -1,3 +1,6 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeOSCListener } from "./FeOSCListener";
+import type { FeOSCRepeaterTarget } from "./FeOSCRepeaterTarget";
+import type { OSCTransport } from "./OSCTransport";
 
-export interface FeOSCNetworking { bind: string, remote: string, osc_query_enabled: boolean, }
\ No newline at end of file
+export interface FeOSCNetworking { bind: Array<FeOSCListener>, remote: string, remote_transport: OSCTransport, osc_query_enabled: boolean, repeater: Array<FeOSCRepeaterTarget>, feedback_rate: number, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeOSCParameterConsumer } from "./FeOSCParameterConsumer";
+import type { ModeProcessorInputType } from "./ModeProcessorInputType";
+
+export interface FeOSCParameter { addr: string, value: ModeProcessorInputType, first_seen_ms: number, last_seen_ms: number, updates: number, rate_hz: number, consumers: Array<FeOSCParameterConsumer>, }
\ No newline at end of file
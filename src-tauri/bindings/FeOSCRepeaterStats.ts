@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface FeOSCRepeaterStats { name: string, enabled: boolean, forwarded_packets: number, forwarded_bytes: number, filtered_packets: number, send_errors: number, }
\ No newline at end of file
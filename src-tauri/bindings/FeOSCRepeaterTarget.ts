@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OSCAddressRewrite } from "./OSCAddressRewrite";
+
+export interface FeOSCRepeaterTarget { name: string, address: string, enabled: boolean, filter: string | null, rewrite: OSCAddressRewrite | null, }
\ No newline at end of file
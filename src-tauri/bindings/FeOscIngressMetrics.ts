@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface FeOscIngressMetrics { packets: number, decode_errors: number, accepted: number, rejected_address: number, rejected_no_arguments: number, rejected_type: number, rejected_non_finite: number, rejected_source: number, rejected_api_secret: number, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeTelemetryTarget } from "./FeTelemetryTarget";
+import type { TelemetryField } from "./TelemetryField";
+
+export interface FeTelemetryConfig { interval_secs: number, targets: Array<FeTelemetryTarget>, address_template: string, fields: Array<TelemetryField>, aliases: Record<string, string>, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeVCFeatureType } from "./FeVCFeatureType";
+
+export interface FeToyFeatureRef { toy_id: number | null, toy_name: string, feature_index: number, feature_type: FeVCFeatureType, }
\ No newline at end of file
@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { FeProcessingMode } from "./FeProcessingMode";
+import type { IntValueMapping } from "./IntValueMapping";
 
-export interface FeToyParameter { parameter: string, processing_mode: FeProcessingMode, }
\ No newline at end of file
+export interface FeToyParameter { parameter: string, processing_mode: FeProcessingMode, int_values: Array<IntValueMapping>, }
\ No newline at end of file
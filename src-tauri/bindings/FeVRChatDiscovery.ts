@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FeAvatarParameter } from "./FeAvatarParameter";
+import type { FeMissingParameter } from "./FeMissingParameter";
+
+export interface FeVRChatDiscovery { name: string | null, http: string, osc_remote: string, avatar_id: string | null, parameters: Array<FeAvatarParameter>, missing_parameters: Array<FeMissingParameter>, }
\ No newline at end of file
@@ -1,4 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BatteryAlertConfig } from "./BatteryAlertConfig";
+import type { ChatboxConfig } from "./ChatboxConfig";
 import type { FeOSCNetworking } from "./FeOSCNetworking";
+import type { FeTelemetryConfig } from "./FeTelemetryConfig";
 
-export interface FeVibeCheckConfig { networking: FeOSCNetworking, scan_on_disconnect: boolean, minimize_on_exit: boolean, desktop_notifications: boolean, lc_override: string | null, show_toy_advanced: boolean, show_feature_advanced: boolean, messages_per_second: number }
\ No newline at end of file
+export interface FeVibeCheckConfig { networking: FeOSCNetworking, scan_on_disconnect: boolean, minimize_on_exit: boolean, desktop_notifications: boolean, lc_override: string | null, show_toy_advanced: boolean, show_feature_advanced: boolean, messages_per_second: number, telemetry: FeTelemetryConfig, battery_alerts: BatteryAlertConfig, chatbox: ChatboxConfig, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OSCFraming } from "./OSCFraming";
+
+export type OSCTransport = { "kind": "Udp" } | { "kind": "Tcp", "framing": OSCFraming };
\ No newline at end of file
@@ -7,16 +7,28 @@ use crate::{
     config::toy::VCToyConfig,
     frontend::{
         frontend_types::{
-            FeBrowserLink, FeToyAlter, FeToyEvent, FeVCFeatureType, FeVCToy, FeVibeCheckConfig,
+            FeAvatarOSCConfig, FeAvatarToySuggestion, FeBatteryHistory, FeBrowserLink,
+            FeOSCBindFailure, FeOSCConfigClearResult, FeOSCConfigUser, FeOSCInjectStep,
+            FeOSCParameter, FeOSCRepeaterStats, FeOscIngressMetrics, FeToyAlter, FeToyEvent,
+            FeToyFeatureRef, FeVCFeatureType, FeVCToy, FeVRChatDiscovery, FeVibeCheckConfig,
         },
+        validation::validate,
         FromFrontend, ToFrontend,
     },
+    osc::{
+        avatar_backup, avatar_config,
+        ingress::{VCOscMessage, OSC_INGRESS_METRICS},
+        inject, learn, param_browser, recording, vrchat_discovery,
+    },
+    toy_handling::{dry_run::dry_run_enabled, toyops::ProcessingMode, ModeProcessorInputType},
+    util::fs::get_vrchat_osc_dir,
     vcore::core::{
         self, native_osc_query_attempt_force, native_osc_query_start, native_osc_query_stop,
     },
     vcore::vcerror::{backend, frontend},
 };
 use log::{error as logerr, trace};
+use std::{path::Path, time::Duration};
 use tauri::Manager;
 
 /*
@@ -136,6 +148,13 @@ pub fn alter_toy(
 ) -> Result<(), frontend::VCFeError> {
     trace!("alter_toy({:#?})", mutate);
 
+    // Reject the whole alteration if any field is invalid
+    let (FeToyAlter::Connected(fe_toy) | FeToyAlter::Disconnected(fe_toy)) = &mutate;
+    if let Err(field_errors) = validate(fe_toy) {
+        logerr!("Toy alteration failed validation: {:?}", field_errors);
+        return Err(frontend::VCFeError::InvalidToyAlteration(field_errors));
+    }
+
     match mutate {
         // Online toy alter
         FeToyAlter::Connected(fe_toy) => {
@@ -272,12 +291,69 @@ pub fn open_default_browser(link: FeBrowserLink) {
 
 /*
  * Clears VRChat OSC avatar configs
- * Clears all folders in the OSC folder that start with 'usr_'
+ * Args: user_id, avatar_id (both None clears every user, avatar_id needs user_id)
+ * Cleared files are moved into a backup that osc_config_restore can put back
  */
 #[tauri::command(async)]
-pub fn clear_osc_config() -> Result<(), backend::VibeCheckFSError> {
+pub fn clear_osc_config(
+    user_id: Option<String>,
+    avatar_id: Option<String>,
+) -> Result<FeOSCConfigClearResult, frontend::VCFeError> {
     trace!("clear_osc_config");
-    core::native_clear_osc_config()
+    core::native_clear_osc_config(user_id, avatar_id)
+}
+
+/*
+ * Lists the users and avatars in the VRChat OSC folder
+ */
+#[tauri::command(async)]
+pub fn osc_config_users() -> Result<Vec<FeOSCConfigUser>, backend::VibeCheckFSError> {
+    trace!("osc_config_users");
+    avatar_config::list_osc_config_users(Path::new(&get_vrchat_osc_dir()))
+}
+
+#[tauri::command(async)]
+pub fn osc_config_backups() -> Vec<String> {
+    trace!("osc_config_backups");
+    avatar_backup::list_osc_config_backups(Path::new(&avatar_backup::backups_dir()))
+}
+
+/*
+ * Puts the files of a backup back into the VRChat OSC folder
+ * Returns the restored files
+ */
+#[tauri::command(async)]
+pub fn osc_config_restore(backup: String) -> Result<Vec<String>, frontend::VCFeError> {
+    trace!("osc_config_restore");
+    avatar_backup::restore_osc_config_backup(
+        Path::new(&get_vrchat_osc_dir()),
+        Path::new(&avatar_backup::backups_dir()),
+        &backup,
+    )
+}
+
+/*
+ * Lists the avatar OSC configs VRChat wrote with a suggested toy setup per avatar
+ */
+#[tauri::command(async)]
+pub fn avatar_configs() -> Result<Vec<FeAvatarOSCConfig>, backend::VibeCheckFSError> {
+    trace!("avatar_configs");
+    core::native_avatar_configs()
+}
+
+/*
+ * Applies a suggested toy setup to a toy feature
+ * Args: feature (toy_id is None for offline toys), suggestion
+ */
+#[tauri::command(async)]
+pub fn avatar_suggestion_accept(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+    app_handle: tauri::AppHandle,
+    feature: FeToyFeatureRef,
+    suggestion: FeAvatarToySuggestion,
+) -> Result<(), frontend::VCFeError> {
+    trace!("avatar_suggestion_accept");
+    core::native_avatar_suggestion_accept(vc_state, app_handle, feature, suggestion)
 }
 
 /*
@@ -305,16 +381,37 @@ pub fn simulate_device_feature(
 }
 
 /*
- * Sends the specified OSC address / value to the app itself
- * Args: simulated_param_address, simulated_param_value
- * Removing this code for now
+ * Injects an OSC message into the dispatch pipeline as if it was received
+ * Args: addr, value
+ */
 #[tauri::command(async)]
-pub fn simulate_feature_osc_input(vc_state: tauri::State<'_, vcore::VCStateMutex>, simulated_param_address: String, simulated_param_value: f32) {
-    trace!("simulate_feature_osc_input");
-    vcore::native_simulate_feature_osc_input(vc_state, simulated_param_address, simulated_param_value)
+pub fn osc_inject(
+    app_handle: tauri::AppHandle,
+    addr: String,
+    value: ModeProcessorInputType,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_inject");
+    inject::inject_osc_message(VCOscMessage { addr, value }, &app_handle)
 }
- *
+
+/*
+ * Injects a scripted sequence of OSC messages over time
+ * Args: steps (each waits delay_ms after the previous one)
  */
+#[tauri::command(async)]
+pub fn osc_inject_sequence(
+    app_handle: tauri::AppHandle,
+    steps: Vec<FeOSCInjectStep>,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_inject_sequence");
+    inject::start_injection_sequence(steps, app_handle)
+}
+
+#[tauri::command(async)]
+pub fn osc_inject_stop() -> Result<(), frontend::VCFeError> {
+    trace!("osc_inject_stop");
+    inject::stop_injection_sequence()
+}
 
 /*
  *
@@ -326,7 +423,9 @@ pub fn sync_offline_toys(
 ) -> Result<Vec<FeVCToy>, frontend::VCFeError> {
     trace!("sync_offline_toys");
     if let Some(toy_manager) = vc_state.0.lock().core_toy_manager.as_mut() {
-        Ok(toy_manager.sync_frontend(refresh_toys))
+        let offline_toys = toy_manager.sync_frontend(refresh_toys);
+        param_browser::refresh_parameter_consumers(toy_manager);
+        Ok(offline_toys)
     } else {
         Err(frontend::VCFeError::ToyManagerNotReady)
     }
@@ -355,3 +454,228 @@ pub fn osc_query_attempt_force_connect(
     trace!("osc_query_attempt_force_connect");
     native_osc_query_attempt_force(vc_state)
 }
+
+/*
+ * Rebuilds the OSCQuery parameter tree from the online toys
+ */
+#[tauri::command(async)]
+pub fn osc_query_populate(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_query_populate");
+    core::osc_query_force_populate(vc_state)
+}
+
+/*
+ * Finds VRChat over OSCQuery, sets the remote and reports the avatar parameters
+ * http skips mDNS discovery
+ */
+#[tauri::command(async)]
+pub fn vrchat_discover(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+    http: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<FeVRChatDiscovery, frontend::VCFeError> {
+    trace!("vrchat_discover");
+    core::native_vrchat_discover(
+        vc_state,
+        http,
+        timeout_ms.map_or(
+            vrchat_discovery::DEFAULT_DISCOVERY_TIMEOUT,
+            Duration::from_millis,
+        ),
+    )
+}
+
+/*
+ * Counters for received OSC packets and messages rejected at ingress
+ */
+#[tauri::command(async)]
+pub fn osc_ingress_metrics() -> FeOscIngressMetrics {
+    trace!("osc_ingress_metrics");
+    OSC_INGRESS_METRICS.to_fe()
+}
+
+/*
+ * OSC listeners that failed to bind on the last (re)bind
+ * Also emitted as fe_osc_bind_failures on every bind
+ */
+#[tauri::command(async)]
+pub fn osc_bind_failures(vc_state: tauri::State<'_, core::VCStateMutex>) -> Vec<FeOSCBindFailure> {
+    trace!("osc_bind_failures");
+    core::native_osc_bind_failures(vc_state)
+}
+
+/*
+ * Forwarding stats of the OSC repeater targets
+ */
+#[tauri::command(async)]
+pub fn osc_repeater_stats(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+) -> Vec<FeOSCRepeaterStats> {
+    trace!("osc_repeater_stats");
+    core::native_osc_repeater_stats(vc_state)
+}
+
+/*
+ * Battery samples and remaining time estimates of the toys seen this session
+ */
+#[tauri::command(async)]
+pub fn battery_history() -> Vec<FeBatteryHistory> {
+    trace!("battery_history");
+    core::native_battery_history()
+}
+
+#[tauri::command(async)]
+pub fn set_osc_repeater_target_enabled(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+    name: String,
+    enabled: bool,
+) -> Result<(), frontend::VCFeError> {
+    trace!("set_osc_repeater_target_enabled");
+    core::native_set_osc_repeater_target_enabled(vc_state, name, enabled)
+}
+
+/*
+ * Starts recording received OSC messages, optionally only addresses matching filter (glob)
+ * Returns the recording file name
+ */
+#[tauri::command(async)]
+pub fn osc_record_start(filter: Option<String>) -> Result<String, frontend::VCFeError> {
+    trace!("osc_record_start");
+    recording::start_recording(filter)
+}
+
+/*
+ * Stops recording OSC messages
+ * Returns the number of recorded messages
+ */
+#[tauri::command(async)]
+pub fn osc_record_stop() -> Result<u64, frontend::VCFeError> {
+    trace!("osc_record_stop");
+    recording::stop_recording()
+}
+
+#[tauri::command(async)]
+pub fn osc_list_recordings() -> Vec<String> {
+    trace!("osc_list_recordings");
+    recording::list_recordings()
+}
+
+/*
+ * Replays a recording into the toy pipeline at speed (1.0 = realtime)
+ * The outcome is emitted as fe_replay_result once the replay ends
+ */
+#[tauri::command(async)]
+pub fn osc_replay_start(
+    app_handle: tauri::AppHandle,
+    file_name: String,
+    speed: f64,
+    filter: Option<String>,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_replay_start");
+    recording::start_replay(&file_name, speed, filter, app_handle)
+}
+
+#[tauri::command(async)]
+pub fn osc_replay_stop() -> Result<(), frontend::VCFeError> {
+    trace!("osc_replay_stop");
+    recording::stop_replay()
+}
+
+/*
+ * Switches dry-run mode
+ * While enabled toy commands are logged and emitted as fe_dry_run_event instead of being sent to devices
+ */
+#[tauri::command(async)]
+pub fn set_dry_run(vc_state: tauri::State<'_, core::VCStateMutex>, enabled: bool) {
+    trace!("set_dry_run");
+    core::native_set_dry_run(vc_state, enabled)
+}
+
+#[tauri::command(async)]
+pub fn get_dry_run() -> bool {
+    trace!("get_dry_run");
+    dry_run_enabled()
+}
+
+/*
+ * Returns every OSC address received while listening
+ * The table is also emitted as fe_osc_parameters (throttled) while messages arrive
+ */
+#[tauri::command(async)]
+pub fn osc_parameters() -> Vec<FeOSCParameter> {
+    trace!("osc_parameters");
+    param_browser::osc_parameter_table()
+}
+
+#[tauri::command(async)]
+pub fn osc_parameters_clear() {
+    trace!("osc_parameters_clear");
+    param_browser::clear_osc_parameters()
+}
+
+/*
+ * Assigns a browsed OSC address to a toy feature with Raw processing
+ * Args: feature (toy_id is None for offline toys), addr
+ */
+#[tauri::command(async)]
+pub fn osc_parameter_assign(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+    app_handle: tauri::AppHandle,
+    feature: FeToyFeatureRef,
+    addr: String,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_parameter_assign");
+    core::native_osc_parameter_assign(vc_state, app_handle, feature, addr, ProcessingMode::Raw)
+}
+
+/*
+ * Watches incoming OSC and proposes the parameter that changed the most for a feature
+ * Args: feature, duration_ms (default 5000, clamped to 1-30s)
+ * The proposal is emitted as fe_learn_result
+ */
+#[tauri::command(async)]
+pub fn osc_learn_start(
+    app_handle: tauri::AppHandle,
+    feature: FeToyFeatureRef,
+    duration_ms: Option<u64>,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_learn_start");
+    learn::start_learning(
+        feature,
+        duration_ms.map_or(learn::DEFAULT_LEARN_DURATION, Duration::from_millis),
+        app_handle,
+    )
+}
+
+#[tauri::command(async)]
+pub fn osc_learn_cancel() -> Result<(), frontend::VCFeError> {
+    trace!("osc_learn_cancel");
+    learn::cancel_learning()
+}
+
+/*
+ * Saves the learned parameter into the feature's osc_parameters
+ */
+#[tauri::command(async)]
+pub fn osc_learn_confirm(
+    vc_state: tauri::State<'_, core::VCStateMutex>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), frontend::VCFeError> {
+    trace!("osc_learn_confirm");
+    let proposal = learn::take_learn_proposal()?;
+    core::native_osc_parameter_assign(
+        vc_state,
+        app_handle,
+        proposal.feature,
+        proposal.addr,
+        proposal.processing_mode,
+    )
+}
+
+#[tauri::command(async)]
+pub fn osc_learn_discard() {
+    trace!("osc_learn_discard");
+    learn::discard_learn_proposal()
+}
@@ -2,12 +2,19 @@
  * Frontend type binding generation
  */
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use ts_rs::TS;
 
+use crate::config::TelemetryConfig;
+use crate::osc::{
+    chatbox::ChatboxConfig, repeater::OSCAddressRewrite, tcp::OSCTransport,
+    telemetry::TelemetryField,
+};
 use crate::toy_handling::{
+    battery::{BatteryAlertConfig, BatteryAlertLevel},
     input_processor::penetration_systems::PenetrationSystemType,
-    toyops::{ProcessingMode, VCFeatureType},
-    ToyPower,
+    toyops::{IntValueMapping, ProcessingMode, VCFeatureType},
+    ModeProcessorInputType, ToyPower,
 };
 
 use super::ToBackend;
@@ -22,15 +29,301 @@ pub struct FeVibeCheckConfig {
     pub lc_override: Option<String>,
     pub show_toy_advanced: bool,
     pub show_feature_advanced: bool,
-    pub messages_per_second: u64
+    pub messages_per_second: u64,
+    #[serde(default)]
+    pub telemetry: FeTelemetryConfig,
+    #[serde(default)]
+    pub battery_alerts: BatteryAlertConfig,
+    #[serde(default)]
+    pub chatbox: ChatboxConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct FeTelemetryConfig {
+    pub interval_secs: u64,
+    pub targets: Vec<FeTelemetryTarget>,
+    pub address_template: String,
+    pub fields: Vec<TelemetryField>,
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Default for FeTelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig::default().to_fe()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct FeTelemetryTarget {
+    pub address: String,
+    #[serde(default)]
+    pub transport: OSCTransport,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, TS)]
 #[ts(export)]
 pub struct FeOSCNetworking {
-    pub bind: String,
+    pub bind: Vec<FeOSCListener>,
     pub remote: String,
+    #[serde(default)]
+    pub remote_transport: OSCTransport,
     pub osc_query_enabled: bool,
+    #[serde(default)]
+    pub repeater: Vec<FeOSCRepeaterTarget>,
+    #[serde(default = "crate::config::default_feedback_rate")]
+    pub feedback_rate: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCListener {
+    pub name: String,
+    pub address: String,
+    pub allowlist: Vec<String>,
+    #[serde(default)]
+    pub api_secret: Option<String>,
+    pub rewrite: Option<OSCAddressRewrite>,
+    #[serde(default)]
+    pub transport: OSCTransport,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCRepeaterTarget {
+    pub name: String,
+    pub address: String,
+    pub enabled: bool,
+    pub filter: Option<String>,
+    pub rewrite: Option<OSCAddressRewrite>,
+}
+
+/*
+ * An OSC listener that could not be bound
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCBindFailure {
+    pub name: String,
+    pub address: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCRepeaterStats {
+    pub name: String,
+    pub enabled: bool,
+    pub forwarded_packets: u64,
+    pub forwarded_bytes: u64,
+    pub filtered_packets: u64,
+    pub send_errors: u64,
+}
+
+/*
+ * One message of an injected OSC sequence
+ * delay_ms is the time to wait after the previous step
+ */
+#[derive(Deserialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct FeOSCInjectStep {
+    pub delay_ms: u64,
+    pub addr: String,
+    pub value: ModeProcessorInputType,
+}
+
+/*
+ * Identifies a toy feature for parameter assignment
+ * toy_id is None for offline toys which are looked up by name
+ */
+#[derive(Deserialize, Serialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct FeToyFeatureRef {
+    pub toy_id: Option<u32>,
+    pub toy_name: String,
+    pub feature_index: u32,
+    pub feature_type: FeVCFeatureType,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeLearnProposal {
+    pub addr: String,
+    pub processing_mode: FeProcessingMode,
+}
+
+/*
+ * Result of a learn session
+ * proposal is None when no parameter changed
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeLearnResult {
+    pub feature: FeToyFeatureRef,
+    pub proposal: Option<FeLearnProposal>,
+}
+
+/*
+ * Emitted as fe_replay_result when a replay ends
+ * skipped counts toy messages dropped because VibeCheck was not listening
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeReplayResult {
+    pub file_name: String,
+    pub replayed: u64,
+    pub skipped: u64,
+}
+
+/*
+ * What was discovered from VRChat's OSCQuery service
+ * missing_parameters are assigned toy parameters the current avatar doesn't have
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeVRChatDiscovery {
+    pub name: Option<String>,
+    pub http: String,
+    pub osc_remote: String,
+    pub avatar_id: Option<String>,
+    pub parameters: Vec<FeAvatarParameter>,
+    pub missing_parameters: Vec<FeMissingParameter>,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeAvatarParameter {
+    pub addr: String,
+    // OSC type tag (f, i, T)
+    pub osc_type: String,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeMissingParameter {
+    pub feature: FeToyFeatureRef,
+    pub parameter: String,
+}
+
+/*
+ * An avatar's OSC config as written by VRChat
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeAvatarOSCConfig {
+    pub user_id: String,
+    pub avatar_id: String,
+    pub avatar_name: String,
+    pub parameters: Vec<FeAvatarParameter>,
+    pub suggestions: Vec<FeAvatarToySuggestion>,
+}
+
+/*
+ * A suggested toy setup for an avatar
+ * object is the SPS or TPS object (eg. Orf/Vagina) the suggestion was made from
+ */
+#[derive(Deserialize, Serialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct FeAvatarToySuggestion {
+    pub anatomy: FeVCToyAnatomy,
+    pub pen_system_type: PenetrationSystemType,
+    pub object: Option<String>,
+    pub parameters: Vec<String>,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCConfigUser {
+    pub user_id: String,
+    pub avatars: Vec<FeOSCConfigAvatar>,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCConfigAvatar {
+    pub avatar_id: String,
+    pub avatar_name: Option<String>,
+}
+
+/*
+ * Result of clearing OSC avatar configs
+ * removed are the cleared files relative to the OSC folder, backup is None when nothing was removed
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCConfigClearResult {
+    pub backup: Option<String>,
+    pub removed: Vec<String>,
+}
+
+/*
+ * A row of the live OSC parameter browser
+ * Times are milliseconds since the UNIX epoch
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCParameter {
+    pub addr: String,
+    pub value: ModeProcessorInputType,
+    pub first_seen_ms: u64,
+    pub last_seen_ms: u64,
+    pub updates: u64,
+    pub rate_hz: f64,
+    pub consumers: Vec<FeOSCParameterConsumer>,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeOSCParameterConsumer {
+    pub toy_name: String,
+    pub toy_id: Option<u32>,
+    pub feature_index: u32,
+    pub feature_type: FeVCFeatureType,
+    // Consumed by the feature's penetration system instead of an assigned parameter
+    pub input_processor: bool,
+}
+
+/*
+ * A toy's battery alert level changed
+ */
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeBatteryEvent {
+    pub toy_id: u32,
+    pub toy_name: String,
+    pub sub_id: u8,
+    pub level: f64,
+    pub alert: BatteryAlertLevel,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeBatteryHistory {
+    pub toy_name: String,
+    pub sub_id: u8,
+    pub alert: BatteryAlertLevel,
+    pub estimated_remaining_secs: Option<u64>,
+    pub samples: Vec<FeBatterySample>,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeBatterySample {
+    pub unix_ms: u64,
+    pub level: f64,
+}
+
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+pub struct FeDryRunCommand {
+    pub timestamp_ms: u64,
+    pub toy_name: String,
+    pub toy_id: Option<u32>,
+    pub feature_index: u32,
+    pub feature_type: FeVCFeatureType,
+    pub actuator: String,
+    pub level: f64,
 }
 
 #[derive(Serialize, Clone, TS)]
@@ -47,7 +340,7 @@ pub enum FeToyEvent {
 #[ts(export)]
 pub enum FeScanEvent {
     Start,
-    //Stop
+    Stop,
 }
 
 #[derive(Serialize, Clone, TS)]
@@ -63,6 +356,7 @@ pub enum FeStateEvent {
 pub enum FeCoreEvent {
     Scan(FeScanEvent),
     State(FeStateEvent),
+    Battery(FeBatteryEvent),
 }
 
 #[derive(Deserialize, Clone, TS)]
@@ -157,6 +451,8 @@ impl ToBackend<ProcessingMode> for FeProcessingMode {
 pub struct FeToyParameter {
     pub parameter: String,
     pub processing_mode: FeProcessingMode,
+    #[serde(default)]
+    pub int_values: Vec<IntValueMapping>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, TS)]
@@ -189,6 +485,8 @@ pub struct FeVCToyFeature {
     pub feature_levels: FeLevelTweaks,
     pub smooth_enabled: bool,
     pub rate_enabled: bool,
+    #[serde(default)]
+    pub output_parameter: Option<String>,
 }
 
 #[derive(Debug, Deserialize, TS)]
@@ -235,3 +533,17 @@ impl PartialEq<VCFeatureType> for FeVCFeatureType {
         *self as u32 == *other as u32
     }
 }
+
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(export)]
+pub struct FeOscIngressMetrics {
+    pub packets: u64,
+    pub decode_errors: u64,
+    pub accepted: u64,
+    pub rejected_address: u64,
+    pub rejected_no_arguments: u64,
+    pub rejected_type: u64,
+    pub rejected_non_finite: u64,
+    pub rejected_source: u64,
+    pub rejected_api_secret: u64,
+}
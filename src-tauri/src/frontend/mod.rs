@@ -3,6 +3,7 @@
 pub mod error;
 pub mod frontend_native;
 pub mod frontend_types;
+pub mod validation;
 
 pub trait ToBackend<B> {
     type OutputType;
@@ -0,0 +1,315 @@
+/*
+ * Validation of frontend toy types
+ * Every invalid field is collected with its path so the frontend can highlight exactly what is wrong.
+ */
+use std::collections::HashSet;
+
+use crate::{
+    toy_handling::toyops::{
+        CONSTANT_LEVEL_RANGE, LEVEL_RANGE, LINEAR_POSITION_SPEED_RANGE, RATE_TUNE_RANGE,
+        SMOOTH_RATE_RANGE,
+    },
+    vcore::vcerror::frontend::{FieldError, FieldErrorKind},
+};
+
+use super::frontend_types::{FeLevelTweaks, FeToyParameter, FeVCToy, FeVCToyFeature};
+
+pub trait Validate {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>);
+}
+
+/*
+ * Validates a frontend type from the root
+ * Returns all field errors found
+ */
+pub fn validate<V: Validate + ?Sized>(value: &V) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+    value.validate("", &mut errors);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn join_path(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+fn check_range(path: String, value: f64, (min, max): (f64, f64), errors: &mut Vec<FieldError>) {
+    // NaN also fails this check
+    if !(min..=max).contains(&value) {
+        errors.push(FieldError {
+            path,
+            kind: FieldErrorKind::OutOfRange { min, max },
+        });
+    }
+}
+
+fn check_unit_range(path: String, value: f64, errors: &mut Vec<FieldError>) {
+    check_range(path, value, LEVEL_RANGE, errors);
+}
+
+impl Validate for FeVCToy {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        self.features.validate(&join_path(path, "features"), errors);
+    }
+}
+
+impl Validate for [FeVCToyFeature] {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        for (i, feature) in self.iter().enumerate() {
+            feature.validate(&format!("{}[{}]", path, i), errors);
+        }
+    }
+}
+
+impl Validate for FeVCToyFeature {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        self.feature_levels
+            .validate(&join_path(path, "feature_levels"), errors);
+        self.osc_parameters
+            .validate(&join_path(path, "osc_parameters"), errors);
+
+        // Empty output parameters are cleared rather than rejected
+        if let Some(output_parameter) = self.output_parameter.as_ref() {
+            if !output_parameter.trim().is_empty() && !output_parameter.starts_with('/') {
+                errors.push(FieldError {
+                    path: join_path(path, "output_parameter"),
+                    kind: FieldErrorKind::MissingLeadingSlash,
+                });
+            }
+        }
+    }
+}
+
+impl Validate for [FeToyParameter] {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        let mut seen = HashSet::new();
+
+        for (i, toy_param) in self.iter().enumerate() {
+            let param_path = format!("{}[{}].parameter", path, i);
+
+            if toy_param.parameter.trim().is_empty() {
+                errors.push(FieldError {
+                    path: param_path,
+                    kind: FieldErrorKind::EmptyAddress,
+                });
+                continue;
+            }
+
+            if !toy_param.parameter.starts_with('/') {
+                errors.push(FieldError {
+                    path: param_path.clone(),
+                    kind: FieldErrorKind::MissingLeadingSlash,
+                });
+            }
+
+            if !seen.insert(toy_param.parameter.as_str()) {
+                errors.push(FieldError {
+                    path: param_path,
+                    kind: FieldErrorKind::DuplicateAddress,
+                });
+            }
+
+            let mut seen_values = HashSet::new();
+            for (j, mapping) in toy_param.int_values.iter().enumerate() {
+                let mapping_path = format!("{}[{}].int_values[{}]", path, i, j);
+                check_unit_range(join_path(&mapping_path, "level"), mapping.level, errors);
+
+                if !seen_values.insert(mapping.value) {
+                    errors.push(FieldError {
+                        path: join_path(&mapping_path, "value"),
+                        kind: FieldErrorKind::DuplicateIntValue,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Validate for FeLevelTweaks {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        check_unit_range(join_path(path, "minimum_level"), self.minimum_level, errors);
+        check_unit_range(join_path(path, "maximum_level"), self.maximum_level, errors);
+        check_unit_range(join_path(path, "idle_level"), self.idle_level, errors);
+        check_range(
+            join_path(path, "smooth_rate"),
+            self.smooth_rate,
+            SMOOTH_RATE_RANGE,
+            errors,
+        );
+        check_range(
+            join_path(path, "linear_position_speed"),
+            self.linear_position_speed as f64,
+            (
+                LINEAR_POSITION_SPEED_RANGE.0 as f64,
+                LINEAR_POSITION_SPEED_RANGE.1 as f64,
+            ),
+            errors,
+        );
+        check_range(
+            join_path(path, "rate_tune"),
+            self.rate_tune,
+            RATE_TUNE_RANGE,
+            errors,
+        );
+        check_range(
+            join_path(path, "constant_level"),
+            self.constant_level,
+            CONSTANT_LEVEL_RANGE,
+            errors,
+        );
+
+        if self.minimum_level > self.maximum_level {
+            errors.push(FieldError {
+                path: join_path(path, "minimum_level"),
+                kind: FieldErrorKind::MinimumAboveMaximum,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frontend::frontend_types::FeProcessingMode,
+        toy_handling::toyops::{IntValueMapping, LevelTweaks},
+    };
+
+    fn levels() -> FeLevelTweaks {
+        LevelTweaks::default().to_fe()
+    }
+
+    fn param(parameter: &str) -> FeToyParameter {
+        FeToyParameter {
+            parameter: parameter.to_string(),
+            processing_mode: FeProcessingMode::Raw,
+            int_values: Vec::new(),
+        }
+    }
+
+    fn error_paths<V: Validate + ?Sized>(value: &V) -> Vec<String> {
+        match validate(value) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|e| e.path).collect(),
+        }
+    }
+
+    #[test]
+    fn default_levels_are_valid() {
+        assert!(validate(&levels()).is_ok());
+    }
+
+    #[test]
+    fn levels_accept_the_slider_ranges() {
+        let mut lt = levels();
+        lt.smooth_rate = 20.0;
+        lt.linear_position_speed = 1000;
+        lt.rate_tune = 2.0;
+        lt.constant_level = 0.01;
+        assert!(validate(&lt).is_ok());
+
+        lt.smooth_rate = 1.0;
+        lt.linear_position_speed = 10;
+        lt.rate_tune = 0.1;
+        lt.constant_level = 1.0;
+        assert!(validate(&lt).is_ok());
+    }
+
+    #[test]
+    fn levels_reject_out_of_range_values() {
+        let mut lt = levels();
+        lt.maximum_level = 1.5;
+        lt.idle_level = -0.1;
+        lt.smooth_rate = 0.0;
+        lt.linear_position_speed = 5;
+        lt.rate_tune = 2.5;
+        lt.constant_level = f64::NAN;
+        assert_eq!(
+            error_paths(&lt),
+            [
+                "maximum_level",
+                "idle_level",
+                "smooth_rate",
+                "linear_position_speed",
+                "rate_tune",
+                "constant_level",
+            ]
+        );
+    }
+
+    #[test]
+    fn levels_reject_minimum_above_maximum() {
+        let mut lt = levels();
+        lt.minimum_level = 0.8;
+        lt.maximum_level = 0.2;
+        let errors = validate(&lt).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "minimum_level");
+        assert_eq!(errors[0].kind, FieldErrorKind::MinimumAboveMaximum);
+    }
+
+    #[test]
+    fn parameters_reject_bad_addresses() {
+        let params = [param("/a"), param("b"), param("  "), param("/a")];
+        let errors = validate(params.as_slice()).unwrap_err();
+        let kinds: Vec<(String, FieldErrorKind)> =
+            errors.into_iter().map(|e| (e.path, e.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    "[1].parameter".to_string(),
+                    FieldErrorKind::MissingLeadingSlash
+                ),
+                ("[2].parameter".to_string(), FieldErrorKind::EmptyAddress),
+                (
+                    "[3].parameter".to_string(),
+                    FieldErrorKind::DuplicateAddress
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parameters_check_int_value_tables() {
+        let mut p = param("/a");
+        p.int_values = vec![
+            IntValueMapping {
+                value: 1,
+                level: 0.5,
+            },
+            IntValueMapping {
+                value: 1,
+                level: 1.2,
+            },
+        ];
+        assert_eq!(
+            error_paths([p].as_slice()),
+            ["[0].int_values[1].level", "[0].int_values[1].value"]
+        );
+    }
+
+    #[test]
+    fn clamped_levels_pass_validation() {
+        let mut lt = LevelTweaks {
+            minimum_level: 0.9,
+            maximum_level: 0.4,
+            idle_level: f64::NAN,
+            smooth_rate: 50.0,
+            linear_position_speed: 0,
+            rate_tune: 3.0,
+            constant_level: 0.0,
+        };
+        assert!(lt.clamp_to_ranges());
+        assert!(validate(&lt.to_fe()).is_ok());
+        assert!(!lt.clamp_to_ranges());
+    }
+}
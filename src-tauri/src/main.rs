@@ -98,12 +98,40 @@ fn main() {
             frontend_native::alter_toy,
             frontend_native::open_default_browser,
             frontend_native::clear_osc_config,
+            frontend_native::osc_config_users,
+            frontend_native::osc_config_backups,
+            frontend_native::osc_config_restore,
+            frontend_native::avatar_configs,
+            frontend_native::avatar_suggestion_accept,
             frontend_native::simulate_device_feature,
             frontend_native::sync_offline_toys,
             frontend_native::osc_query_start,
             frontend_native::osc_query_stop,
             frontend_native::osc_query_attempt_force_connect,
-            //frontend_native::simulate_feature_osc_input,
+            frontend_native::osc_query_populate,
+            frontend_native::vrchat_discover,
+            frontend_native::osc_ingress_metrics,
+            frontend_native::osc_repeater_stats,
+            frontend_native::osc_bind_failures,
+            frontend_native::set_osc_repeater_target_enabled,
+            frontend_native::battery_history,
+            frontend_native::osc_record_start,
+            frontend_native::osc_record_stop,
+            frontend_native::osc_list_recordings,
+            frontend_native::osc_replay_start,
+            frontend_native::osc_replay_stop,
+            frontend_native::set_dry_run,
+            frontend_native::get_dry_run,
+            frontend_native::osc_inject,
+            frontend_native::osc_inject_sequence,
+            frontend_native::osc_inject_stop,
+            frontend_native::osc_parameters,
+            frontend_native::osc_parameters_clear,
+            frontend_native::osc_parameter_assign,
+            frontend_native::osc_learn_start,
+            frontend_native::osc_learn_cancel,
+            frontend_native::osc_learn_confirm,
+            frontend_native::osc_learn_discard,
         ])
         .build(tauri::generate_context!())
         .expect("Failed to generate Tauri context");
@@ -127,9 +155,11 @@ fn main() {
         trace!("Started TMH");
         vc_state.init_ceh();
         trace!("Started CEH");
-        vc_state.start_disabled_listener();
-        trace!("Started DOL");
     }
+    if !vcore::core::ensure_osc_listeners(&vibecheck_state_pointer) {
+        warn!("No OSC listener could be bound");
+    }
+    trace!("OSC listeners bound");
 
     app.run(|_app_handle, event| {
         match event {
@@ -0,0 +1,555 @@
+/*
+ * VRChat OSC avatar config clearing
+ * Cleared configs are moved into a timestamped backup in the config dir instead of being deleted so they can be restored.
+ */
+use log::{info, warn};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    frontend::frontend_types::FeOSCConfigClearResult, util::fs::get_config_dir,
+    vcore::vcerror::frontend::VCFeError,
+};
+
+use super::avatar_config::user_dirs;
+
+pub fn backups_dir() -> String {
+    format!("{}\\OSCBackups", get_config_dir())
+}
+
+/*
+ * Backup names are generated by VibeCheck, anything that could leave the backups dir is refused
+ */
+fn backup_path(backups: &Path, name: &str) -> Result<PathBuf, VCFeError> {
+    if !is_plain_name(name) || !name.starts_with("osc-") {
+        return Err(VCFeError::InvalidOSCConfigBackup);
+    }
+    Ok(backups.join(name))
+}
+
+/*
+ * Creates a new empty backup, a suffix is added if the name is taken
+ */
+fn create_backup_dir(backups: &Path) -> Result<(String, PathBuf), VCFeError> {
+    fs::create_dir_all(backups)
+        .map_err(|e| VCFeError::OSCConfigMoveFailure(format!("{}: {}", backups.display(), e)))?;
+
+    let unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    for attempt in 0..100 {
+        let backup_name = match attempt {
+            0 => format!("osc-{}", unix_ms),
+            n => format!("osc-{}-{}", unix_ms, n),
+        };
+        let backup = backup_path(backups, &backup_name)?;
+        match fs::create_dir(&backup) {
+            Ok(()) => return Ok((backup_name, backup)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(VCFeError::OSCConfigMoveFailure(format!(
+                    "{}: {}",
+                    backup_name, e
+                )))
+            }
+        }
+    }
+    Err(VCFeError::OSCConfigMoveFailure(
+        "No free backup name".to_string(),
+    ))
+}
+
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && !name.contains("..")
+}
+
+/*
+ * Collects the files below dir as paths relative to base
+ */
+fn collect_files(dir: &Path, base: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, base, out)?;
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            out.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/*
+ * Moves a file, falling back to copy and remove when the rename crosses drives
+ */
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+/*
+ * Removes dir and its subdirectories once their files were moved
+ * Fails instead of deleting a file that was written after the files were collected
+ */
+fn remove_empty_dirs(dir: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            remove_empty_dirs(&path)?;
+        }
+    }
+    fs::remove_dir(dir)
+}
+
+/*
+ * Undoes (from, to) moves in reverse order
+ * Returns the number of files that could not be moved back
+ */
+fn undo_moves(moves: &[(PathBuf, PathBuf)]) -> usize {
+    let mut stranded = 0;
+    for (from, to) in moves.iter().rev() {
+        if let Err(e) = move_file(to, from) {
+            warn!("Failed to move {} back: {}", to.display(), e);
+            stranded += 1;
+        }
+    }
+    stranded
+}
+
+/*
+ * Moves the files of a failed clear back into osc_dir so a clear either fully happens or not at all
+ * Files that can't be moved back stay in the backup, the error names it so it can be restored
+ */
+fn roll_back_clear(
+    backup: &Path,
+    backup_name: &str,
+    moves: &[(PathBuf, PathBuf)],
+    error: String,
+) -> VCFeError {
+    warn!(
+        "Clearing OSC configs failed ({}), restoring {} files",
+        error,
+        moves.len()
+    );
+
+    let stranded = undo_moves(moves);
+    if stranded == 0 {
+        if let Err(e) = remove_empty_dirs(backup) {
+            warn!("Failed to remove backup {}: {}", backup_name, e);
+        }
+        VCFeError::OSCConfigMoveFailure(format!("{}, nothing was cleared", error))
+    } else {
+        VCFeError::OSCConfigMoveFailure(format!(
+            "{}, {} files remain in backup {}",
+            error, stranded, backup_name
+        ))
+    }
+}
+
+/*
+ * Clears the OSC configs of an avatar, a user or every user when both are None
+ * The removed files are moved into a new backup and reported relative to osc_dir
+ * If a file can't be moved the files moved so far are put back
+ */
+pub fn clear_osc_configs(
+    osc_dir: &Path,
+    backups: &Path,
+    user_id: Option<&str>,
+    avatar_id: Option<&str>,
+) -> Result<FeOSCConfigClearResult, VCFeError> {
+    if user_id.is_some_and(|u| !is_plain_name(u) || !u.starts_with("usr_"))
+        || avatar_id.is_some_and(|a| !is_plain_name(a))
+    {
+        return Err(VCFeError::InvalidOSCConfigTarget);
+    }
+
+    // (Directory or file to remove, removes the directory itself afterwards)
+    let targets: Vec<(PathBuf, bool)> = match (user_id, avatar_id) {
+        (Some(user_id), Some(avatar_id)) => {
+            let file = osc_dir
+                .join(user_id)
+                .join("Avatars")
+                .join(format!("{}.json", avatar_id));
+            if !file.is_file() {
+                return Err(VCFeError::OSCConfigNotFound);
+            }
+            vec![(file, false)]
+        }
+        (Some(user_id), None) => {
+            let dir = osc_dir.join(user_id);
+            if !dir.is_dir() {
+                return Err(VCFeError::OSCConfigNotFound);
+            }
+            vec![(dir, true)]
+        }
+        (None, None) => user_dirs(osc_dir)
+            .map_err(|_| VCFeError::OSCConfigNotFound)?
+            .into_iter()
+            .map(|(_, dir)| (dir, true))
+            .collect(),
+        (None, Some(_)) => return Err(VCFeError::InvalidOSCConfigTarget),
+    };
+
+    let mut files = Vec::new();
+    for (target, _) in &targets {
+        if target.is_dir() {
+            collect_files(target, osc_dir, &mut files).map_err(|e| {
+                VCFeError::OSCConfigMoveFailure(format!("{}: {}", target.display(), e))
+            })?;
+        } else if let Ok(relative) = target.strip_prefix(osc_dir) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    files.sort();
+
+    let mut result = FeOSCConfigClearResult {
+        backup: None,
+        removed: Vec::new(),
+    };
+
+    if !files.is_empty() {
+        let (backup_name, backup) = create_backup_dir(backups)?;
+        info!("Backing up cleared OSC configs to {}", backup.display());
+
+        let mut moves = Vec::new();
+        for file in &files {
+            let (from, to) = (osc_dir.join(file), backup.join(file));
+            if let Err(e) = move_file(&from, &to) {
+                let error = format!("{}: {}", file.display(), e);
+                return Err(roll_back_clear(&backup, &backup_name, &moves, error));
+            }
+            moves.push((from, to));
+        }
+        result.removed = files.iter().map(|f| f.display().to_string()).collect();
+        result.backup = Some(backup_name);
+    }
+
+    for (target, remove_dir) in targets {
+        if remove_dir {
+            if let Err(e) = remove_empty_dirs(&target) {
+                warn!("Failed to remove {}: {}", target.display(), e);
+            }
+        }
+    }
+
+    info!("Cleared {} OSC config files", result.removed.len());
+    Ok(result)
+}
+
+pub fn list_osc_config_backups(backups: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(backups) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("osc-"))
+        .collect();
+    backups.sort();
+    backups
+}
+
+/*
+ * Puts the files of a failed restore back into the backup and the files they replaced back into osc_dir
+ */
+fn roll_back_restore(
+    displaced: &Path,
+    displaced_name: &str,
+    name: &str,
+    moves: &[(PathBuf, PathBuf)],
+    error: String,
+) -> VCFeError {
+    warn!(
+        "Restoring OSC config backup {} failed ({}), undoing {} moves",
+        name,
+        error,
+        moves.len()
+    );
+
+    let stranded = undo_moves(moves);
+    if stranded == 0 {
+        if let Err(e) = remove_empty_dirs(displaced) {
+            warn!("Failed to remove backup {}: {}", displaced_name, e);
+        }
+        VCFeError::OSCConfigMoveFailure(format!("{}, nothing was restored", error))
+    } else {
+        VCFeError::OSCConfigMoveFailure(format!(
+            "{}, {} files could not be moved back, check backups {} and {}",
+            error, stranded, name, displaced_name
+        ))
+    }
+}
+
+/*
+ * Moves the files of a backup back into osc_dir and removes the backup
+ * Files VRChat has written since the backup are overwritten
+ * If a file can't be moved every file is put back where it was, replaced ones included
+ * Returns the restored files relative to osc_dir
+ */
+pub fn restore_osc_config_backup(
+    osc_dir: &Path,
+    backups: &Path,
+    name: &str,
+) -> Result<Vec<String>, VCFeError> {
+    let backup = backup_path(backups, name)?;
+    if !backup.is_dir() {
+        return Err(VCFeError::OSCConfigBackupNotFound);
+    }
+
+    let mut files = Vec::new();
+    collect_files(&backup, &backup, &mut files)
+        .map_err(|e| VCFeError::OSCConfigMoveFailure(format!("{}: {}", name, e)))?;
+    files.sort();
+
+    // Replaced files are kept aside until every file is restored
+    let (displaced_name, displaced) = create_backup_dir(backups)?;
+
+    let mut moves = Vec::new();
+    for file in &files {
+        let target = osc_dir.join(file);
+        let mut pending = vec![(backup.join(file), target.clone())];
+        if target.is_file() {
+            pending.insert(0, (target, displaced.join(file)));
+        }
+
+        for (from, to) in pending {
+            if let Err(e) = move_file(&from, &to) {
+                let error = format!("{}: {}", file.display(), e);
+                return Err(roll_back_restore(
+                    &displaced,
+                    &displaced_name,
+                    name,
+                    &moves,
+                    error,
+                ));
+            }
+            moves.push((from, to));
+        }
+    }
+
+    if let Err(e) = fs::remove_dir_all(&displaced) {
+        warn!("Failed to remove replaced files {}: {}", displaced_name, e);
+    }
+    if let Err(e) = remove_empty_dirs(&backup) {
+        warn!("Failed to remove restored backup {}: {}", name, e);
+    }
+    info!("Restored {} OSC config files from {}", files.len(), name);
+    Ok(files.iter().map(|f| f.display().to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // An OSC dir and a backups dir below one temp dir
+    struct BackupFixture {
+        root: PathBuf,
+        osc_dir: PathBuf,
+        backups: PathBuf,
+    }
+
+    impl BackupFixture {
+        fn new() -> Self {
+            static NEXT: AtomicU32 = AtomicU32::new(0);
+            let root = std::env::temp_dir().join(format!(
+                "vibecheck-avatar-backup-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&root);
+            let osc_dir = root.join("OSC");
+            let backups = root.join("OSCBackups");
+            fs::create_dir_all(&osc_dir).unwrap();
+            Self {
+                root,
+                osc_dir,
+                backups,
+            }
+        }
+
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.osc_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn read(&self, path: &str) -> Option<String> {
+            fs::read_to_string(self.osc_dir.join(path)).ok()
+        }
+    }
+
+    impl Drop for BackupFixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn clear(
+        fixture: &BackupFixture,
+        user_id: Option<&str>,
+        avatar_id: Option<&str>,
+    ) -> FeOSCConfigClearResult {
+        clear_osc_configs(&fixture.osc_dir, &fixture.backups, user_id, avatar_id)
+            .unwrap_or_else(|_| panic!("clear failed"))
+    }
+
+    fn relative(path: &str) -> String {
+        path.split('/').collect::<PathBuf>().display().to_string()
+    }
+
+    #[test]
+    fn clears_into_a_backup() {
+        let fixture = BackupFixture::new();
+        fixture.write("usr_1/Avatars/avtr_a.json", "a");
+        fixture.write("usr_1/Avatars/avtr_b.json", "b");
+        fixture.write("usr_2/Avatars/avtr_c.json", "c");
+
+        let result = clear(&fixture, Some("usr_1"), Some("avtr_a"));
+        assert_eq!(result.removed, [relative("usr_1/Avatars/avtr_a.json")]);
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_a.json"), None);
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_b.json").unwrap(), "b");
+
+        let backup = fixture.backups.join(result.backup.unwrap());
+        assert_eq!(
+            fs::read_to_string(backup.join("usr_1/Avatars/avtr_a.json")).unwrap(),
+            "a"
+        );
+
+        let result = clear(&fixture, None, None);
+        assert_eq!(
+            result.removed,
+            [
+                relative("usr_1/Avatars/avtr_b.json"),
+                relative("usr_2/Avatars/avtr_c.json")
+            ]
+        );
+        assert!(!fixture.osc_dir.join("usr_1").exists());
+        assert!(!fixture.osc_dir.join("usr_2").exists());
+
+        // Every clear gets its own backup
+        assert_eq!(list_osc_config_backups(&fixture.backups).len(), 2);
+    }
+
+    #[test]
+    fn refuses_invalid_targets() {
+        let fixture = BackupFixture::new();
+        fixture.write("usr_1/Avatars/avtr_a.json", "a");
+
+        for (user_id, avatar_id) in [
+            (Some("../usr_1"), None),
+            (Some("usr_1"), Some("..\\avtr_a")),
+            (Some("not_a_user"), None),
+            (None, Some("avtr_a")),
+        ] {
+            assert!(matches!(
+                clear_osc_configs(&fixture.osc_dir, &fixture.backups, user_id, avatar_id),
+                Err(VCFeError::InvalidOSCConfigTarget)
+            ));
+        }
+        assert!(matches!(
+            clear_osc_configs(&fixture.osc_dir, &fixture.backups, Some("usr_2"), None),
+            Err(VCFeError::OSCConfigNotFound)
+        ));
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_a.json").unwrap(), "a");
+    }
+
+    #[test]
+    fn rolls_back_a_failed_clear() {
+        let fixture = BackupFixture::new();
+        fixture.write("usr_1/Avatars/avtr_a.json", "a");
+        fixture.write("usr_1/Avatars/avtr_b.json", "b");
+
+        // The state after avtr_a was moved and avtr_b failed
+        let Ok((backup_name, backup)) = create_backup_dir(&fixture.backups) else {
+            panic!("backup dir not created");
+        };
+        let file = PathBuf::from("usr_1/Avatars/avtr_a.json");
+        let moves = vec![(fixture.osc_dir.join(&file), backup.join(&file))];
+        move_file(&moves[0].0, &moves[0].1).unwrap();
+
+        let error = roll_back_clear(&backup, &backup_name, &moves, "avtr_b".to_string());
+        match error {
+            VCFeError::OSCConfigMoveFailure(message) => {
+                assert_eq!(message, "avtr_b, nothing was cleared")
+            }
+            _ => panic!("unexpected error"),
+        }
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_a.json").unwrap(), "a");
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_b.json").unwrap(), "b");
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn restores_a_backup() {
+        let fixture = BackupFixture::new();
+        fixture.write("usr_1/Avatars/avtr_a.json", "a");
+        fixture.write("usr_1/Avatars/avtr_b.json", "b");
+        let backup_name = clear(&fixture, Some("usr_1"), None).backup.unwrap();
+
+        // VRChat rewrote one of the configs since
+        fixture.write("usr_1/Avatars/avtr_a.json", "new a");
+
+        let restored = restore_osc_config_backup(&fixture.osc_dir, &fixture.backups, &backup_name)
+            .unwrap_or_else(|_| panic!("restore failed"));
+        assert_eq!(
+            restored,
+            [
+                relative("usr_1/Avatars/avtr_a.json"),
+                relative("usr_1/Avatars/avtr_b.json")
+            ]
+        );
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_a.json").unwrap(), "a");
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_b.json").unwrap(), "b");
+        assert!(list_osc_config_backups(&fixture.backups).is_empty());
+
+        assert!(matches!(
+            restore_osc_config_backup(&fixture.osc_dir, &fixture.backups, &backup_name),
+            Err(VCFeError::OSCConfigBackupNotFound)
+        ));
+        assert!(matches!(
+            restore_osc_config_backup(&fixture.osc_dir, &fixture.backups, "osc-1/../.."),
+            Err(VCFeError::InvalidOSCConfigBackup)
+        ));
+    }
+
+    #[test]
+    fn rolls_back_a_failed_restore() {
+        let fixture = BackupFixture::new();
+        fixture.write("usr_1/Avatars/avtr_a.json", "a");
+        fixture.write("usr_2/Avatars/avtr_b.json", "b");
+        let backup_name = clear(&fixture, None, None).backup.unwrap();
+
+        // avtr_a replaces a newer config, avtr_b can't be restored below a file
+        fixture.write("usr_1/Avatars/avtr_a.json", "new a");
+        fixture.write("usr_2", "not a dir");
+
+        assert!(matches!(
+            restore_osc_config_backup(&fixture.osc_dir, &fixture.backups, &backup_name),
+            Err(VCFeError::OSCConfigMoveFailure(_))
+        ));
+        assert_eq!(fixture.read("usr_1/Avatars/avtr_a.json").unwrap(), "new a");
+        assert_eq!(fixture.read("usr_2").unwrap(), "not a dir");
+
+        // The backup is untouched and the only one left
+        let backup = fixture.backups.join(&backup_name);
+        assert_eq!(
+            fs::read_to_string(backup.join("usr_1/Avatars/avtr_a.json")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            fs::read_to_string(backup.join("usr_2/Avatars/avtr_b.json")).unwrap(),
+            "b"
+        );
+        assert_eq!(list_osc_config_backups(&fixture.backups), [backup_name]);
+    }
+}
@@ -0,0 +1,577 @@
+/*
+ * VRChat avatar OSC config reader
+ * VRChat writes one JSON file per avatar to OSC/usr_<user id>/Avatars/ describing the parameters it sends.
+ * The configs are used to suggest toy setups (anatomy, penetration system and parameters) for an avatar.
+ */
+use log::{debug, warn};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::toy::VCToyAnatomy,
+    frontend::frontend_types::{
+        FeAvatarOSCConfig, FeAvatarParameter, FeAvatarToySuggestion, FeOSCConfigAvatar,
+        FeOSCConfigUser,
+    },
+    toy_handling::input_processor::penetration_systems::PenetrationSystemType,
+    vcore::vcerror::backend::VibeCheckFSError,
+};
+
+const AVATAR_PARAMETER_PREFIX: &str = "/avatar/parameters/";
+const SPS_PREFIX: &str = "/avatar/parameters/OGB/";
+const TPS_PREFIX: &str = "/avatar/parameters/TPS_Internal/";
+
+// Matched against the whole words of object and parameter names (a trailing s is ignored), first match wins
+const ANATOMY_KEYWORDS: &[(&str, VCToyAnatomy)] = &[
+    ("vagina", VCToyAnatomy::Vagina),
+    ("pussy", VCToyAnatomy::Vagina),
+    ("vulva", VCToyAnatomy::Vulva),
+    ("labia", VCToyAnatomy::Labia),
+    ("clit", VCToyAnatomy::Clitoris),
+    ("clitoris", VCToyAnatomy::Clitoris),
+    ("anal", VCToyAnatomy::Anus),
+    ("anus", VCToyAnatomy::Anus),
+    ("butt", VCToyAnatomy::Buttocks),
+    ("buttocks", VCToyAnatomy::Buttocks),
+    ("blowjob", VCToyAnatomy::Mouth),
+    ("mouth", VCToyAnatomy::Mouth),
+    ("oral", VCToyAnatomy::Mouth),
+    ("nipple", VCToyAnatomy::Nipples),
+    ("breast", VCToyAnatomy::Breasts),
+    ("boob", VCToyAnatomy::Breasts),
+    ("chest", VCToyAnatomy::Chest),
+    ("penis", VCToyAnatomy::Penis),
+    ("cock", VCToyAnatomy::Penis),
+    ("dick", VCToyAnatomy::Penis),
+    ("testicle", VCToyAnatomy::Testicles),
+    ("balls", VCToyAnatomy::Testicles),
+    ("thigh", VCToyAnatomy::Thighs),
+    ("hand", VCToyAnatomy::Hands),
+    ("feet", VCToyAnatomy::Feet),
+    ("foot", VCToyAnatomy::Feet),
+];
+
+#[derive(Deserialize)]
+struct AvatarConfigFile {
+    id: String,
+    name: String,
+    #[serde(default)]
+    parameters: Vec<AvatarConfigParameter>,
+}
+
+#[derive(Deserialize)]
+struct AvatarConfigParameter {
+    #[serde(default)]
+    output: Option<AvatarConfigEndpoint>,
+}
+
+#[derive(Deserialize)]
+struct AvatarConfigEndpoint {
+    address: String,
+    #[serde(rename = "type")]
+    osc_type: String,
+}
+
+/*
+ * Reads every avatar config of every user in osc_dir
+ * Files that fail to parse are skipped
+ */
+pub fn read_avatar_configs(osc_dir: &Path) -> Result<Vec<FeAvatarOSCConfig>, VibeCheckFSError> {
+    let mut configs = Vec::new();
+
+    for (user_id, user_dir) in user_dirs(osc_dir)? {
+        for avatar_file in avatar_files(&user_dir)? {
+            match read_avatar_config(&avatar_file, &user_id) {
+                Some(config) => configs.push(config),
+                None => warn!("Skipping avatar config {}", avatar_file.display()),
+            }
+        }
+    }
+
+    configs.sort_by(|a, b| a.avatar_name.cmp(&b.avatar_name));
+    Ok(configs)
+}
+
+/*
+ * Lists the users in osc_dir and the avatars each has a config for
+ * The avatar name is None when the config can't be parsed
+ */
+pub fn list_osc_config_users(osc_dir: &Path) -> Result<Vec<FeOSCConfigUser>, VibeCheckFSError> {
+    let mut users = Vec::new();
+
+    for (user_id, user_dir) in user_dirs(osc_dir)? {
+        let mut avatars: Vec<FeOSCConfigAvatar> = avatar_files(&user_dir)?
+            .into_iter()
+            .filter_map(|avatar_file| {
+                let avatar_id = avatar_file.file_stem()?.to_str()?.to_string();
+                let avatar_name = read_avatar_config(&avatar_file, &user_id).map(|c| c.avatar_name);
+                Some(FeOSCConfigAvatar {
+                    avatar_id,
+                    avatar_name,
+                })
+            })
+            .collect();
+        avatars.sort_by(|a, b| a.avatar_id.cmp(&b.avatar_id));
+
+        users.push(FeOSCConfigUser { user_id, avatars });
+    }
+
+    users.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+    Ok(users)
+}
+
+/*
+ * The usr_* directories in osc_dir with their user ids
+ */
+pub fn user_dirs(osc_dir: &Path) -> Result<Vec<(String, PathBuf)>, VibeCheckFSError> {
+    Ok(read_dir_paths(osc_dir)?
+        .into_iter()
+        .filter(|dir| dir.is_dir())
+        .filter_map(|dir| {
+            let user_id = dir
+                .file_name()?
+                .to_str()
+                .filter(|n| n.starts_with("usr_"))?
+                .to_string();
+            Some((user_id, dir))
+        })
+        .collect())
+}
+
+fn avatar_files(user_dir: &Path) -> Result<Vec<PathBuf>, VibeCheckFSError> {
+    let avatars_dir = user_dir.join("Avatars");
+    if !avatars_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    Ok(read_dir_paths(&avatars_dir)?
+        .into_iter()
+        .filter(|file| file.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect())
+}
+
+fn read_dir_paths(dir: &Path) -> Result<Vec<PathBuf>, VibeCheckFSError> {
+    fs::read_dir(dir)
+        .map_err(|_| VibeCheckFSError::ReadDirFailure)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, std::io::Error>>()
+        .map_err(|_| VibeCheckFSError::ReadDirPathFailure)
+}
+
+fn read_avatar_config(path: &Path, user_id: &str) -> Option<FeAvatarOSCConfig> {
+    let contents = fs::read_to_string(path).ok()?;
+    // VRChat writes the files with a byte order mark
+    let file: AvatarConfigFile = serde_json::from_str(contents.trim_start_matches('\u{feff}'))
+        .map_err(|e| debug!("Failed to parse {}: {}", path.display(), e))
+        .ok()?;
+
+    let mut parameters: Vec<FeAvatarParameter> = file
+        .parameters
+        .into_iter()
+        .filter_map(|p| p.output)
+        .map(|output| FeAvatarParameter {
+            osc_type: osc_type_tag(&output.osc_type).to_string(),
+            addr: output.address,
+        })
+        .collect();
+    parameters.sort_by(|a, b| a.addr.cmp(&b.addr));
+
+    Some(FeAvatarOSCConfig {
+        user_id: user_id.to_string(),
+        avatar_id: file.id,
+        avatar_name: file.name,
+        suggestions: suggest_toy_setups(&parameters),
+        parameters,
+    })
+}
+
+fn osc_type_tag(vrc_type: &str) -> &'static str {
+    match vrc_type {
+        "Int" => "i",
+        "Bool" => "T",
+        _ => "f",
+    }
+}
+
+/*
+ * Splits a name into lowercase words
+ * Words are separated by anything that isn't alphanumeric, case changes (VibeAnal, SPSButt) and digits (Hand2)
+ */
+fn name_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        if let Some(&prev) = i.checked_sub(1).and_then(|p| chars.get(p)) {
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            let boundary = c.is_numeric() != prev.is_numeric()
+                || (c.is_uppercase() && prev.is_lowercase())
+                || (c.is_uppercase() && prev.is_uppercase() && next_lower);
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn anatomy_from_name(name: &str) -> Option<VCToyAnatomy> {
+    let words = name_words(name);
+    ANATOMY_KEYWORDS
+        .iter()
+        .find(|(keyword, _)| {
+            words
+                .iter()
+                .any(|word| word == keyword || word.strip_suffix('s') == Some(keyword))
+        })
+        .map(|(_, anatomy)| anatomy.clone())
+}
+
+/*
+ * Groups penetration system parameters by object (Orf/Pen + object name)
+ */
+fn penetration_objects<'a>(
+    parameters: &'a [FeAvatarParameter],
+    prefix: &str,
+) -> BTreeMap<(&'a str, &'a str), Vec<String>> {
+    let mut objects: BTreeMap<(&str, &str), Vec<String>> = BTreeMap::new();
+    for param in parameters {
+        let Some(path) = param.addr.strip_prefix(prefix) else {
+            continue;
+        };
+        let mut segments = path.split('/');
+        let (Some(kind), Some(object), Some(_leaf)) =
+            (segments.next(), segments.next(), segments.next())
+        else {
+            continue;
+        };
+        objects
+            .entry((kind, object))
+            .or_default()
+            .push(param.addr.clone());
+    }
+    objects
+}
+
+/*
+ * Suggests a toy setup per SPS and TPS object and per anatomy named by a plain parameter
+ * Penetrators default to Penis, orifices without a recognisable name are left as NA
+ */
+fn suggest_toy_setups(parameters: &[FeAvatarParameter]) -> Vec<FeAvatarToySuggestion> {
+    let mut suggestions = Vec::new();
+
+    for (prefix, pen_system_type) in [
+        (SPS_PREFIX, PenetrationSystemType::SPS),
+        (TPS_PREFIX, PenetrationSystemType::TPS),
+    ] {
+        for ((kind, object), parameters) in penetration_objects(parameters, prefix) {
+            let anatomy = anatomy_from_name(object).unwrap_or(if kind == "Pen" {
+                VCToyAnatomy::Penis
+            } else {
+                VCToyAnatomy::NA
+            });
+            suggestions.push(FeAvatarToySuggestion {
+                anatomy: anatomy.to_fe(),
+                pen_system_type: pen_system_type.clone(),
+                object: Some(format!("{}/{}", kind, object)),
+                parameters,
+            });
+        }
+    }
+
+    // Plain float and bool parameters named after an anatomy
+    let mut by_anatomy: Vec<(VCToyAnatomy, Vec<String>)> = Vec::new();
+    for param in parameters {
+        if param.osc_type == "i"
+            || !param.addr.starts_with(AVATAR_PARAMETER_PREFIX)
+            || param.addr.starts_with(SPS_PREFIX)
+            || param.addr.starts_with(TPS_PREFIX)
+            || param.addr.starts_with("/avatar/parameters/vibecheck/")
+        {
+            continue;
+        }
+        let name = param.addr.rsplit('/').next().unwrap_or_default();
+        let Some(anatomy) = anatomy_from_name(name) else {
+            continue;
+        };
+        match by_anatomy.iter_mut().find(|(a, _)| *a == anatomy) {
+            Some((_, addrs)) => addrs.push(param.addr.clone()),
+            None => by_anatomy.push((anatomy, vec![param.addr.clone()])),
+        }
+    }
+    for (anatomy, parameters) in by_anatomy {
+        suggestions.push(FeAvatarToySuggestion {
+            anatomy: anatomy.to_fe(),
+            pen_system_type: PenetrationSystemType::NONE,
+            object: None,
+            parameters,
+        });
+    }
+
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::frontend_types::FeVCToyAnatomy;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn splits_names_into_words() {
+        assert_eq!(name_words("VibeAnal"), ["vibe", "anal"]);
+        assert_eq!(name_words("SPSButtPlug"), ["sps", "butt", "plug"]);
+        assert_eq!(name_words("left_hand/Touch"), ["left", "hand", "touch"]);
+        assert_eq!(name_words("Hand2"), ["hand", "2"]);
+        assert_eq!(name_words("nipple-L"), ["nipple", "l"]);
+        assert!(name_words("__").is_empty());
+    }
+
+    #[test]
+    fn matches_whole_words() {
+        assert_eq!(anatomy_from_name("VibeAnal"), Some(VCToyAnatomy::Anus));
+        assert_eq!(anatomy_from_name("Butt"), Some(VCToyAnatomy::Buttocks));
+        assert_eq!(anatomy_from_name("OralTouch"), Some(VCToyAnatomy::Mouth));
+        assert_eq!(anatomy_from_name("LeftHand"), Some(VCToyAnatomy::Hands));
+        assert_eq!(anatomy_from_name("Nipples_L"), Some(VCToyAnatomy::Nipples));
+        assert_eq!(anatomy_from_name("boobs"), Some(VCToyAnatomy::Breasts));
+    }
+
+    #[test]
+    fn ignores_keywords_inside_other_words() {
+        for name in [
+            "Button", "Floral", "Coral", "Analog", "Handle", "Chestnut", "Scockpit",
+        ] {
+            assert_eq!(anatomy_from_name(name), None, "{}", name);
+        }
+    }
+
+    fn param(addr: &str, osc_type: &str) -> FeAvatarParameter {
+        FeAvatarParameter {
+            addr: addr.to_string(),
+            osc_type: osc_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn suggests_penetration_objects() {
+        let suggestions = suggest_toy_setups(&[
+            param("/avatar/parameters/OGB/Orf/Blowjob/PenSelfNewRoot", "f"),
+            param("/avatar/parameters/OGB/Orf/Blowjob/PenSelfNewTip", "f"),
+            param("/avatar/parameters/OGB/Pen/Cock/TouchSelf", "f"),
+            param("/avatar/parameters/OGB/Pen/Tail/TouchSelf", "f"),
+            param("/avatar/parameters/OGB/Orf/Floral/PenSelfNewTip", "f"),
+            param("/avatar/parameters/TPS_Internal/Orf/Anal/Depth_In", "f"),
+        ]);
+
+        let found: Vec<(&str, &FeVCToyAnatomy, &PenetrationSystemType, usize)> = suggestions
+            .iter()
+            .map(|s| {
+                (
+                    s.object.as_deref().unwrap_or_default(),
+                    &s.anatomy,
+                    &s.pen_system_type,
+                    s.parameters.len(),
+                )
+            })
+            .collect();
+
+        assert_eq!(found.len(), 5);
+        assert!(matches!(
+            found[0],
+            (
+                "Orf/Blowjob",
+                FeVCToyAnatomy::Mouth,
+                PenetrationSystemType::SPS,
+                2
+            )
+        ));
+        assert!(matches!(
+            found[1],
+            (
+                "Orf/Floral",
+                FeVCToyAnatomy::NA,
+                PenetrationSystemType::SPS,
+                1
+            )
+        ));
+        assert!(matches!(
+            found[2],
+            (
+                "Pen/Cock",
+                FeVCToyAnatomy::Penis,
+                PenetrationSystemType::SPS,
+                1
+            )
+        ));
+        assert!(matches!(
+            found[3],
+            (
+                "Pen/Tail",
+                FeVCToyAnatomy::Penis,
+                PenetrationSystemType::SPS,
+                1
+            )
+        ));
+        assert!(matches!(
+            found[4],
+            (
+                "Orf/Anal",
+                FeVCToyAnatomy::Anus,
+                PenetrationSystemType::TPS,
+                1
+            )
+        ));
+    }
+
+    #[test]
+    fn suggests_plain_parameters_by_anatomy() {
+        let suggestions = suggest_toy_setups(&[
+            param("/avatar/parameters/Nipple_L", "f"),
+            param("/avatar/parameters/Nipple_R", "T"),
+            param("/avatar/parameters/NippleCount", "i"),
+            param("/avatar/parameters/ButtonPressed", "T"),
+            param("/avatar/parameters/vibecheck/Nipple", "f"),
+            param("/avatar/parameters/Toys/HandTouch", "f"),
+            param("/input/Nipple", "f"),
+        ]);
+
+        assert_eq!(suggestions.len(), 2);
+        assert!(matches!(suggestions[0].anatomy, FeVCToyAnatomy::Nipples));
+        assert!(matches!(
+            suggestions[0].pen_system_type,
+            PenetrationSystemType::NONE
+        ));
+        assert_eq!(
+            suggestions[0].parameters,
+            ["/avatar/parameters/Nipple_L", "/avatar/parameters/Nipple_R"]
+        );
+        assert!(matches!(suggestions[1].anatomy, FeVCToyAnatomy::Hands));
+        assert_eq!(
+            suggestions[1].parameters,
+            ["/avatar/parameters/Toys/HandTouch"]
+        );
+    }
+
+    /*
+     * A throwaway OSC directory in the system temp dir, removed on drop
+     */
+    struct OscDirFixture(PathBuf);
+
+    impl OscDirFixture {
+        fn new() -> Self {
+            static NEXT: AtomicU32 = AtomicU32::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "vibecheck-avatar-config-{}-{}",
+                std::process::id(),
+                NEXT.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, path: &str, contents: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for OscDirFixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const AVATAR_CONFIG: &str = r#"{
+        "id": "avtr_b",
+        "name": "Bravo",
+        "parameters": [
+            {"name": "Vibe", "input": {"address": "/avatar/parameters/Vibe", "type": "Float"}},
+            {"name": "AnalDepth", "output": {"address": "/avatar/parameters/AnalDepth", "type": "Float"}},
+            {"name": "Count", "output": {"address": "/avatar/parameters/Count", "type": "Int"}},
+            {"name": "Clit", "output": {"address": "/avatar/parameters/Clit", "type": "Bool"}}
+        ]
+    }"#;
+
+    #[test]
+    fn reads_avatar_config_fixtures() {
+        let fixture = OscDirFixture::new();
+        // VRChat writes the files with a byte order mark
+        fixture.write(
+            "usr_1/Avatars/avtr_b.json",
+            &format!("\u{feff}{}", AVATAR_CONFIG),
+        );
+        fixture.write(
+            "usr_1/Avatars/avtr_a.json",
+            r#"{"id": "avtr_a", "name": "Alpha"}"#,
+        );
+        fixture.write("usr_1/Avatars/broken.json", "{");
+        fixture.write("usr_1/Avatars/notes.txt", "not a config");
+        fixture.write(
+            "usr_2/Avatars/avtr_c.json",
+            r#"{"id": "avtr_c", "name": "Charlie"}"#,
+        );
+        fixture.write(
+            "other/Avatars/avtr_d.json",
+            r#"{"id": "avtr_d", "name": "Delta"}"#,
+        );
+
+        let Ok(configs) = read_avatar_configs(&fixture.0) else {
+            panic!("failed to read fixture dir");
+        };
+        let names: Vec<(&str, &str)> = configs
+            .iter()
+            .map(|c| (c.user_id.as_str(), c.avatar_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [("usr_1", "Alpha"), ("usr_1", "Bravo"), ("usr_2", "Charlie")]
+        );
+
+        let bravo = &configs[1];
+        assert_eq!(bravo.avatar_id, "avtr_b");
+        let parameters: Vec<(&str, &str)> = bravo
+            .parameters
+            .iter()
+            .map(|p| (p.addr.as_str(), p.osc_type.as_str()))
+            .collect();
+        assert_eq!(
+            parameters,
+            [
+                ("/avatar/parameters/AnalDepth", "f"),
+                ("/avatar/parameters/Clit", "T"),
+                ("/avatar/parameters/Count", "i"),
+            ]
+        );
+        assert_eq!(bravo.suggestions.len(), 2);
+        assert!(matches!(bravo.suggestions[0].anatomy, FeVCToyAnatomy::Anus));
+        assert!(matches!(
+            bravo.suggestions[1].anatomy,
+            FeVCToyAnatomy::Clitoris
+        ));
+    }
+
+    #[test]
+    fn missing_osc_dir_fails() {
+        let fixture = OscDirFixture::new();
+        assert!(read_avatar_configs(&fixture.0.join("missing")).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use log::{trace, warn};
+use rosc::{OscMessage, OscPacket, OscTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Seconds between the NTP epoch (1900) used by OSC timetags and the UNIX epoch (1970)
+const NTP_UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+// Bundles scheduled further ahead than this are most likely from a sender with a skewed clock
+const MAX_SCHEDULE_AHEAD: Duration = Duration::from_secs(10);
+
+/*
+ * An OSC message unpacked from a packet
+ * deliver_at is None when the message should be handled immediately
+ */
+#[derive(Debug, Clone)]
+pub struct TimedOscMessage<M = OscMessage> {
+    pub deliver_at: Option<SystemTime>,
+    pub msg: M,
+}
+
+impl<M> TimedOscMessage<M> {
+    /*
+     * Time left until this message should be delivered
+     * None means deliver now
+     */
+    pub fn delay(&self) -> Option<Duration> {
+        self.deliver_at?
+            .duration_since(SystemTime::now())
+            .ok()
+            .filter(|d| !d.is_zero())
+    }
+}
+
+/*
+ * Flattens an OSC packet into its messages
+ * Nested bundles are unpacked recursively and every message keeps the timetag of its innermost bundle
+ */
+pub fn unpack_packet(pkt: OscPacket) -> Vec<TimedOscMessage> {
+    let mut out = Vec::new();
+    unpack_into(pkt, None, &mut out);
+    out
+}
+
+fn unpack_into(pkt: OscPacket, deliver_at: Option<SystemTime>, out: &mut Vec<TimedOscMessage>) {
+    match pkt {
+        OscPacket::Message(msg) => out.push(TimedOscMessage { deliver_at, msg }),
+        OscPacket::Bundle(bundle) => {
+            // An immediate timetag in a nested bundle inherits the enclosing bundle's time
+            let bundle_time = match osc_time_to_system_time(bundle.timetag) {
+                Some(time) => clamp_schedule(time),
+                None => deliver_at,
+            };
+            trace!(
+                "Unpacking OSC bundle with {} elements",
+                bundle.content.len()
+            );
+            for inner in bundle.content {
+                unpack_into(inner, bundle_time, out);
+            }
+        }
+    }
+}
+
+/*
+ * Bundles scheduled too far ahead are delivered immediately
+ * Checked per bundle so a skewed sender only warns once per bundle
+ */
+fn clamp_schedule(deliver_at: SystemTime) -> Option<SystemTime> {
+    match deliver_at.duration_since(SystemTime::now()) {
+        Ok(ahead) if ahead > MAX_SCHEDULE_AHEAD => {
+            warn!(
+                "OSC bundle timetag is {:?} ahead, delivering immediately",
+                ahead
+            );
+            None
+        }
+        _ => Some(deliver_at),
+    }
+}
+
+/*
+ * Converts an OSC timetag to SystemTime
+ * Returns None for the special "immediately" timetag
+ */
+pub fn osc_time_to_system_time(time: OscTime) -> Option<SystemTime> {
+    if time.seconds == 0 && time.fractional <= 1 {
+        return None;
+    }
+
+    let unix_secs = (time.seconds as u64).checked_sub(NTP_UNIX_EPOCH_OFFSET)?;
+    let nanos = ((time.fractional as u64) * 1_000_000_000) >> 32;
+    Some(UNIX_EPOCH + Duration::new(unix_secs, nanos as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscBundle, OscType};
+
+    const IMMEDIATE: OscTime = OscTime {
+        seconds: 0,
+        fractional: 1,
+    };
+
+    fn osc_time(time: SystemTime) -> OscTime {
+        let since_unix = time.duration_since(UNIX_EPOCH).unwrap();
+        OscTime {
+            seconds: (since_unix.as_secs() + NTP_UNIX_EPOCH_OFFSET) as u32,
+            fractional: (((since_unix.subsec_nanos() as u64) << 32) / 1_000_000_000) as u32,
+        }
+    }
+
+    fn message(addr: &str) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args: vec![OscType::Float(0.5)],
+        })
+    }
+
+    fn bundle(timetag: OscTime, content: Vec<OscPacket>) -> OscPacket {
+        OscPacket::Bundle(OscBundle { timetag, content })
+    }
+
+    #[test]
+    fn converts_timetags() {
+        assert_eq!(osc_time_to_system_time(IMMEDIATE), None);
+        assert_eq!(
+            osc_time_to_system_time(OscTime {
+                seconds: 0,
+                fractional: 0,
+            }),
+            None
+        );
+        // Before the UNIX epoch
+        assert_eq!(
+            osc_time_to_system_time(OscTime {
+                seconds: 1,
+                fractional: 0,
+            }),
+            None
+        );
+
+        assert_eq!(
+            osc_time_to_system_time(OscTime {
+                seconds: NTP_UNIX_EPOCH_OFFSET as u32 + 10,
+                fractional: 1 << 31,
+            }),
+            Some(UNIX_EPOCH + Duration::from_millis(10_500))
+        );
+
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 250_000_000);
+        assert_eq!(osc_time_to_system_time(osc_time(time)), Some(time));
+    }
+
+    #[test]
+    fn nested_bundles_keep_the_innermost_timetag() {
+        let outer = SystemTime::now() + Duration::from_secs(2);
+        let inner = SystemTime::now() + Duration::from_secs(4);
+        let msgs = unpack_packet(bundle(
+            osc_time(outer),
+            vec![
+                message("/outer"),
+                bundle(osc_time(inner), vec![message("/inner")]),
+                bundle(IMMEDIATE, vec![message("/inherited")]),
+            ],
+        ));
+
+        let unpacked: Vec<(&str, Option<SystemTime>)> = msgs
+            .iter()
+            .map(|timed| (timed.msg.addr.as_str(), timed.deliver_at))
+            .collect();
+        // Timetags keep nanosecond precision through the conversion
+        let outer = osc_time_to_system_time(osc_time(outer));
+        let inner = osc_time_to_system_time(osc_time(inner));
+        assert_eq!(
+            unpacked,
+            [("/outer", outer), ("/inner", inner), ("/inherited", outer)]
+        );
+
+        assert!(unpack_packet(message("/plain"))[0].deliver_at.is_none());
+    }
+
+    #[test]
+    fn delays_until_the_timetag() {
+        let msgs = unpack_packet(bundle(IMMEDIATE, vec![message("/now")]));
+        assert_eq!(msgs[0].delay(), None);
+
+        let past = SystemTime::now() - Duration::from_secs(5);
+        let msgs = unpack_packet(bundle(osc_time(past), vec![message("/past")]));
+        assert_eq!(msgs[0].deliver_at, osc_time_to_system_time(osc_time(past)));
+        assert_eq!(msgs[0].delay(), None);
+
+        let soon = SystemTime::now() + Duration::from_secs(5);
+        let msgs = unpack_packet(bundle(osc_time(soon), vec![message("/soon")]));
+        let delay = msgs[0].delay().unwrap();
+        assert!(delay > Duration::from_secs(4) && delay <= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn far_future_bundles_are_delivered_immediately() {
+        let far = SystemTime::now() + MAX_SCHEDULE_AHEAD + Duration::from_secs(60);
+        let msgs = unpack_packet(bundle(
+            osc_time(far),
+            vec![
+                message("/a"),
+                message("/b"),
+                bundle(IMMEDIATE, vec![message("/c")]),
+            ],
+        ));
+
+        assert_eq!(msgs.len(), 3);
+        for timed in msgs {
+            assert_eq!(timed.deliver_at, None, "{} was scheduled", timed.msg.addr);
+            assert_eq!(timed.delay(), None);
+        }
+    }
+}
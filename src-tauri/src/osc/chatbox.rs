@@ -0,0 +1,353 @@
+/*
+ * VRChat chatbox
+ * Messages are sent to /chatbox/input on the remote.
+ * Status announcements are built from templates and queued so VRChat's chatbox cooldown is respected,
+ * announcements made during the cooldown are combined into one message.
+ */
+use futures_timer::Delay;
+use log::{debug, info};
+use parking_lot::Mutex;
+use rosc::{OscMessage, OscType};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use ts_rs::TS;
+
+use super::feedback::send_to_remote;
+
+// VRChat cuts chatbox messages off after this many characters
+const CHATBOX_MAX_CHARS: usize = 144;
+// VRChat ignores chatbox input sent faster than this
+pub const MIN_CHATBOX_COOLDOWN_MS: u64 = 1500;
+const SEPARATOR: &str = " | ";
+
+/*
+ * Templates use {toy}, {battery} and {alert}
+ * Announcements without a template aren't sent
+ * quiet sends without the chatbox notification sound
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ChatboxConfig {
+    pub enabled: bool,
+    pub quiet: bool,
+    pub cooldown_ms: u64,
+    pub toy_connected: Option<String>,
+    pub toy_disconnected: Option<String>,
+    pub battery_low: Option<String>,
+    pub vibecheck_enabled: Option<String>,
+    pub vibecheck_disabled: Option<String>,
+}
+
+impl Default for ChatboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            quiet: false,
+            cooldown_ms: 2000,
+            toy_connected: Some("{toy} connected ({battery})".to_string()),
+            toy_disconnected: Some("{toy} disconnected".to_string()),
+            battery_low: Some("{toy} battery {alert} ({battery})".to_string()),
+            vibecheck_enabled: Some("VibeCheck enabled".to_string()),
+            vibecheck_disabled: Some("VibeCheck disabled".to_string()),
+        }
+    }
+}
+
+pub enum ChatboxAnnouncement<'a> {
+    ToyConnected {
+        toy: &'a str,
+        battery: &'a str,
+    },
+    ToyDisconnected {
+        toy: &'a str,
+    },
+    BatteryLow {
+        toy: &'a str,
+        battery: &'a str,
+        alert: &'a str,
+    },
+    VibeCheckEnabled,
+    VibeCheckDisabled,
+}
+
+impl ChatboxAnnouncement<'_> {
+    fn format(&self, config: &ChatboxConfig) -> Option<String> {
+        let (template, toy, battery, alert) = match self {
+            Self::ToyConnected { toy, battery } => (&config.toy_connected, *toy, *battery, ""),
+            Self::ToyDisconnected { toy } => (&config.toy_disconnected, *toy, "", ""),
+            Self::BatteryLow {
+                toy,
+                battery,
+                alert,
+            } => (&config.battery_low, *toy, *battery, *alert),
+            Self::VibeCheckEnabled => (&config.vibecheck_enabled, "", "", ""),
+            Self::VibeCheckDisabled => (&config.vibecheck_disabled, "", "", ""),
+        };
+
+        let message = template
+            .as_deref()?
+            .replace("{toy}", toy)
+            .replace("{battery}", battery)
+            .replace("{alert}", alert);
+        let message = message.trim();
+        (!message.is_empty()).then(|| message.to_string())
+    }
+}
+
+struct Chatbox {
+    config: ChatboxConfig,
+    queue: VecDeque<String>,
+    last_sent: Option<Instant>,
+    flushing: bool,
+}
+
+impl Chatbox {
+    fn cooldown_left(&self) -> Duration {
+        let cooldown = Duration::from_millis(self.config.cooldown_ms.max(MIN_CHATBOX_COOLDOWN_MS));
+        self.last_sent
+            .map(|t| cooldown.saturating_sub(t.elapsed()))
+            .unwrap_or_default()
+    }
+
+    /*
+     * Takes the next message to send and whether it should make a sound
+     * Combines what fits, the rest waits for the next cooldown
+     */
+    fn next_message(&mut self) -> Option<(String, bool)> {
+        let mut message = self.queue.pop_front()?;
+        while let Some(next) = self.queue.front() {
+            if message.chars().count() + SEPARATOR.len() + next.chars().count() > CHATBOX_MAX_CHARS
+            {
+                break;
+            }
+            message.push_str(SEPARATOR);
+            message.push_str(next);
+            self.queue.pop_front();
+        }
+        self.last_sent = Some(Instant::now());
+        Some((message, !self.config.quiet))
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CHATBOX: Mutex<Chatbox> = Mutex::new(Chatbox {
+        config: ChatboxConfig::default(),
+        queue: VecDeque::new(),
+        last_sent: None,
+        flushing: false,
+    });
+}
+
+pub fn configure_chatbox(config: ChatboxConfig) {
+    let mut chatbox = CHATBOX.lock();
+    if !config.enabled {
+        chatbox.queue.clear();
+    }
+    chatbox.config = config;
+}
+
+/*
+ * Queues a status announcement when announcements are enabled and it has a template
+ */
+pub fn announce(announcement: ChatboxAnnouncement) {
+    let mut chatbox = CHATBOX.lock();
+    if !chatbox.config.enabled {
+        return;
+    }
+    let Some(message) = announcement.format(&chatbox.config) else {
+        return;
+    };
+
+    debug!("Queued chatbox announcement: {}", message);
+    chatbox.queue.push_back(message);
+    if !chatbox.flushing {
+        chatbox.flushing = true;
+        tauri::async_runtime::spawn(flush_announcements());
+    }
+}
+
+/*
+ * Sends the queued announcements once the cooldown passed until the queue is empty
+ */
+async fn flush_announcements() {
+    loop {
+        let wait = CHATBOX.lock().cooldown_left();
+        Delay::new(wait).await;
+
+        let (message, sound) = {
+            let mut chatbox = CHATBOX.lock();
+            let Some(next) = chatbox.next_message() else {
+                chatbox.flushing = false;
+                return;
+            };
+            next
+        };
+
+        info!("Chatbox announcement: {}", message);
+        send_chatbox_message(&message, sound);
+    }
+}
+
+/*
+ * Shows a message in the chatbox immediately
+ */
+pub fn send_chatbox_message(text: &str, sound: bool) {
+    send_to_remote(vec![chatbox_message(text, sound)]);
+}
+
+fn chatbox_message(text: &str, sound: bool) -> OscMessage {
+    let text: String = text.chars().take(CHATBOX_MAX_CHARS).collect();
+    debug!("Chatbox: {}", text);
+    OscMessage {
+        addr: "/chatbox/input".to_string(),
+        args: vec![
+            OscType::String(text),
+            OscType::Bool(true),
+            OscType::Bool(sound),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chatbox(config: ChatboxConfig, queued: &[&str]) -> Chatbox {
+        Chatbox {
+            config,
+            queue: queued.iter().map(|m| m.to_string()).collect(),
+            last_sent: None,
+            flushing: true,
+        }
+    }
+
+    #[test]
+    fn formats_announcements_from_templates() {
+        let config = ChatboxConfig::default();
+        let connected = ChatboxAnnouncement::ToyConnected {
+            toy: "Hush",
+            battery: "80%",
+        };
+        assert_eq!(
+            connected.format(&config).as_deref(),
+            Some("Hush connected (80%)")
+        );
+        let low = ChatboxAnnouncement::BatteryLow {
+            toy: "Hush",
+            battery: "9%",
+            alert: "critical",
+        };
+        assert_eq!(
+            low.format(&config).as_deref(),
+            Some("Hush battery critical (9%)")
+        );
+
+        // No template or nothing left after filling it in
+        let config = ChatboxConfig {
+            toy_disconnected: None,
+            vibecheck_enabled: Some(" {alert} ".to_string()),
+            ..Default::default()
+        };
+        assert!(ChatboxAnnouncement::ToyDisconnected { toy: "Hush" }
+            .format(&config)
+            .is_none());
+        assert!(ChatboxAnnouncement::VibeCheckEnabled
+            .format(&config)
+            .is_none());
+    }
+
+    #[test]
+    fn merges_announcements_queued_during_the_cooldown() {
+        let mut chatbox = chatbox(
+            ChatboxConfig::default(),
+            &[
+                "Hush connected (80%)",
+                "Nora connected (50%)",
+                "VibeCheck enabled",
+            ],
+        );
+        assert_eq!(chatbox.cooldown_left(), Duration::ZERO);
+
+        assert_eq!(
+            chatbox.next_message(),
+            Some((
+                "Hush connected (80%) | Nora connected (50%) | VibeCheck enabled".to_string(),
+                true
+            ))
+        );
+        assert!(chatbox.queue.is_empty());
+        assert!(chatbox.next_message().is_none());
+
+        // The next announcement waits for the cooldown
+        let cooldown = chatbox.cooldown_left();
+        assert!(cooldown > Duration::ZERO && cooldown <= Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn merged_messages_fit_the_chatbox() {
+        let long = "x".repeat(100);
+        let mut chatbox = chatbox(ChatboxConfig::default(), &[&long, "short", &long, "end"]);
+
+        let mut sent = Vec::new();
+        while let Some((message, _)) = chatbox.next_message() {
+            assert!(message.chars().count() <= CHATBOX_MAX_CHARS);
+            sent.push(message);
+        }
+        assert_eq!(
+            sent,
+            [format!("{} | short", long), format!("{} | end", long)]
+        );
+    }
+
+    #[test]
+    fn cooldown_is_never_below_vrchats() {
+        let mut chatbox = chatbox(
+            ChatboxConfig {
+                cooldown_ms: 10,
+                ..Default::default()
+            },
+            &["VibeCheck enabled"],
+        );
+        chatbox.next_message();
+        assert!(chatbox.cooldown_left() > Duration::from_millis(MIN_CHATBOX_COOLDOWN_MS - 500));
+    }
+
+    #[test]
+    fn quiet_mode_sends_without_sound() {
+        let mut chatbox = chatbox(
+            ChatboxConfig {
+                quiet: true,
+                ..Default::default()
+            },
+            &["VibeCheck disabled"],
+        );
+        let Some((message, sound)) = chatbox.next_message() else {
+            panic!("nothing was sent");
+        };
+        assert!(!sound);
+
+        let msg = chatbox_message(&message, sound);
+        assert_eq!(msg.addr, "/chatbox/input");
+        assert_eq!(
+            msg.args,
+            [
+                OscType::String("VibeCheck disabled".to_string()),
+                OscType::Bool(true),
+                OscType::Bool(false)
+            ]
+        );
+    }
+
+    #[test]
+    fn long_messages_are_cut_off() {
+        let msg = chatbox_message(&"ä".repeat(200), true);
+        let Some(OscType::String(text)) = msg.args.first() else {
+            panic!("no text argument");
+        };
+        assert_eq!(text.chars().count(), CHATBOX_MAX_CHARS);
+        assert_eq!(msg.args[2], OscType::Bool(true));
+    }
+}
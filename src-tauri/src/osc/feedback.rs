@@ -0,0 +1,425 @@
+/*
+ * Avatar feedback
+ * Sends what toys are doing back to the avatar so it can drive visuals (glow, particles, etc.).
+ * Feature levels are sent from the toy command path to the feature's output parameter, throttled per address.
+ * Everything is queued to a single feedback task so toy commands never wait on the remote.
+ * Toy connected/listening state is sent as booleans under the toy's osc_data address (telemetry template and aliases).
+ */
+use log::{debug, info, warn};
+use rosc::{encoder, OscMessage, OscPacket, OscType};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::{
+    config::{OSCNetworking, TelemetryConfig},
+    util::net::HostPort,
+};
+
+use super::{
+    tcp::{OSCSender, OSCTransport},
+    telemetry::{format_telemetry_addr, TelemetryField, DEFAULT_ADDRESS_TEMPLATE},
+};
+
+// Don't retry a failed remote connection on every toy command
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+enum FeedbackCmd {
+    Configure {
+        remote: (HostPort, OSCTransport),
+        interval: Duration,
+    },
+    Level {
+        addr: String,
+        level: f32,
+        stop: bool,
+    },
+    Addresses {
+        template: String,
+        aliases: BTreeMap<String, String>,
+    },
+    ToyState {
+        toy_name: String,
+        sub_id: u8,
+        connected: bool,
+        listening: bool,
+    },
+    Send(Vec<OscMessage>),
+}
+
+struct OSCFeedback {
+    remote: Option<(HostPort, OSCTransport)>,
+    interval: Duration,
+    sender: Option<OSCSender>,
+    retry_at: Option<Instant>,
+    last_sent: HashMap<String, Instant>,
+    // Latest throttled level per address, sent once the address' interval has passed
+    pending: HashMap<String, f32>,
+    // osc_data addresses follow the telemetry config so both send the same fields to the same address
+    address_template: String,
+    aliases: BTreeMap<String, String>,
+}
+
+lazy_static::lazy_static! {
+    static ref OSC_FEEDBACK_TX: UnboundedSender<FeedbackCmd> = {
+        let (tx, rx) = unbounded_channel();
+        tauri::async_runtime::spawn(osc_feedback_routine(rx));
+        tx
+    };
+}
+
+fn queue_feedback(cmd: FeedbackCmd) {
+    let _ = OSC_FEEDBACK_TX.send(cmd);
+}
+
+/*
+ * Points feedback at the configured remote
+ * Called when VibeCheck starts listening so config changes apply on the next enable
+ */
+pub fn configure_osc_feedback(networking: &OSCNetworking) {
+    queue_feedback(FeedbackCmd::Configure {
+        remote: (networking.remote.clone(), networking.remote_transport),
+        interval: Duration::from_secs_f64(1.0 / networking.feedback_rate.max(1) as f64),
+    });
+}
+
+/*
+ * Uses the telemetry address template and aliases for the toy state addresses
+ */
+pub fn configure_osc_data_addresses(telemetry: &TelemetryConfig) {
+    queue_feedback(FeedbackCmd::Addresses {
+        template: telemetry.address_template.clone(),
+        aliases: telemetry.aliases.clone(),
+    });
+}
+
+async fn osc_feedback_routine(mut rx: UnboundedReceiver<FeedbackCmd>) {
+    let mut feedback = OSCFeedback::new();
+
+    loop {
+        let cmd = match feedback.next_flush() {
+            Some(at) => {
+                match tokio::time::timeout_at(tokio::time::Instant::from_std(at), rx.recv()).await {
+                    Ok(cmd) => cmd,
+                    Err(_) => {
+                        feedback.flush_pending().await;
+                        continue;
+                    }
+                }
+            }
+            None => rx.recv().await,
+        };
+
+        match cmd {
+            Some(FeedbackCmd::Configure { remote, interval }) => {
+                feedback.configure(remote, interval)
+            }
+            Some(FeedbackCmd::Level { addr, level, stop }) => {
+                feedback.level(addr, level, stop).await
+            }
+            Some(FeedbackCmd::Addresses { template, aliases }) => {
+                feedback.address_template = template;
+                feedback.aliases = aliases;
+            }
+            Some(FeedbackCmd::ToyState {
+                toy_name,
+                sub_id,
+                connected,
+                listening,
+            }) => {
+                let msgs = feedback.toy_state_messages(&toy_name, sub_id, connected, listening);
+                feedback.send(msgs).await
+            }
+            Some(FeedbackCmd::Send(msgs)) => feedback.send(msgs).await,
+            None => return,
+        }
+    }
+}
+
+fn level_message(addr: String, level: f32) -> OscMessage {
+    OscMessage {
+        addr,
+        args: vec![OscType::Float(level)],
+    }
+}
+
+impl OSCFeedback {
+    fn new() -> Self {
+        Self {
+            remote: None,
+            interval: Duration::from_millis(100),
+            sender: None,
+            retry_at: None,
+            last_sent: HashMap::new(),
+            pending: HashMap::new(),
+            address_template: DEFAULT_ADDRESS_TEMPLATE.to_string(),
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    /*
+     * Address of a toy's osc_data field
+     * eg. /avatar/parameters/vibecheck/osc_data/lovense_hush/0/connected
+     */
+    fn osc_data_addr(&self, toy_name: &str, sub_id: u8, field: &str) -> String {
+        format_telemetry_addr(
+            &self.address_template,
+            toy_name,
+            self.aliases.get(toy_name).map(String::as_str),
+            sub_id,
+            field,
+        )
+    }
+
+    fn toy_state_messages(
+        &self,
+        toy_name: &str,
+        sub_id: u8,
+        connected: bool,
+        listening: bool,
+    ) -> Vec<OscMessage> {
+        vec![
+            OscMessage {
+                addr: self.osc_data_addr(toy_name, sub_id, TelemetryField::Connected.name()),
+                args: vec![OscType::Bool(connected)],
+            },
+            OscMessage {
+                addr: self.osc_data_addr(toy_name, sub_id, "listening"),
+                args: vec![OscType::Bool(listening)],
+            },
+        ]
+    }
+
+    fn configure(&mut self, remote: (HostPort, OSCTransport), interval: Duration) {
+        let remote = Some(remote);
+        if self.remote != remote {
+            if let Some((host, _)) = remote.as_ref() {
+                info!("OSC feedback remote set to {}", host);
+            }
+            self.remote = remote;
+            self.sender = None;
+            self.retry_at = None;
+        }
+        self.interval = interval;
+        self.last_sent.clear();
+        self.pending.clear();
+    }
+
+    /*
+     * Throttled to the interval per address, stops (input 0) are always sent so visuals don't stick
+     * A throttled level is kept and sent when the interval has passed so the last level isn't lost
+     */
+    async fn level(&mut self, addr: String, level: f32, stop: bool) {
+        let now = Instant::now();
+        let interval = self.interval;
+        if !stop
+            && self
+                .last_sent
+                .get(&addr)
+                .is_some_and(|last| now.duration_since(*last) < interval)
+        {
+            self.pending.insert(addr, level);
+            return;
+        }
+
+        self.pending.remove(&addr);
+        self.last_sent.insert(addr.clone(), now);
+        self.send(vec![level_message(addr, level)]).await;
+    }
+
+    /*
+     * When the earliest pending level is due
+     */
+    fn next_flush(&self) -> Option<Instant> {
+        self.pending
+            .keys()
+            .filter_map(|addr| self.last_sent.get(addr))
+            .map(|last| *last + self.interval)
+            .min()
+    }
+
+    async fn flush_pending(&mut self) {
+        let now = Instant::now();
+        let interval = self.interval;
+        let due: Vec<String> = self
+            .pending
+            .keys()
+            .filter(|addr| {
+                self.last_sent
+                    .get(*addr)
+                    .map_or(true, |last| now.duration_since(*last) >= interval)
+            })
+            .cloned()
+            .collect();
+
+        let mut msgs = Vec::new();
+        for addr in due {
+            if let Some(level) = self.pending.remove(&addr) {
+                self.last_sent.insert(addr.clone(), now);
+                msgs.push(level_message(addr, level));
+            }
+        }
+        self.send(msgs).await;
+    }
+
+    async fn send(&mut self, msgs: Vec<OscMessage>) {
+        if msgs.is_empty() {
+            return;
+        }
+
+        if self.sender.is_none() {
+            if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                return;
+            }
+            let Some((remote, transport)) = self.remote.clone() else {
+                return;
+            };
+
+            let connected = tokio::time::timeout(CONNECT_TIMEOUT, async {
+                let addr = remote.resolve_async().await?;
+                OSCSender::connect(addr, transport).await
+            })
+            .await
+            .unwrap_or_else(|_| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "Connection timed out",
+                ))
+            });
+            match connected {
+                Ok(sender) => {
+                    debug!("Connected OSC feedback sender to {}", remote);
+                    self.sender = Some(sender);
+                    self.retry_at = None;
+                }
+                Err(e) => {
+                    warn!("Failed to connect OSC feedback sender to {}: {}", remote, e);
+                    self.retry_at = Some(Instant::now() + RECONNECT_BACKOFF);
+                    return;
+                }
+            }
+        }
+
+        let sender = self.sender.as_mut().unwrap();
+        for msg in msgs {
+            let Ok(packet) = encoder::encode(&OscPacket::Message(msg)) else {
+                continue;
+            };
+            if let Err(e) = sender.send(&packet).await {
+                warn!("Failed to send OSC feedback: {}", e);
+                // Reconnect on the next send (TCP remotes can go away)
+                self.sender = None;
+                return;
+            }
+        }
+    }
+}
+
+/*
+ * Sends the final level of a feature to its output parameter
+ */
+pub fn send_feature_level(output_parameter: &str, level: f64, stop: bool) {
+    queue_feedback(FeedbackCmd::Level {
+        addr: output_parameter.to_string(),
+        level: level as f32,
+        stop,
+    });
+}
+
+/*
+ * Sends a toy's connected and listening booleans
+ */
+pub fn send_toy_state(toy_name: &str, sub_id: u8, connected: bool, listening: bool) {
+    queue_feedback(FeedbackCmd::ToyState {
+        toy_name: toy_name.to_string(),
+        sub_id,
+        connected,
+        listening,
+    });
+}
+
+/*
+ * Sends messages to the remote over the feedback connection
+ */
+pub fn send_to_remote(msgs: Vec<OscMessage>) {
+    queue_feedback(FeedbackCmd::Send(msgs));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(feedback: &mut OSCFeedback, addr: &str, level: f32, stop: bool) {
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(feedback.level(addr.to_string(), level, stop));
+    }
+
+    #[test]
+    fn throttled_levels_are_kept_until_due() {
+        // No remote, sends are dropped
+        let mut feedback = OSCFeedback::new();
+        assert!(feedback.next_flush().is_none());
+
+        level(&mut feedback, "/a", 0.1, false);
+        assert!(feedback.pending.is_empty());
+
+        level(&mut feedback, "/a", 0.2, false);
+        level(&mut feedback, "/a", 0.3, false);
+        assert_eq!(feedback.pending.get("/a"), Some(&0.3));
+        let due = feedback.next_flush().unwrap();
+        assert_eq!(due, feedback.last_sent["/a"] + feedback.interval);
+
+        std::thread::sleep(due.saturating_duration_since(Instant::now()));
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(feedback.flush_pending());
+        assert!(feedback.pending.is_empty());
+        assert!(feedback.next_flush().is_none());
+    }
+
+    #[test]
+    fn toy_state_follows_the_telemetry_addresses() {
+        let mut feedback = OSCFeedback::new();
+        let addrs = |feedback: &OSCFeedback| -> Vec<String> {
+            feedback
+                .toy_state_messages("Lovense Connect Hush", 0, true, false)
+                .into_iter()
+                .map(|msg| msg.addr)
+                .collect()
+        };
+        assert_eq!(
+            addrs(&feedback),
+            [
+                "/avatar/parameters/vibecheck/osc_data/lovense_hush/0/connected",
+                "/avatar/parameters/vibecheck/osc_data/lovense_hush/0/listening",
+            ]
+        );
+
+        feedback.address_template = "/avatar/parameters/{alias}/{field}".to_string();
+        feedback
+            .aliases
+            .insert("Lovense Connect Hush".to_string(), "plug".to_string());
+        assert_eq!(
+            addrs(&feedback),
+            [
+                "/avatar/parameters/plug/connected",
+                "/avatar/parameters/plug/listening",
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_replace_pending_levels() {
+        let mut feedback = OSCFeedback::new();
+        level(&mut feedback, "/a", 0.5, false);
+        level(&mut feedback, "/a", 0.6, false);
+        assert!(!feedback.pending.is_empty());
+
+        level(&mut feedback, "/a", 0.0, true);
+        assert!(feedback.pending.is_empty());
+    }
+}
@@ -0,0 +1,464 @@
+use log::{debug, warn};
+use parking_lot::Mutex;
+use rosc::{OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{frontend::frontend_types::FeOscIngressMetrics, toy_handling::ModeProcessorInputType};
+
+use super::{
+    bundle::{unpack_packet, TimedOscMessage},
+    repeater::OSCAddressRewrite,
+};
+
+// Sent by VRChat with the new avatar's id as a string
+pub const AVATAR_CHANGE_ADDR: &str = "/avatar/change";
+
+/*
+ * OSC message after ingress validation
+ * Every handler past the listener works with this instead of a raw rosc message.
+ * The address is guaranteed to start with '/' and the value is a supported finite type.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VCOscMessage {
+    pub addr: String,
+    pub value: ModeProcessorInputType,
+}
+
+/*
+ * Why a listener refused a packet, connection or API message from a source
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SourceRejection {
+    NotAllowed,
+    ApiSecret,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum IngressRejection {
+    InvalidAddress,
+    NoArguments,
+    UnsupportedType,
+    NonFiniteFloat,
+}
+
+impl TryFrom<OscMessage> for VCOscMessage {
+    type Error = IngressRejection;
+
+    fn try_from(mut msg: OscMessage) -> Result<Self, Self::Error> {
+        // Only the avatar change itself is used, not the avatar id it carries
+        if msg.addr == AVATAR_CHANGE_ADDR {
+            return Ok(Self {
+                addr: msg.addr,
+                value: ModeProcessorInputType::Boolean(true),
+            });
+        }
+
+        // VRChat sends one argument per parameter. The last argument wins like before.
+        let value = match msg.args.pop().ok_or(IngressRejection::NoArguments)? {
+            OscType::Float(f) => ModeProcessorInputType::Float(f as f64),
+            OscType::Double(d) => ModeProcessorInputType::Float(d),
+            OscType::Bool(b) => ModeProcessorInputType::Boolean(b),
+            OscType::Int(i) => ModeProcessorInputType::Int(i),
+            _ => return Err(IngressRejection::UnsupportedType),
+        };
+
+        let vc_msg = Self {
+            addr: msg.addr,
+            value,
+        };
+        vc_msg.check()?;
+        Ok(vc_msg)
+    }
+}
+
+impl VCOscMessage {
+    /*
+     * Checks the ingress guarantees
+     * Also used for messages that did not come from a socket (eg. recordings)
+     */
+    pub fn check(&self) -> Result<(), IngressRejection> {
+        if !self.addr.starts_with('/') || self.addr.len() < 2 {
+            return Err(IngressRejection::InvalidAddress);
+        }
+
+        if let ModeProcessorInputType::Float(f) = self.value {
+            if !f.is_finite() {
+                return Err(IngressRejection::NonFiniteFloat);
+            }
+        }
+        Ok(())
+    }
+
+    /*
+     * Validates a raw OSC message and records the result in the ingress metrics
+     */
+    pub fn ingress(msg: OscMessage) -> Option<Self> {
+        let addr = msg.addr.clone();
+        match Self::try_from(msg) {
+            Ok(vc_msg) => {
+                OSC_INGRESS_METRICS.accepted.fetch_add(1, Ordering::Relaxed);
+                Some(vc_msg)
+            }
+            Err(rejection) => {
+                debug!("Rejected OSC message {:?}: {:?}", addr, rejection);
+                OSC_INGRESS_METRICS.record_rejection(rejection);
+                None
+            }
+        }
+    }
+
+    /*
+     * Applies a listener address rewrite
+     * A rewrite that would produce an invalid address is ignored
+     */
+    pub fn rewrite(&mut self, rewrite: &OSCAddressRewrite) {
+        match rewrite.apply(&self.addr) {
+            Some(addr) if addr.starts_with('/') && addr.len() >= 2 => self.addr = addr,
+            Some(addr) => debug!("Ignoring rewrite of {} to invalid {}", self.addr, addr),
+            None => {}
+        }
+    }
+}
+
+/*
+ * Decodes a received datagram
+ * Only the received bytes are decoded (not the whole receive buffer)
+ */
+pub fn decode_packet(buf: &[u8]) -> Option<OscPacket> {
+    OSC_INGRESS_METRICS.packets.fetch_add(1, Ordering::Relaxed);
+
+    match rosc::decoder::decode_udp(buf) {
+        Ok((_, pkt)) => Some(pkt),
+        Err(e) => {
+            warn!("Failed to decode OSC packet ({} bytes): {:?}", buf.len(), e);
+            OSC_INGRESS_METRICS
+                .decode_errors
+                .fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+/*
+ * Unpacks a decoded packet into validated messages
+ * Malformed messages are dropped here so they never reach the API or toy handlers
+ */
+pub fn ingress_packet(pkt: OscPacket) -> Vec<TimedOscMessage<VCOscMessage>> {
+    unpack_packet(pkt)
+        .into_iter()
+        .filter_map(|timed| {
+            Some(TimedOscMessage {
+                deliver_at: timed.deliver_at,
+                msg: VCOscMessage::ingress(timed.msg)?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct OscIngressMetrics {
+    packets: AtomicU64,
+    decode_errors: AtomicU64,
+    accepted: AtomicU64,
+    rejected_address: AtomicU64,
+    rejected_no_arguments: AtomicU64,
+    rejected_type: AtomicU64,
+    rejected_non_finite: AtomicU64,
+    rejected_source: AtomicU64,
+    rejected_api_secret: AtomicU64,
+}
+
+impl OscIngressMetrics {
+    fn record_rejection(&self, rejection: IngressRejection) {
+        let counter = match rejection {
+            IngressRejection::InvalidAddress => &self.rejected_address,
+            IngressRejection::NoArguments => &self.rejected_no_arguments,
+            IngressRejection::UnsupportedType => &self.rejected_type,
+            IngressRejection::NonFiniteFloat => &self.rejected_non_finite,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_source_rejection(&self, rejection: SourceRejection) {
+        let counter = match rejection {
+            SourceRejection::NotAllowed => &self.rejected_source,
+            SourceRejection::ApiSecret => &self.rejected_api_secret,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn to_fe(&self) -> FeOscIngressMetrics {
+        FeOscIngressMetrics {
+            packets: self.packets.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected_address: self.rejected_address.load(Ordering::Relaxed),
+            rejected_no_arguments: self.rejected_no_arguments.load(Ordering::Relaxed),
+            rejected_type: self.rejected_type.load(Ordering::Relaxed),
+            rejected_non_finite: self.rejected_non_finite.load(Ordering::Relaxed),
+            rejected_source: self.rejected_source.load(Ordering::Relaxed),
+            rejected_api_secret: self.rejected_api_secret.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Sources spoofing many addresses must not grow the warned set without bound
+const MAX_WARNED_REJECTIONS: usize = 1024;
+
+lazy_static::lazy_static! {
+    pub static ref OSC_INGRESS_METRICS: OscIngressMetrics = OscIngressMetrics::default();
+    // (listener, source, rejection) already warned about
+    static ref WARNED_REJECTIONS: Mutex<HashSet<(String, IpAddr, SourceRejection)>> = Mutex::new(HashSet::new());
+}
+
+/*
+ * Counts and logs a rejection by source
+ * Each listener, source and reason is warned about once so a flood doesn't flood the log
+ * The warned set is cleared once full, so a source may be warned about again
+ */
+pub fn reject_source(listener: &str, source: IpAddr, rejection: SourceRejection) {
+    OSC_INGRESS_METRICS.record_source_rejection(rejection);
+
+    let reason = match rejection {
+        SourceRejection::NotAllowed => "source not allowed",
+        SourceRejection::ApiSecret => "API message without the API secret",
+    };
+    let first = {
+        let mut warned = WARNED_REJECTIONS.lock();
+        if warned.len() >= MAX_WARNED_REJECTIONS {
+            warned.clear();
+        }
+        warned.insert((listener.to_string(), source, rejection))
+    };
+    if first {
+        warn!(
+            "Rejected OSC from {} on listener {}: {}",
+            source, listener, reason
+        );
+    } else {
+        debug!(
+            "Rejected OSC from {} on listener {}: {}",
+            source, listener, reason
+        );
+    }
+}
+
+/*
+ * Random OSC input shared by the ingress and API fuzz tests
+ */
+#[cfg(test)]
+pub mod fuzz {
+    use super::AVATAR_CHANGE_ADDR;
+    use rosc::{encoder, OscBundle, OscMessage, OscPacket, OscTime, OscType};
+
+    /*
+     * Small deterministic generator so failing fuzz cases can be reproduced from the seed
+     */
+    pub struct XorShift(pub u64);
+
+    impl XorShift {
+        pub fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        pub fn below(&mut self, n: usize) -> usize {
+            (self.next_u64() % n as u64) as usize
+        }
+
+        pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[self.below(items.len())]
+        }
+    }
+
+    // Toy parameters match the features of the dispatch fuzz toy
+    const ADDRESSES: [&str; 12] = [
+        "/avatar/parameters/vibecheck/api/state",
+        "/avatar/parameters/vibecheck/api/toy/lovense_hush/0/feature/vibrator/0/max_level",
+        "/avatar/parameters/vibecheck/api/anatomy/Anus/enabled",
+        "/avatar/parameters/TPS_Internal/Pen/Orf_Root/Length",
+        "/avatar/parameters/TPS_Internal/Orf/Hole/Depth_In",
+        "/avatar/parameters/OGB/Orf/Hole/PenSelfNewRoot",
+        "/avatar/parameters/fuzz/level",
+        "/avatar/parameters/fuzz/gesture",
+        AVATAR_CHANGE_ADDR,
+        "/",
+        "",
+        "no/leading/slash",
+    ];
+
+    pub fn random_arg(rng: &mut XorShift) -> OscType {
+        match rng.below(8) {
+            0 => OscType::Float(*rng.pick(&[0.0, 0.5, 1.0, -1.0, f32::NAN, f32::INFINITY])),
+            1 => OscType::Double(*rng.pick(&[0.25, f64::NEG_INFINITY, f64::NAN])),
+            2 => OscType::Bool(rng.next_u64() % 2 == 0),
+            3 => OscType::Int(rng.next_u64() as i32),
+            4 => OscType::String("avtr_00000000-0000-0000-0000-000000000000".to_string()),
+            5 => OscType::Nil,
+            6 => OscType::Long(rng.next_u64() as i64),
+            _ => OscType::Blob(vec![0; rng.below(8)]),
+        }
+    }
+
+    pub fn random_message(rng: &mut XorShift) -> OscMessage {
+        OscMessage {
+            addr: rng.pick(&ADDRESSES).to_string(),
+            args: (0..rng.below(3)).map(|_| random_arg(rng)).collect(),
+        }
+    }
+
+    pub fn random_packet(rng: &mut XorShift, depth: usize) -> OscPacket {
+        if depth > 2 || rng.below(3) > 0 {
+            return OscPacket::Message(random_message(rng));
+        }
+        OscPacket::Bundle(OscBundle {
+            timetag: OscTime {
+                seconds: *rng.pick(&[0, 1, u32::MAX]),
+                fractional: 1,
+            },
+            content: (0..rng.below(4))
+                .map(|_| random_packet(rng, depth + 1))
+                .collect(),
+        })
+    }
+
+    /*
+     * Random packets, encoded and then randomly corrupted
+     */
+    pub fn random_datagrams(seed: u64, count: usize) -> Vec<Vec<u8>> {
+        let mut rng = XorShift(seed);
+        (0..count)
+            .map(|_| {
+                let mut raw = encoder::encode(&random_packet(&mut rng, 0)).unwrap_or_default();
+                match rng.below(4) {
+                    0 if !raw.is_empty() => {
+                        let i = rng.below(raw.len());
+                        raw[i] = rng.next_u64() as u8;
+                    }
+                    1 => raw.truncate(rng.below(raw.len() + 1)),
+                    2 => raw = (0..rng.below(64)).map(|_| rng.next_u64() as u8).collect(),
+                    _ => {}
+                }
+                raw
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzz::random_datagrams, *};
+    use rosc::{encoder, OscBundle, OscTime};
+
+    fn ingress_one(msg: OscMessage) -> Result<VCOscMessage, IngressRejection> {
+        VCOscMessage::try_from(msg)
+    }
+
+    #[test]
+    fn rejects_messages_without_arguments() {
+        let msg = OscMessage {
+            addr: "/avatar/parameters/a".to_string(),
+            args: vec![],
+        };
+        assert!(matches!(
+            ingress_one(msg),
+            Err(IngressRejection::NoArguments)
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_addresses_and_non_finite_floats() {
+        for addr in ["", "/", "avatar/parameters/a"] {
+            let msg = OscMessage {
+                addr: addr.to_string(),
+                args: vec![OscType::Float(1.0)],
+            };
+            assert!(matches!(
+                ingress_one(msg),
+                Err(IngressRejection::InvalidAddress)
+            ));
+        }
+
+        let msg = OscMessage {
+            addr: "/avatar/parameters/a".to_string(),
+            args: vec![OscType::Float(f32::NAN)],
+        };
+        assert!(matches!(
+            ingress_one(msg),
+            Err(IngressRejection::NonFiniteFloat)
+        ));
+    }
+
+    #[test]
+    fn accepts_avatar_change_with_avatar_id() {
+        let msg = OscMessage {
+            addr: AVATAR_CHANGE_ADDR.to_string(),
+            args: vec![OscType::String("avtr_1234".to_string())],
+        };
+        let vc_msg = ingress_one(msg).unwrap();
+        assert_eq!(vc_msg.addr, AVATAR_CHANGE_ADDR);
+    }
+
+    #[test]
+    fn rejects_strings_on_parameters() {
+        let msg = OscMessage {
+            addr: "/avatar/parameters/a".to_string(),
+            args: vec![OscType::String("x".to_string())],
+        };
+        assert!(matches!(
+            ingress_one(msg),
+            Err(IngressRejection::UnsupportedType)
+        ));
+    }
+
+    #[test]
+    fn unpacks_nested_bundles() {
+        let message = |addr: &str| {
+            OscPacket::Message(OscMessage {
+                addr: addr.to_string(),
+                args: vec![OscType::Int(2)],
+            })
+        };
+        let pkt = OscPacket::Bundle(OscBundle {
+            timetag: OscTime {
+                seconds: 0,
+                fractional: 1,
+            },
+            content: vec![
+                message("/a"),
+                OscPacket::Bundle(OscBundle {
+                    timetag: OscTime {
+                        seconds: 0,
+                        fractional: 1,
+                    },
+                    content: vec![message("/b"), message("")],
+                }),
+            ],
+        });
+        let raw = encoder::encode(&pkt).unwrap();
+        let msgs = ingress_packet(decode_packet(&raw).unwrap());
+        let addrs: Vec<&str> = msgs.iter().map(|m| m.msg.addr.as_str()).collect();
+        assert_eq!(addrs, ["/a", "/b"]);
+    }
+
+    #[test]
+    fn fuzz_ingress_keeps_its_guarantees() {
+        for seed in 1..=64 {
+            for raw in random_datagrams(seed, 256) {
+                let Some(pkt) = decode_packet(&raw) else {
+                    continue;
+                };
+                for timed in ingress_packet(pkt) {
+                    assert!(timed.msg.check().is_ok(), "seed {}: {:?}", seed, timed.msg);
+                }
+            }
+        }
+    }
+}
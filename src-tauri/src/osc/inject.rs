@@ -0,0 +1,218 @@
+/*
+ * In-process OSC input injection
+ * Synthetic messages go straight into the dispatch pipeline so the whole mapping chain can be tested without a socket or VRChat.
+ */
+use log::{info, warn};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::{
+    frontend::frontend_types::FeOSCInjectStep,
+    osc_api::osc_api::dispatch_internal_osc_message,
+    util::stoppable::{StopFlag, StoppableThread},
+    vcore::vcerror::frontend::VCFeError,
+};
+
+use super::ingress::VCOscMessage;
+
+static OSC_INJECT: StoppableThread = StoppableThread::new();
+
+/*
+ * Injects a single message
+ * The message goes through the same checks as a received one
+ */
+pub fn inject_osc_message(msg: VCOscMessage, app_handle: &AppHandle) -> Result<(), VCFeError> {
+    if msg.check().is_err() {
+        return Err(VCFeError::InvalidInjectedMessage);
+    }
+
+    if dispatch_internal_osc_message(msg, app_handle) {
+        Ok(())
+    } else {
+        Err(VCFeError::NotListening)
+    }
+}
+
+/*
+ * Injects a scripted sequence of messages in the background
+ * Only one sequence runs at a time
+ */
+pub fn start_injection_sequence(
+    steps: Vec<FeOSCInjectStep>,
+    app_handle: AppHandle,
+) -> Result<(), VCFeError> {
+    let steps = sequence_steps(steps)?;
+
+    let started = OSC_INJECT.start(move |stop| {
+        let step_count = steps.len();
+        let injected = inject_sequence(steps, stop, |msg| {
+            dispatch_internal_osc_message(msg, &app_handle)
+        });
+        info!("Injected {}/{} OSC sequence steps", injected, step_count);
+    });
+
+    if started {
+        Ok(())
+    } else {
+        Err(VCFeError::InjectionActive)
+    }
+}
+
+pub fn stop_injection_sequence() -> Result<(), VCFeError> {
+    if OSC_INJECT.stop() {
+        Ok(())
+    } else {
+        Err(VCFeError::InjectionNotActive)
+    }
+}
+
+/*
+ * Validates every step up front so a bad step does not stop a sequence halfway
+ */
+fn sequence_steps(steps: Vec<FeOSCInjectStep>) -> Result<Vec<(Duration, VCOscMessage)>, VCFeError> {
+    steps
+        .into_iter()
+        .map(|step| {
+            let msg = VCOscMessage {
+                addr: step.addr,
+                value: step.value,
+            };
+            match msg.check() {
+                Ok(()) => Ok((Duration::from_millis(step.delay_ms), msg)),
+                Err(_) => Err(VCFeError::InvalidInjectedMessage),
+            }
+        })
+        .collect()
+}
+
+fn inject_sequence(
+    steps: Vec<(Duration, VCOscMessage)>,
+    stop: &StopFlag,
+    mut dispatch: impl FnMut(VCOscMessage) -> bool,
+) -> usize {
+    let mut injected = 0;
+
+    for (delay, msg) in steps {
+        if !stop.sleep(delay) {
+            return injected;
+        }
+
+        if !dispatch(msg) {
+            warn!("Stopping OSC injection: VibeCheck is not listening");
+            return injected;
+        }
+        injected += 1;
+    }
+
+    injected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy_handling::ModeProcessorInputType;
+
+    fn step(delay_ms: u64, addr: &str, value: ModeProcessorInputType) -> FeOSCInjectStep {
+        FeOSCInjectStep {
+            delay_ms,
+            addr: addr.to_string(),
+            value,
+        }
+    }
+
+    fn valid_steps() -> Vec<FeOSCInjectStep> {
+        vec![
+            step(
+                0,
+                "/avatar/parameters/a",
+                ModeProcessorInputType::Float(0.5),
+            ),
+            step(
+                1,
+                "/avatar/parameters/b",
+                ModeProcessorInputType::Boolean(true),
+            ),
+            step(2, "/avatar/parameters/c", ModeProcessorInputType::Int(3)),
+        ]
+    }
+
+    #[test]
+    fn validates_sequence_steps() {
+        let Ok(steps) = sequence_steps(valid_steps()) else {
+            panic!("valid sequence was rejected");
+        };
+        let steps: Vec<(u128, &str)> = steps
+            .iter()
+            .map(|(delay, msg)| (delay.as_millis(), msg.addr.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (0, "/avatar/parameters/a"),
+                (1, "/avatar/parameters/b"),
+                (2, "/avatar/parameters/c"),
+            ]
+        );
+        assert!(matches!(sequence_steps(vec![]), Ok(steps) if steps.is_empty()));
+    }
+
+    #[test]
+    fn one_invalid_step_rejects_the_sequence() {
+        for invalid in [
+            step(0, "avatar/parameters/a", ModeProcessorInputType::Float(0.5)),
+            step(0, "/", ModeProcessorInputType::Boolean(true)),
+            step(0, "", ModeProcessorInputType::Int(1)),
+            step(
+                0,
+                "/avatar/parameters/a",
+                ModeProcessorInputType::Float(f64::NAN),
+            ),
+            step(
+                0,
+                "/avatar/parameters/a",
+                ModeProcessorInputType::Float(f64::INFINITY),
+            ),
+        ] {
+            let mut steps = valid_steps();
+            steps.insert(1, invalid);
+            assert!(matches!(
+                sequence_steps(steps),
+                Err(VCFeError::InvalidInjectedMessage)
+            ));
+        }
+    }
+
+    #[test]
+    fn injects_until_dispatch_fails_or_stopped() {
+        let Ok(steps) = sequence_steps(valid_steps()) else {
+            panic!("valid sequence was rejected");
+        };
+
+        let mut dispatched = Vec::new();
+        let injected = inject_sequence(steps.clone(), &StopFlag::new(), |msg| {
+            dispatched.push(msg.addr);
+            true
+        });
+        assert_eq!(injected, 3);
+        assert_eq!(
+            dispatched,
+            [
+                "/avatar/parameters/a",
+                "/avatar/parameters/b",
+                "/avatar/parameters/c"
+            ]
+        );
+
+        // Not listening anymore
+        let mut calls = 0;
+        let injected = inject_sequence(steps.clone(), &StopFlag::new(), |_| {
+            calls += 1;
+            calls < 2
+        });
+        assert_eq!((injected, calls), (1, 2));
+
+        let stop = StopFlag::new();
+        stop.stop();
+        assert_eq!(inject_sequence(steps, &stop, |_| true), 0);
+    }
+}
@@ -0,0 +1,389 @@
+/*
+ * Parameter learn mode
+ * Watches received OSC for a few seconds and proposes the parameter that changed the most for a toy feature.
+ * The proposal is only saved to the feature once the frontend confirms it.
+ */
+use log::info;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    frontend::{
+        frontend_types::{FeLearnProposal, FeLearnResult, FeToyFeatureRef},
+        ToFrontend,
+    },
+    toy_handling::{toyops::ProcessingMode, ModeProcessorInputType},
+    vcore::vcerror::frontend::VCFeError,
+};
+
+use super::ingress::VCOscMessage;
+
+const MIN_LEARN_DURATION: Duration = Duration::from_secs(1);
+const MAX_LEARN_DURATION: Duration = Duration::from_secs(30);
+pub const DEFAULT_LEARN_DURATION: Duration = Duration::from_secs(5);
+
+// Avatar parameters that change constantly without the user doing anything on purpose
+const NOISY_PARAMETERS: &[&str] = &[
+    "VelocityX",
+    "VelocityY",
+    "VelocityZ",
+    "VelocityMagnitude",
+    "AngularX",
+    "AngularY",
+    "AngularZ",
+    "Upright",
+    "Grounded",
+    "Voice",
+    "Viseme",
+    "Seated",
+    "AFK",
+    "InStation",
+    "TrackingType",
+    "VRMode",
+    "MuteSelf",
+    "Earmuffs",
+];
+
+struct LearnedParameter {
+    last: ModeProcessorInputType,
+    change: f64,
+}
+
+struct LearnSession {
+    id: u64,
+    feature: FeToyFeatureRef,
+    params: HashMap<String, LearnedParameter>,
+}
+
+impl LearnSession {
+    fn observe<'a>(&mut self, msgs: impl Iterator<Item = &'a VCOscMessage>) {
+        for msg in msgs {
+            if !msg.addr.starts_with("/avatar/parameters/")
+                || msg.addr.starts_with("/avatar/parameters/vibecheck/")
+                || is_noisy(&msg.addr)
+            {
+                continue;
+            }
+
+            match self.params.get_mut(&msg.addr) {
+                Some(param) => {
+                    param.change += value_change(param.last, msg.value);
+                    param.last = msg.value;
+                }
+                None => {
+                    self.params.insert(
+                        msg.addr.clone(),
+                        LearnedParameter {
+                            last: msg.value,
+                            change: 0.0,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub struct LearnProposal {
+    pub feature: FeToyFeatureRef,
+    pub addr: String,
+    pub processing_mode: ProcessingMode,
+}
+
+// Checked before taking the session lock so listeners don't contend on it while not learning
+static LEARNING: AtomicBool = AtomicBool::new(false);
+static LEARN_SESSION_ID: AtomicU64 = AtomicU64::new(0);
+
+lazy_static::lazy_static! {
+    static ref LEARN_SESSION: Mutex<Option<LearnSession>> = Mutex::new(None);
+    static ref LEARN_PROPOSAL: Mutex<Option<LearnProposal>> = Mutex::new(None);
+}
+
+fn is_noisy(addr: &str) -> bool {
+    let name = addr.rsplit('/').next().unwrap_or(addr);
+    NOISY_PARAMETERS.contains(&name)
+}
+
+/*
+ * How much a value moved
+ * Floats count their distance, a bool flip or int change counts as a full step
+ */
+fn value_change(last: ModeProcessorInputType, new: ModeProcessorInputType) -> f64 {
+    match (last, new) {
+        (ModeProcessorInputType::Float(a), ModeProcessorInputType::Float(b)) => (b - a).abs(),
+        (ModeProcessorInputType::Boolean(a), ModeProcessorInputType::Boolean(b)) => {
+            if a != b {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        (ModeProcessorInputType::Int(a), ModeProcessorInputType::Int(b)) => {
+            if a != b {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        // Type changed, treat as a change
+        _ => 1.0,
+    }
+}
+
+fn proposed_processing_mode(value: ModeProcessorInputType) -> ProcessingMode {
+    match value {
+        ModeProcessorInputType::Boolean(_) => ProcessingMode::Constant,
+        ModeProcessorInputType::Float(_) | ModeProcessorInputType::Int(_) => ProcessingMode::Raw,
+    }
+}
+
+/*
+ * Starts learning a parameter for a feature
+ * The result is emitted as fe_learn_result once the duration is over
+ */
+pub fn start_learning(
+    feature: FeToyFeatureRef,
+    duration: Duration,
+    app_handle: AppHandle,
+) -> Result<(), VCFeError> {
+    let duration = duration.clamp(MIN_LEARN_DURATION, MAX_LEARN_DURATION);
+    let id = LEARN_SESSION_ID.fetch_add(1, Ordering::Relaxed) + 1;
+
+    {
+        let mut session = LEARN_SESSION.lock();
+        if session.is_some() {
+            return Err(VCFeError::LearnActive);
+        }
+        info!(
+            "Learning parameter for {} feature {} for {:?}",
+            feature.toy_name, feature.feature_index, duration
+        );
+        *session = Some(LearnSession {
+            id,
+            feature,
+            params: HashMap::new(),
+        });
+        *LEARN_PROPOSAL.lock() = None;
+        LEARNING.store(true, Ordering::Relaxed);
+    }
+
+    thread::spawn(move || {
+        thread::sleep(duration);
+
+        let session = {
+            let mut session = LEARN_SESSION.lock();
+            // Cancelled or replaced by a newer session
+            if !session.as_ref().is_some_and(|s| s.id == id) {
+                return;
+            }
+            LEARNING.store(false, Ordering::Relaxed);
+            session.take().unwrap()
+        };
+
+        let result = finish_learning(session);
+        let _ = app_handle.emit_all("fe_learn_result", result);
+    });
+
+    Ok(())
+}
+
+pub fn cancel_learning() -> Result<(), VCFeError> {
+    let mut session = LEARN_SESSION.lock();
+    if session.take().is_none() {
+        return Err(VCFeError::LearnNotActive);
+    }
+    LEARNING.store(false, Ordering::Relaxed);
+    Ok(())
+}
+
+/*
+ * Tracks value changes of received messages for the active session
+ */
+pub fn learn_osc_messages<'a>(msgs: impl Iterator<Item = &'a VCOscMessage>) {
+    if !LEARNING.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Some(session) = LEARN_SESSION.lock().as_mut() {
+        session.observe(msgs);
+    }
+}
+
+/*
+ * Picks the parameter that changed the most and keeps it as the pending proposal
+ */
+fn finish_learning(session: LearnSession) -> FeLearnResult {
+    let best = session
+        .params
+        .into_iter()
+        .filter(|(_, param)| param.change > 0.0)
+        .max_by(|(_, a), (_, b)| a.change.total_cmp(&b.change));
+
+    let proposal = best.map(|(addr, param)| {
+        info!("Learned parameter {} (change {:.2})", addr, param.change);
+        LearnProposal {
+            feature: session.feature.clone(),
+            addr,
+            processing_mode: proposed_processing_mode(param.last),
+        }
+    });
+
+    let result = FeLearnResult {
+        feature: session.feature.clone(),
+        proposal: proposal.as_ref().map(|p| FeLearnProposal {
+            addr: p.addr.clone(),
+            processing_mode: p.processing_mode.to_frontend(),
+        }),
+    };
+
+    *LEARN_PROPOSAL.lock() = proposal;
+    result
+}
+
+/*
+ * Takes the pending proposal so it can be saved
+ */
+pub fn take_learn_proposal() -> Result<LearnProposal, VCFeError> {
+    LEARN_PROPOSAL
+        .lock()
+        .take()
+        .ok_or(VCFeError::NoLearnProposal)
+}
+
+pub fn discard_learn_proposal() {
+    *LEARN_PROPOSAL.lock() = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::frontend_types::{FeProcessingMode, FeVCFeatureType};
+
+    fn new_session() -> LearnSession {
+        LearnSession {
+            id: 0,
+            feature: FeToyFeatureRef {
+                toy_id: Some(1),
+                toy_name: "Hush".to_string(),
+                feature_index: 0,
+                feature_type: FeVCFeatureType::Vibrator,
+            },
+            params: HashMap::new(),
+        }
+    }
+
+    fn msg(addr: &str, value: ModeProcessorInputType) -> VCOscMessage {
+        VCOscMessage {
+            addr: addr.to_string(),
+            value,
+        }
+    }
+
+    fn floats(addr: &str, values: &[f64]) -> Vec<VCOscMessage> {
+        values
+            .iter()
+            .map(|v| msg(addr, ModeProcessorInputType::Float(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn proposes_the_most_changed_parameter() {
+        let mut session = new_session();
+        session.observe(floats("/avatar/parameters/small", &[0.0, 0.2, 0.0]).iter());
+        session.observe(floats("/avatar/parameters/large", &[0.0, 0.5, 0.0, 0.5]).iter());
+        session.observe(floats("/avatar/parameters/still", &[0.7, 0.7, 0.7]).iter());
+
+        let result = finish_learning(session);
+        let Some(proposal) = result.proposal else {
+            panic!("nothing was proposed");
+        };
+        assert_eq!(proposal.addr, "/avatar/parameters/large");
+        assert!(matches!(proposal.processing_mode, FeProcessingMode::Raw));
+        assert_eq!(result.feature.toy_name, "Hush");
+    }
+
+    #[test]
+    fn skips_noisy_and_foreign_parameters() {
+        let mut session = new_session();
+        session.observe(floats("/avatar/parameters/VelocityX", &[0.0, 5.0, -5.0]).iter());
+        session.observe(floats("/avatar/parameters/Viseme", &[0.0, 9.0, 3.0]).iter());
+        session.observe(floats("/avatar/parameters/vibecheck/api/state", &[0.0, 1.0]).iter());
+        session.observe(floats("/input/Vertical", &[0.0, 1.0, 0.0]).iter());
+        session.observe(floats("/avatar/parameters/Touch", &[0.0, 0.1]).iter());
+
+        assert_eq!(
+            session.params.keys().collect::<Vec<_>>(),
+            ["/avatar/parameters/Touch"]
+        );
+        let proposal = finish_learning(session).proposal.map(|p| p.addr);
+        assert_eq!(proposal.as_deref(), Some("/avatar/parameters/Touch"));
+    }
+
+    #[test]
+    fn unchanged_parameters_are_not_proposed() {
+        let mut session = new_session();
+        session.observe(floats("/avatar/parameters/still", &[0.3, 0.3]).iter());
+        session.observe(
+            [msg(
+                "/avatar/parameters/once",
+                ModeProcessorInputType::Int(2),
+            )]
+            .iter(),
+        );
+
+        assert!(finish_learning(session).proposal.is_none());
+    }
+
+    #[test]
+    fn proposes_constant_for_bools_and_raw_otherwise() {
+        let mut session = new_session();
+        session.observe(
+            [true, false, true]
+                .map(|b| {
+                    msg(
+                        "/avatar/parameters/toggle",
+                        ModeProcessorInputType::Boolean(b),
+                    )
+                })
+                .iter(),
+        );
+        let Some(proposal) = finish_learning(session).proposal else {
+            panic!("bool toggle was not proposed");
+        };
+        assert_eq!(proposal.addr, "/avatar/parameters/toggle");
+        assert!(matches!(
+            proposal.processing_mode,
+            FeProcessingMode::Constant
+        ));
+
+        let mut session = new_session();
+        session.observe(
+            [0, 2, 3]
+                .map(|i| msg("/avatar/parameters/gesture", ModeProcessorInputType::Int(i)))
+                .iter(),
+        );
+        let Some(proposal) = finish_learning(session).proposal else {
+            panic!("int change was not proposed");
+        };
+        assert!(matches!(proposal.processing_mode, FeProcessingMode::Raw));
+    }
+
+    #[test]
+    fn measures_value_changes() {
+        use ModeProcessorInputType::*;
+
+        assert_eq!(value_change(Float(0.25), Float(0.75)), 0.5);
+        assert_eq!(value_change(Float(0.75), Float(0.25)), 0.5);
+        assert_eq!(value_change(Boolean(true), Boolean(false)), 1.0);
+        assert_eq!(value_change(Boolean(true), Boolean(true)), 0.0);
+        assert_eq!(value_change(Int(1), Int(5)), 1.0);
+        assert_eq!(value_change(Int(5), Int(5)), 0.0);
+        assert_eq!(value_change(Int(1), Float(1.0)), 1.0);
+    }
+}
@@ -1,271 +1,208 @@
-use futures_timer::Delay;
-use log::{error as logerr, info, trace, warn};
-use parking_lot::Mutex;
-use rosc::encoder;
-use rosc::OscType;
-use rosc::{self, OscMessage, OscPacket};
+use log::{error as logerr, info};
+use rosc::{self, OscPacket};
 
-use tauri::AppHandle;
-use tauri::Manager;
-use tokio::net::UdpSocket as tUdpSocket;
+use tauri::{AppHandle, Manager};
 
-use tokio::sync::broadcast::Sender as BSender;
-use tokio::sync::mpsc::UnboundedSender;
-
-use std::net::Ipv4Addr;
-use std::net::UdpSocket;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::net::{TcpListener, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
-use crate::frontend::frontend_types::FeCoreEvent;
-use crate::frontend::frontend_types::FeToyEvent;
-use crate::frontend::frontend_types::FeVCToy;
-use crate::frontend::ToFrontend;
+use crate::frontend::frontend_types::FeOSCBindFailure;
+use crate::osc::ingress::{decode_packet, reject_source, SourceRejection};
+use crate::osc::repeater::OSCRepeater;
+use crate::osc::tcp::{tcp_listener_routine, OSCTransport};
 use crate::osc_api::osc_api::vibecheck_osc_api;
-use crate::toy_handling::ToyPower;
-use crate::toy_handling::ToySig;
-use crate::vcore::config::OSCNetworking;
-use crate::vcore::core::TmSig;
-use crate::vcore::core::ToyManagementEvent;
-use crate::vcore::core::VibeCheckState;
+use crate::vcore::config::{OSCListener, OSCNetworking, OSCRepeaterTarget};
+
+// How often blocked listener threads check if they should stop
+pub const LISTENER_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 /*
-    This subroutine
-    Binds the OSC listen socket
-    receives OSC messages
-    broadcasts the OSC messages to each toy
-*/
-pub fn toy_input_routine(
-    toy_bcst_tx: BSender<ToySig>,
-    tme_send: UnboundedSender<ToyManagementEvent>,
-    app_handle: AppHandle,
-    vc_config: OSCNetworking,
-) {
-    let bind_sock =
-        match UdpSocket::bind(format!("{}:{}", vc_config.bind.ip(), vc_config.bind.port())) {
-            Ok(s) => {
-                let _ = tme_send.send(ToyManagementEvent::Sig(TmSig::Listening));
-                s
-            }
-            Err(_e) => {
-                let _ = tme_send.send(ToyManagementEvent::Sig(TmSig::BindError));
-                return;
+ * The OSC listen sockets
+ * Bound once at startup and only rebound when the listeners or repeater targets change,
+ * so enabling and disabling VibeCheck never races a rebind.
+ * Every received message goes through the same dispatch in every state, toy messages are dropped while disabled.
+ * Listeners that fail to bind are emitted as fe_osc_bind_failures (empty when all are bound).
+ */
+pub struct OSCListeners {
+    bind: Vec<OSCListener>,
+    repeater: Vec<OSCRepeaterTarget>,
+    bound: usize,
+    bind_failures: Vec<FeOSCBindFailure>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl OSCListeners {
+    pub fn bind(networking: &OSCNetworking, app_handle: &AppHandle) -> Self {
+        let udp_binds: Vec<SocketAddr> = networking
+            .bind
+            .iter()
+            .filter(|l| l.transport == OSCTransport::Udp)
+            .map(|l| l.address)
+            .collect();
+        let repeater = Arc::new(OSCRepeater::new(&networking.repeater, &udp_binds));
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut threads = Vec::with_capacity(networking.bind.len());
+        let mut bind_failures = Vec::new();
+
+        for listener in &networking.bind {
+            let name = listener.name.clone();
+            let address = listener.address;
+
+            let spawned = match listener.transport {
+                OSCTransport::Udp => spawn_udp_listener(
+                    listener.clone(),
+                    repeater.clone(),
+                    app_handle.clone(),
+                    stop.clone(),
+                ),
+                OSCTransport::Tcp(framing) => TcpListener::bind(address).map(|tcp_listener| {
+                    let listener = listener.clone();
+                    let repeater = repeater.clone();
+                    let app_handle = app_handle.clone();
+                    let stop = stop.clone();
+                    thread::spawn(move || {
+                        tcp_listener_routine(
+                            tcp_listener,
+                            listener,
+                            framing,
+                            repeater,
+                            app_handle,
+                            stop,
+                        )
+                    })
+                }),
+            };
+
+            match spawned {
+                Ok(listener_thread) => {
+                    info!("Listener {} is bound {}", name, address);
+                    threads.push(listener_thread);
+                }
+                Err(e) => {
+                    logerr!("Failed to bind OSC listener {} to {}: {}", name, address, e);
+                    bind_failures.push(FeOSCBindFailure {
+                        name,
+                        address: address.to_string(),
+                        error: e.to_string(),
+                    });
+                }
             }
-        };
-    info!(
-        "Listen sock is bound {} : {}",
-        vc_config.bind.ip(),
-        vc_config.bind.port()
-    );
-    bind_sock.set_nonblocking(false).unwrap();
-    let _ = bind_sock.set_read_timeout(Some(Duration::from_secs(1)));
+        }
 
-    loop {
-        // try recv OSC packet
-        // parse OSC packet
-        // Send address and arg to broadcast channel
-        // Die when channel disconnects
+        let _ = app_handle.emit_all("fe_osc_bind_failures", bind_failures.clone());
 
-        if !vibecheck_osc_api(&bind_sock, &app_handle, &toy_bcst_tx) {
-            return;
+        Self {
+            bind: networking.bind.clone(),
+            repeater: networking.repeater.clone(),
+            bound: threads.len(),
+            bind_failures,
+            stop,
+            threads,
         }
     }
-}
 
-pub async fn vc_disabled_osc_command_listen(app_handle: AppHandle, vc_config: OSCNetworking) {
-    info!("Listening for OSC commands while disabled");
-    let mut retries = 3;
-    let sock;
-    loop {
-        Delay::new(Duration::from_secs(1)).await;
-        match tUdpSocket::bind(format!("{}:{}", vc_config.bind.ip(), vc_config.bind.port())).await {
-            Ok(s) => {
-                info!("Listening while disabled");
-                sock = s;
-                break;
-            }
-            Err(_e) => {
-                logerr!(
-                    "Failed to bind UDP socket for disabled cmd listening.. Retries remaining: {}",
-                    retries
-                );
-                if retries == 0 {
-                    return;
-                }
-                retries -= 1;
-                continue;
-            }
-        };
+    pub fn any_bound(&self) -> bool {
+        self.bound > 0
     }
 
-    loop {
-        let mut buf = [0u8; rosc::decoder::MTU];
+    pub fn bind_failures(&self) -> &[FeOSCBindFailure] {
+        &self.bind_failures
+    }
 
-        let (br, _a) = match sock.recv_from(&mut buf).await {
-            Ok((br, a)) => (br, a),
-            Err(_e) => {
-                logerr!("Failed to receive data");
-                continue;
-            }
-        };
+    /*
+     * Every listener is bound from these settings
+     */
+    pub fn is_current(&self, networking: &OSCNetworking) -> bool {
+        self.bound == self.bind.len()
+            && self.bind == networking.bind
+            && self.repeater == networking.repeater
+    }
 
-        if br == 0 {
-            continue;
-        } else {
-            let pkt = match rosc::decoder::decode_udp(&buf) {
-                Ok(pkt) => pkt,
-                Err(_e) => {
-                    logerr!("Failed to parse OSC packet");
-                    continue;
-                }
-            };
+    /*
+     * Stops the listener threads and waits for them so their sockets are closed before rebinding
+     */
+    pub fn shutdown(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for listener_thread in self.threads.drain(..) {
+            let _ = listener_thread.join();
+        }
+        info!("OSC listeners stopped");
+    }
+}
 
-            match pkt.1 {
-                OscPacket::Message(mut msg) => {
-                    if msg.addr == "/avatar/parameters/vibecheck/state" {
-                        if let Some(state_bool) = msg.args.pop().unwrap().bool() {
-                            if state_bool {
-                                info!("Sending EnableAndScan event");
-                                let _ = app_handle.emit_all("fe_core_event", FeCoreEvent::State(crate::frontend::frontend_types::FeStateEvent::EnableAndScan));
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    info!("Didn't get OscPacket::Message, skipping..");
-                }
-            }
+impl Drop for OSCListeners {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn spawn_udp_listener(
+    listener: OSCListener,
+    repeater: Arc<OSCRepeater>,
+    app_handle: AppHandle,
+    stop: Arc<AtomicBool>,
+) -> io::Result<JoinHandle<()>> {
+    let bind_sock = UdpSocket::bind(listener.address)?;
+    bind_sock.set_nonblocking(false)?;
+    let _ = bind_sock.set_read_timeout(Some(LISTENER_POLL_INTERVAL));
+
+    Ok(thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            vibecheck_osc_api(&bind_sock, &listener, &repeater, &app_handle);
         }
+        info!("Listener {} stopped", listener.name);
+    }))
+}
+
+/*
+ * Checks a datagram source against the listener allowlist
+ */
+fn accept_source(listener: &OSCListener, source: IpAddr) -> bool {
+    if listener.allows(source) {
+        true
+    } else {
+        reject_source(&listener.name, source, SourceRejection::NotAllowed);
+        false
     }
 }
 
+/*
+ * Receives and decodes a single datagram with its source
+ * Datagrams from sources not allowed on the listener are dropped before the repeater sees them.
+ * The datagram is handed to the repeater before VibeCheck handles it
+ */
 #[inline]
-pub fn recv_osc_cmd(sock: &UdpSocket) -> Option<OscMessage> {
+pub fn recv_osc_cmd(
+    sock: &UdpSocket,
+    listener: &OSCListener,
+    repeater: &OSCRepeater,
+) -> Option<(OscPacket, IpAddr)> {
     let mut buf = [0u8; rosc::decoder::MTU];
 
-    let (br, _a) = match sock.recv_from(&mut buf) {
+    let (br, a) = match sock.recv_from(&mut buf) {
         Ok((br, a)) => (br, a),
         Err(_e) => {
             return None;
         }
     };
 
-    if br == 0 {
+    if br == 0 || !accept_source(listener, a.ip()) {
         return None;
     }
-    let pkt = rosc::decoder::decode_udp(&buf).ok()?;
-    match pkt.1 {
-        OscPacket::Message(msg) => Some(msg),
-        _ => None,
-    }
+    ingress_datagram(&buf[..br], repeater).map(|pkt| (pkt, a.ip()))
 }
 
-/* FUTURE MAYBE
- * Toy update loop every 1 sec maybe 5
- * How to do parameter structure
- * /avatar/parameters/toy_name
- *
- * ATM this only sends a battery life OSC address/value.
+/*
+ * Decodes a received datagram (or TCP frame) after handing it to the repeater
  */
-
-pub async fn toy_refresh(
-    vibecheck_state_pointer: Arc<Mutex<VibeCheckState>>,
-    app_handle: AppHandle,
-) {
-    loop {
-        Delay::new(Duration::from_secs(15)).await;
-
-        let (toys, remote) = {
-            let vc_lock = vibecheck_state_pointer.lock();
-            if !vc_lock
-                .core_toy_manager
-                .as_ref()
-                .unwrap()
-                .online_toys
-                .is_empty()
-            {
-                (
-                    vc_lock
-                        .core_toy_manager
-                        .as_ref()
-                        .unwrap()
-                        .online_toys
-                        .clone(),
-                    vc_lock.config.networking.remote,
-                )
-            } else {
-                continue;
-            }
-        };
-
-        let sock = tUdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.unwrap();
-        info!(
-            "Bound toy_refresh sender sock to {}",
-            sock.local_addr().unwrap()
-        );
-        sock.connect(remote).await.unwrap();
-        for (.., mut toy) in toys {
-            // Can use this to differ between toys with batteries and toys without!
-            let toy_power = if toy.device_handle.has_battery_level() {
-                match toy.device_handle.battery_level().await {
-                    Ok(battery_lvl) => ToyPower::Battery(battery_lvl),
-                    Err(_e) => {
-                        warn!("Device battery_level() error: {:?}", _e);
-                        ToyPower::Pending
-                    }
-                }
-            } else {
-                ToyPower::NoBattery
-            };
-
-            toy.toy_power = toy_power.clone();
-
-            let _ = app_handle.emit_all(
-                "fe_toy_event",
-                FeToyEvent::Update({
-                    FeVCToy {
-                        toy_id: Some(toy.toy_id),
-                        toy_name: toy.toy_name.clone(),
-                        toy_anatomy: toy.config.as_ref().unwrap().anatomy.to_fe(),
-                        toy_power: toy_power.clone(),
-                        toy_connected: toy.toy_connected,
-                        features: toy.parsed_toy_features.features.to_frontend(),
-                        listening: toy.listening,
-                        osc_data: toy.osc_data,
-                        sub_id: toy.sub_id,
-                    }
-                }),
-            );
-
-            if toy.osc_data {
-                trace!("Sending OSC data for toy: {}", toy.toy_name);
-
-                let battery_level_msg = encoder::encode(&OscPacket::Message(OscMessage {
-                    addr: format!(
-                        "/avatar/parameters/vibecheck/osc_data/{}/{}/battery",
-                        toy.toy_name
-                            .replace("Lovense Connect", "lovense")
-                            .replace(" ", "_")
-                            .to_lowercase(),
-                        toy.sub_id
-                    ),
-                    args: vec![OscType::Float(toy_power.to_float() as f32)],
-                }))
-                .unwrap();
-
-                let batt_send_err = sock.send(&battery_level_msg).await;
-                if batt_send_err.is_err() {
-                    warn!("Failed to send battery_level to {}", remote.to_string());
-                } else {
-                    info!(
-                        "Sent battery_level: {} to {}",
-                        toy_power.to_float() as f32,
-                        toy.toy_name
-                    );
-                }
-            } else {
-                trace!("OSC data disabled for toy {}", toy.toy_name);
-            }
-        }
-    }
+pub fn ingress_datagram(raw: &[u8], repeater: &OSCRepeater) -> Option<OscPacket> {
+    let pkt = decode_packet(raw);
+    repeater.forward(raw, pkt.as_ref());
+    pkt
 }
@@ -1 +1,16 @@
+pub mod avatar_backup;
+pub mod avatar_config;
+pub mod bundle;
+pub mod chatbox;
+pub mod feedback;
+pub mod ingress;
+pub mod inject;
+pub mod learn;
 pub mod logic;
+pub mod oscquery;
+pub mod param_browser;
+pub mod recording;
+pub mod repeater;
+pub mod tcp;
+pub mod telemetry;
+pub mod vrchat_discovery;
@@ -0,0 +1,547 @@
+/*
+ * OSCQuery host tree
+ * Describes the OSC addresses VibeCheck consumes so VRChat and other OSCQuery clients only send what is used.
+ * The vrcoscquery handler only advertises the service over mDNS, the tree is served by the HTTP server here.
+ */
+use log::{debug, info, trace, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddrV4, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    config::toy::VCToyAnatomy,
+    osc_api::{feature_api_addr, toy_api_addr, API_PREFIX},
+    toy_handling::input_processor::penetration_systems::PenetrationSystemType,
+    toy_handling::toyops::VCToy,
+};
+
+// OSCQuery ACCESS values
+const ACCESS_NONE: u8 = 0;
+const ACCESS_WRITE: u8 = 2;
+
+const SPS_PREFIX: &str = "/avatar/parameters/OGB";
+const TPS_PREFIX: &str = "/avatar/parameters/TPS_Internal";
+
+// Requests are a single GET line with a few headers
+const MAX_REQUEST_SIZE: usize = 8192;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct OSCQueryNode {
+    #[serde(rename = "FULL_PATH")]
+    full_path: String,
+    #[serde(rename = "ACCESS")]
+    access: u8,
+    #[serde(rename = "TYPE", skip_serializing_if = "Option::is_none")]
+    osc_type: Option<&'static str>,
+    #[serde(rename = "DESCRIPTION", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "CONTENTS", skip_serializing_if = "BTreeMap::is_empty")]
+    contents: BTreeMap<String, OSCQueryNode>,
+}
+
+impl OSCQueryNode {
+    fn root() -> Self {
+        Self {
+            full_path: "/".to_string(),
+            description: Some("VibeCheck".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /*
+     * Inserts a node at path creating the containers on the way
+     * An existing node keeps its type and gets a new description appended
+     */
+    fn insert(
+        &mut self,
+        path: &str,
+        access: u8,
+        osc_type: Option<&'static str>,
+        description: String,
+    ) {
+        let mut node = self;
+        let mut full_path = String::new();
+
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            full_path.push('/');
+            full_path.push_str(segment);
+            node = node
+                .contents
+                .entry(segment.to_string())
+                .or_insert_with(|| OSCQueryNode {
+                    full_path: full_path.clone(),
+                    access: ACCESS_NONE,
+                    ..Default::default()
+                });
+        }
+
+        node.access = access;
+        if node.osc_type.is_none() {
+            node.osc_type = osc_type;
+        }
+        node.description = Some(match node.description.take() {
+            // Owners are only listed once
+            Some(existing)
+                if existing != description
+                    && !existing.split(", ").any(|owner| owner == description) =>
+            {
+                format!("{}, {}", existing, description)
+            }
+            Some(existing) => existing,
+            None => description,
+        });
+    }
+
+    fn find(&self, path: &str) -> Option<&Self> {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |node, segment| node.contents.get(segment))
+    }
+}
+
+/*
+ * Builds the tree for the API endpoints and the parameters of the given toys
+ */
+pub fn build_oscquery_tree<'a>(toys: impl Iterator<Item = &'a VCToy>) -> OSCQueryNode {
+    let mut root = OSCQueryNode::root();
+    for (endpoint, description) in [
+        (
+            "state",
+            "true enables VibeCheck and starts scanning, false disables it",
+        ),
+        (
+            "scan",
+            "true starts scanning for toys, false stops scanning",
+        ),
+        ("stop_all", "true stops every toy"),
+    ] {
+        root.insert(
+            &format!("{}/{}", API_PREFIX, endpoint),
+            ACCESS_WRITE,
+            Some("T"),
+            format!("VibeCheck API: {}", description),
+        );
+    }
+
+    let mut anatomies = Vec::new();
+    for toy in toys {
+        if let Some(config) = toy.config.as_ref() {
+            if !anatomies.contains(&config.anatomy) {
+                anatomies.push(config.anatomy.clone());
+            }
+        }
+
+        let toy_addr = toy_api_addr(&toy.toy_name, toy.sub_id);
+        root.insert(
+            &format!("{}/enabled", toy_addr),
+            ACCESS_WRITE,
+            Some("T"),
+            format!(
+                "VibeCheck API: enables or disables every feature of {}",
+                toy.toy_name
+            ),
+        );
+        root.insert(
+            &format!("{}/max_level", toy_addr),
+            ACCESS_WRITE,
+            Some("f"),
+            format!(
+                "VibeCheck API: maximum level of every feature of {}",
+                toy.toy_name
+            ),
+        );
+
+        for feature in &toy.parsed_toy_features.features {
+            let owner = format!(
+                "{} {:?} {}",
+                toy.toy_name, feature.feature_type, feature.feature_index
+            );
+
+            let feature_addr = feature_api_addr(
+                &toy.toy_name,
+                toy.sub_id,
+                feature.feature_type,
+                feature.feature_index,
+            );
+            root.insert(
+                &format!("{}/enabled", feature_addr),
+                ACCESS_WRITE,
+                Some("T"),
+                format!("VibeCheck API: enables or disables {}", owner),
+            );
+            root.insert(
+                &format!("{}/max_level", feature_addr),
+                ACCESS_WRITE,
+                Some("f"),
+                format!("VibeCheck API: maximum level of {}", owner),
+            );
+            for index in 0..feature.osc_parameters.len() {
+                root.insert(
+                    &format!("{}/parameter/{}/mode", feature_addr, index),
+                    ACCESS_WRITE,
+                    Some("i"),
+                    format!(
+                        "VibeCheck API: processing mode of parameter {} of {} (0 raw, 1 smooth, 2 rate, 3 constant)",
+                        index, owner
+                    ),
+                );
+            }
+
+            for param in &feature.osc_parameters {
+                // Int value tables need the int, everything else is driven by floats or bools cast to floats
+                let osc_type = if param.int_values.is_empty() {
+                    "f"
+                } else {
+                    "i"
+                };
+                root.insert(
+                    &param.parameter,
+                    ACCESS_WRITE,
+                    Some(osc_type),
+                    owner.clone(),
+                );
+            }
+
+            match feature.penetration_system.pen_system_type {
+                PenetrationSystemType::SPS => root.insert(
+                    SPS_PREFIX,
+                    ACCESS_NONE,
+                    None,
+                    format!("SPS input for {}", owner),
+                ),
+                PenetrationSystemType::TPS => root.insert(
+                    TPS_PREFIX,
+                    ACCESS_NONE,
+                    None,
+                    format!("TPS input for {}", owner),
+                ),
+                PenetrationSystemType::NONE => {}
+            }
+        }
+    }
+
+    for anatomy in anatomies {
+        if let VCToyAnatomy::NA = anatomy {
+            continue;
+        }
+        root.insert(
+            &format!("{}/anatomy/{:?}/enabled", API_PREFIX, anatomy),
+            ACCESS_WRITE,
+            Some("T"),
+            format!("VibeCheck API: enables or disables {:?} toys", anatomy),
+        );
+    }
+
+    root
+}
+
+lazy_static::lazy_static! {
+    static ref OSCQUERY_TREE: Mutex<OSCQueryNode> = Mutex::new(build_oscquery_tree(std::iter::empty()));
+}
+
+/*
+ * Rebuilds the served tree
+ * Called whenever toys are added, removed or altered
+ */
+pub fn update_oscquery_tree<'a>(toys: impl Iterator<Item = &'a VCToy>) {
+    let tree = build_oscquery_tree(toys);
+    trace!(
+        "Updated OSCQuery tree: {} root entries",
+        tree.contents.len()
+    );
+    *OSCQUERY_TREE.lock() = tree;
+}
+
+#[derive(Serialize)]
+struct OSCQueryHostInfo {
+    #[serde(rename = "NAME")]
+    name: &'static str,
+    #[serde(rename = "OSC_IP")]
+    osc_ip: String,
+    #[serde(rename = "OSC_PORT")]
+    osc_port: u16,
+    #[serde(rename = "OSC_TRANSPORT")]
+    osc_transport: &'static str,
+    #[serde(rename = "EXTENSIONS")]
+    extensions: BTreeMap<&'static str, bool>,
+}
+
+/*
+ * HTTP server for the OSCQuery tree
+ * Bound to the HTTP address advertised over mDNS
+ */
+pub struct OSCQueryHttp {
+    http_net: SocketAddrV4,
+    osc_net: SocketAddrV4,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OSCQueryHttp {
+    pub fn new(http_net: SocketAddrV4, osc_net: SocketAddrV4) -> Self {
+        Self {
+            http_net,
+            osc_net,
+            stop: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    pub fn start(&mut self) -> std::io::Result<()> {
+        if self.thread.is_some() {
+            return Ok(());
+        }
+
+        let listener = TcpListener::bind(self.http_net)?;
+        // Non blocking accept so the server notices when it is stopped
+        listener.set_nonblocking(true)?;
+        info!("Serving OSCQuery tree on http://{}", self.http_net);
+
+        self.stop.store(false, Ordering::Relaxed);
+        let stop = self.stop.clone();
+        let osc_net = self.osc_net;
+        self.thread = Some(thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        trace!("OSCQuery request from {}", peer);
+                        if let Err(e) = handle_request(stream, osc_net) {
+                            debug!("OSCQuery request from {} failed: {}", peer, e);
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                    Err(e) => {
+                        warn!("Failed to accept OSCQuery connection: {}", e);
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            }
+            info!("OSCQuery HTTP server stopped");
+        }));
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for OSCQueryHttp {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn handle_request(mut stream: TcpStream, osc_net: SocketAddrV4) -> std::io::Result<()> {
+    // Accepted streams can inherit non blocking mode from the listener on some platforms
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk)?;
+        if n == 0 || buf.len() + n > MAX_REQUEST_SIZE {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return respond(&mut stream, "400 Bad Request", "");
+    };
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "");
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if query == "HOST_INFO" {
+        let body = serde_json::to_string(&OSCQueryHostInfo {
+            name: "VibeCheck",
+            osc_ip: osc_net.ip().to_string(),
+            osc_port: osc_net.port(),
+            osc_transport: "UDP",
+            extensions: BTreeMap::from([
+                ("ACCESS", true),
+                ("DESCRIPTION", true),
+                ("VALUE", false),
+                ("RANGE", false),
+                ("TAGS", false),
+                ("CLIPMODE", false),
+                ("UNIT", false),
+                ("CRITICAL", false),
+                ("LISTEN", false),
+                ("PATH_CHANGED", false),
+            ]),
+        })
+        .unwrap_or_default();
+        return respond(&mut stream, "200 OK", &body);
+    }
+
+    let body = {
+        let tree = OSCQUERY_TREE.lock();
+        tree.find(path)
+            .and_then(|node| serde_json::to_string(node).ok())
+    };
+    match body {
+        Some(body) => respond(&mut stream, "200 OK", &body),
+        None => respond(&mut stream, "404 Not Found", ""),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Shutdown};
+
+    #[test]
+    fn inserts_nodes_with_their_containers() {
+        let mut root = OSCQueryNode::root();
+        root.insert(
+            "/avatar/parameters/Touch",
+            ACCESS_WRITE,
+            Some("f"),
+            "Hush Vibrator 0".to_string(),
+        );
+
+        let Some(avatar) = root.find("/avatar") else {
+            panic!("container was not created");
+        };
+        assert_eq!(avatar.full_path, "/avatar");
+        assert_eq!(avatar.access, ACCESS_NONE);
+        assert!(avatar.osc_type.is_none());
+
+        let Some(touch) = root.find("/avatar/parameters/Touch") else {
+            panic!("parameter was not inserted");
+        };
+        assert_eq!(touch.full_path, "/avatar/parameters/Touch");
+        assert_eq!(touch.access, ACCESS_WRITE);
+        assert_eq!(touch.osc_type, Some("f"));
+        assert!(touch.contents.is_empty());
+
+        assert!(root.find("/avatar/parameters/Missing").is_none());
+        assert_eq!(
+            root.find("/").map(|node| node.full_path.as_str()),
+            Some("/")
+        );
+    }
+
+    #[test]
+    fn shared_nodes_keep_their_type_and_list_every_owner() {
+        let mut root = OSCQueryNode::root();
+        for (osc_type, owner) in [
+            ("i", "Hush Vibrator 0"),
+            ("f", "Nora Rotator 1"),
+            ("f", "Nora Rotator 1"),
+        ] {
+            root.insert(
+                "/avatar/parameters/Gesture",
+                ACCESS_WRITE,
+                Some(osc_type),
+                owner.to_string(),
+            );
+        }
+
+        let gesture = root.find("/avatar/parameters/Gesture").unwrap();
+        assert_eq!(gesture.osc_type, Some("i"));
+        assert_eq!(
+            gesture.description.as_deref(),
+            Some("Hush Vibrator 0, Nora Rotator 1")
+        );
+    }
+
+    #[test]
+    fn tree_without_toys_serves_the_api() {
+        let tree = build_oscquery_tree(std::iter::empty());
+        for endpoint in ["state", "scan", "stop_all"] {
+            let Some(node) = tree.find(&format!("{}/{}", API_PREFIX, endpoint)) else {
+                panic!("{} is missing", endpoint);
+            };
+            assert_eq!((node.access, node.osc_type), (ACCESS_WRITE, Some("T")));
+        }
+        assert!(tree.find(&format!("{}/toy", API_PREFIX)).is_none());
+        assert!(tree.find(SPS_PREFIX).is_none());
+
+        // Empty containers and types are left out of the JSON
+        let json = serde_json::to_value(tree.find(API_PREFIX).unwrap()).unwrap();
+        assert_eq!(json["FULL_PATH"], API_PREFIX);
+        assert_eq!(json["ACCESS"], ACCESS_NONE);
+        assert!(json.get("TYPE").is_none());
+        assert_eq!(json["CONTENTS"]["scan"]["TYPE"], "T");
+        assert!(json["CONTENTS"]["scan"].get("CONTENTS").is_none());
+    }
+
+    fn request(raw: &str) -> (String, String) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(raw.as_bytes()).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        handle_request(stream, SocketAddrV4::new(Ipv4Addr::LOCALHOST, 9001)).unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_string(), body.to_string())
+    }
+
+    #[test]
+    fn serves_host_info_and_nodes() {
+        let (status, body) = request("GET /?HOST_INFO HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let info: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(info["NAME"], "VibeCheck");
+        assert_eq!(info["OSC_IP"], "127.0.0.1");
+        assert_eq!(info["OSC_PORT"], 9001);
+        assert_eq!(info["OSC_TRANSPORT"], "UDP");
+
+        let (status, body) = request(&format!("GET {}/stop_all HTTP/1.1\r\n\r\n", API_PREFIX));
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let node: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(node["FULL_PATH"], format!("{}/stop_all", API_PREFIX));
+
+        let (status, body) = request("GET /not/served HTTP/1.1\r\n\r\n");
+        assert_eq!(
+            (status.as_str(), body.as_str()),
+            ("HTTP/1.1 404 Not Found", "")
+        );
+    }
+
+    #[test]
+    fn refuses_other_requests() {
+        let (status, _) = request("POST / HTTP/1.1\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+
+        let (status, _) = request("\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    }
+}
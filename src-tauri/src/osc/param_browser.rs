@@ -0,0 +1,419 @@
+/*
+ * Live OSC parameter browser
+ * Keeps a table of every address received on the listeners so users can see what their avatar is actually sending.
+ */
+use log::trace;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    frontend::frontend_types::{FeOSCParameter, FeOSCParameterConsumer},
+    toy_handling::{toy_manager::ToyManager, toyops::VCToyFeatures, ModeProcessorInputType},
+};
+
+use super::ingress::VCOscMessage;
+
+// Protects against senders flooding the table with unique addresses
+const MAX_OBSERVED_PARAMETERS: usize = 4096;
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const EMIT_INTERVAL: Duration = Duration::from_millis(500);
+
+struct ObservedParameter {
+    value: ModeProcessorInputType,
+    first_seen_ms: u64,
+    last_seen_ms: u64,
+    updates: u64,
+    window_start: Instant,
+    window_updates: u32,
+    rate_hz: f64,
+}
+
+impl ObservedParameter {
+    fn new(value: ModeProcessorInputType, now_ms: u64) -> Self {
+        Self {
+            value,
+            first_seen_ms: now_ms,
+            last_seen_ms: now_ms,
+            updates: 0,
+            window_start: Instant::now(),
+            window_updates: 0,
+            rate_hz: 0.0,
+        }
+    }
+
+    fn update(&mut self, value: ModeProcessorInputType, now_ms: u64) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= RATE_WINDOW {
+            self.rate_hz = self.window_updates as f64 / elapsed.as_secs_f64();
+            self.window_start = Instant::now();
+            self.window_updates = 0;
+        }
+
+        self.value = value;
+        self.last_seen_ms = now_ms;
+        self.updates += 1;
+        self.window_updates += 1;
+    }
+
+    /*
+     * Rate of the last full window
+     * A parameter that stopped updating decays towards 0 instead of keeping its last rate
+     */
+    fn rate_hz(&self) -> f64 {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= RATE_WINDOW {
+            self.window_updates as f64 / elapsed.as_secs_f64()
+        } else {
+            self.rate_hz
+        }
+    }
+}
+
+#[derive(Default)]
+struct ParameterBrowser {
+    params: HashMap<String, ObservedParameter>,
+    changed: bool,
+    last_emit: Option<Instant>,
+}
+
+impl ParameterBrowser {
+    /*
+     * Records received messages
+     * Returns true when the changed table is due to be emitted
+     */
+    fn observe<'a>(&mut self, msgs: impl Iterator<Item = &'a VCOscMessage>, now_ms: u64) -> bool {
+        for msg in msgs {
+            if let Some(param) = self.params.get_mut(&msg.addr) {
+                param.update(msg.value, now_ms);
+            } else if self.params.len() < MAX_OBSERVED_PARAMETERS {
+                self.params
+                    .insert(msg.addr.clone(), ObservedParameter::new(msg.value, now_ms));
+            } else {
+                continue;
+            }
+            self.changed = true;
+        }
+
+        let due = self
+            .last_emit
+            .map_or(true, |last| last.elapsed() >= EMIT_INTERVAL);
+        if self.changed && due {
+            self.changed = false;
+            self.last_emit = Some(Instant::now());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn table(&self, toys: &[ConsumingToy]) -> Vec<FeOSCParameter> {
+        let mut table: Vec<FeOSCParameter> = self
+            .params
+            .iter()
+            .map(|(addr, param)| FeOSCParameter {
+                addr: addr.clone(),
+                value: param.value,
+                first_seen_ms: param.first_seen_ms,
+                last_seen_ms: param.last_seen_ms,
+                updates: param.updates,
+                rate_hz: param.rate_hz(),
+                consumers: parameter_consumers(addr, toys),
+            })
+            .collect();
+        table.sort_by(|a, b| a.addr.cmp(&b.addr));
+        table
+    }
+}
+
+// Toy name, toy id (None while offline) and features
+type ConsumingToy = (String, Option<u32>, VCToyFeatures);
+
+lazy_static::lazy_static! {
+    static ref OSC_PARAMETER_BROWSER: Mutex<ParameterBrowser> = Mutex::new(ParameterBrowser::default());
+    // Snapshot of the toy manager so building the table doesn't need the state lock
+    static ref CONSUMING_TOYS: Mutex<Vec<ConsumingToy>> = Mutex::new(Vec::new());
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/*
+ * Updates the table with received messages
+ * Emits fe_osc_parameters at most every EMIT_INTERVAL
+ */
+pub fn observe_osc_messages<'a>(
+    msgs: impl Iterator<Item = &'a VCOscMessage>,
+    app_handle: &AppHandle,
+) {
+    let emit = OSC_PARAMETER_BROWSER.lock().observe(msgs, unix_ms());
+
+    if emit {
+        trace!("Emitting OSC parameter table");
+        let _ = app_handle.emit_all("fe_osc_parameters", osc_parameter_table());
+    }
+}
+
+/*
+ * Snapshot of the table with the toy features that consume each address
+ */
+pub fn osc_parameter_table() -> Vec<FeOSCParameter> {
+    let toys = CONSUMING_TOYS.lock();
+    OSC_PARAMETER_BROWSER.lock().table(&toys)
+}
+
+pub fn clear_osc_parameters() {
+    let mut browser = OSC_PARAMETER_BROWSER.lock();
+    browser.params.clear();
+    browser.changed = false;
+}
+
+/*
+ * Refreshes the consumer snapshot from online toys and the saved configs of offline toys
+ * Called whenever the toy manager in the VibeCheck state changes
+ */
+pub fn refresh_parameter_consumers(toy_manager: &ToyManager) {
+    let mut toys: Vec<_> = toy_manager
+        .online_toys
+        .values()
+        .map(|toy| {
+            (
+                toy.toy_name.clone(),
+                Some(toy.toy_id),
+                toy.parsed_toy_features.clone(),
+            )
+        })
+        .collect();
+
+    for config in toy_manager.configs.values() {
+        if toy_manager.check_toy_online(&config.toy_name) {
+            continue;
+        }
+        let mut features = config.features.clone();
+        features.init_penetration_systems();
+        toys.push((config.toy_name.clone(), None, features));
+    }
+    *CONSUMING_TOYS.lock() = toys;
+}
+
+fn parameter_consumers(addr: &String, toys: &[ConsumingToy]) -> Vec<FeOSCParameterConsumer> {
+    let mut consumers = Vec::new();
+
+    for (toy_name, toy_id, features) in toys {
+        for feature in features.features.iter().filter(|f| f.feature_enabled) {
+            let parameter = feature.osc_parameters.iter().any(|p| p.parameter == *addr);
+            let input_processor = feature
+                .penetration_system
+                .pen_system
+                .as_ref()
+                .is_some_and(|pen_system| pen_system.is_parameter(addr));
+
+            if parameter || input_processor {
+                consumers.push(FeOSCParameterConsumer {
+                    toy_name: toy_name.clone(),
+                    toy_id: *toy_id,
+                    feature_index: feature.feature_index,
+                    feature_type: feature.feature_type.to_fe(),
+                    input_processor,
+                });
+            }
+        }
+    }
+    consumers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy_handling::{
+        input_processor::penetration_systems::{PenetrationSystem, PenetrationSystemType},
+        toyops::{
+            LevelTweaks, ProcessingMode, ProcessingModeValues, ToyParameter, VCFeatureType,
+            VCToyFeature,
+        },
+    };
+
+    fn msg(addr: &str, value: ModeProcessorInputType) -> VCOscMessage {
+        VCOscMessage {
+            addr: addr.to_string(),
+            value,
+        }
+    }
+
+    fn feature(
+        feature_index: u32,
+        parameters: &[&str],
+        pen_system_type: PenetrationSystemType,
+    ) -> VCToyFeature {
+        VCToyFeature {
+            feature_index,
+            feature_enabled: true,
+            feature_type: VCFeatureType::Vibrator,
+            osc_parameters: parameters
+                .iter()
+                .map(|parameter| ToyParameter {
+                    parameter: parameter.to_string(),
+                    processing_mode: ProcessingMode::Raw,
+                    int_values: vec![],
+                    processing_mode_values: ProcessingModeValues::Raw,
+                })
+                .collect(),
+            penetration_system: PenetrationSystem {
+                pen_system_type,
+                ..Default::default()
+            },
+            flip_input_float: false,
+            feature_levels: LevelTweaks::default(),
+            smooth_enabled: false,
+            rate_enabled: false,
+            output_parameter: None,
+        }
+    }
+
+    #[test]
+    fn aggregates_messages_per_address() {
+        let mut browser = ParameterBrowser::default();
+        browser.observe(
+            [
+                msg(
+                    "/avatar/parameters/b",
+                    ModeProcessorInputType::Boolean(true),
+                ),
+                msg("/avatar/parameters/a", ModeProcessorInputType::Float(0.1)),
+                msg("/avatar/parameters/a", ModeProcessorInputType::Float(0.3)),
+            ]
+            .iter(),
+            100,
+        );
+        browser.observe(
+            [msg(
+                "/avatar/parameters/a",
+                ModeProcessorInputType::Float(0.5),
+            )]
+            .iter(),
+            200,
+        );
+
+        let table = browser.table(&[]);
+        let rows: Vec<(&str, u64, u64, u64)> = table
+            .iter()
+            .map(|p| (p.addr.as_str(), p.first_seen_ms, p.last_seen_ms, p.updates))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("/avatar/parameters/a", 100, 200, 2),
+                ("/avatar/parameters/b", 100, 100, 0),
+            ]
+        );
+        assert!(matches!(table[0].value, ModeProcessorInputType::Float(v) if v == 0.5));
+        assert!(matches!(
+            table[1].value,
+            ModeProcessorInputType::Boolean(true)
+        ));
+    }
+
+    #[test]
+    fn caps_unique_addresses() {
+        let mut browser = ParameterBrowser::default();
+        let flood: Vec<VCOscMessage> = (0..MAX_OBSERVED_PARAMETERS + 10)
+            .map(|i| msg(&format!("/flood/{}", i), ModeProcessorInputType::Int(1)))
+            .collect();
+        browser.observe(flood.iter(), 0);
+        assert_eq!(browser.params.len(), MAX_OBSERVED_PARAMETERS);
+        assert!(!browser.params.contains_key("/flood/4100"));
+
+        // Known addresses still update once full
+        browser.observe([msg("/flood/0", ModeProcessorInputType::Int(2))].iter(), 1);
+        assert_eq!(browser.params["/flood/0"].updates, 1);
+    }
+
+    #[test]
+    fn throttles_emits() {
+        let mut browser = ParameterBrowser::default();
+        let update = [msg(
+            "/avatar/parameters/a",
+            ModeProcessorInputType::Float(0.5),
+        )];
+
+        assert!(browser.observe(update.iter(), 0));
+        assert!(!browser.observe(update.iter(), 0));
+
+        // Pending changes go out once the interval passed
+        browser.last_emit = Some(Instant::now() - EMIT_INTERVAL);
+        assert!(browser.observe([].iter(), 0));
+
+        // Nothing changed since
+        browser.last_emit = Some(Instant::now() - EMIT_INTERVAL);
+        assert!(!browser.observe([].iter(), 0));
+    }
+
+    #[test]
+    fn rate_decays_when_updates_stop() {
+        let mut param = ObservedParameter::new(ModeProcessorInputType::Float(0.0), 0);
+        param.rate_hz = 20.0;
+        assert_eq!(param.rate_hz(), 20.0);
+
+        param.window_start = Instant::now() - RATE_WINDOW * 2;
+        param.window_updates = 4;
+        let rate = param.rate_hz();
+        assert!(rate > 1.9 && rate <= 2.0, "rate was {}", rate);
+
+        param.window_updates = 0;
+        assert_eq!(param.rate_hz(), 0.0);
+    }
+
+    #[test]
+    fn lists_consuming_features() {
+        let mut disabled = feature(1, &["/avatar/parameters/a"], PenetrationSystemType::NONE);
+        disabled.feature_enabled = false;
+        let mut features = VCToyFeatures {
+            features: vec![
+                feature(0, &["/avatar/parameters/a"], PenetrationSystemType::NONE),
+                disabled,
+                feature(2, &[], PenetrationSystemType::TPS),
+            ],
+        };
+        features.init_penetration_systems();
+        let toys = [
+            ("Hush".to_string(), Some(3), features),
+            (
+                "Nora".to_string(),
+                None,
+                VCToyFeatures {
+                    features: vec![feature(
+                        4,
+                        &["/avatar/parameters/a", "/avatar/parameters/b"],
+                        PenetrationSystemType::NONE,
+                    )],
+                },
+            ),
+        ];
+
+        let consumers = |addr: &str| -> Vec<(String, Option<u32>, u32, bool)> {
+            parameter_consumers(&addr.to_string(), &toys)
+                .into_iter()
+                .map(|c| (c.toy_name, c.toy_id, c.feature_index, c.input_processor))
+                .collect()
+        };
+        assert_eq!(
+            consumers("/avatar/parameters/a"),
+            [
+                ("Hush".to_string(), Some(3), 0, false),
+                ("Nora".to_string(), None, 4, false),
+            ]
+        );
+        assert_eq!(
+            consumers("/avatar/parameters/TPS_Internal/Orf/Hole/Depth_In"),
+            [("Hush".to_string(), Some(3), 2, true)]
+        );
+        assert!(consumers("/avatar/parameters/c").is_empty());
+    }
+}
@@ -0,0 +1,457 @@
+/*
+ * Recording and replaying of received OSC messages
+ * Recordings are JSON lines files in the config dir, one validated message per line with its offset from the recording start.
+ */
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    ops::RangeInclusive,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    frontend::frontend_types::FeReplayResult,
+    osc_api::osc_api::dispatch_internal_osc_message,
+    util::{
+        fs::get_config_dir,
+        glob::glob_match,
+        stoppable::{StopFlag, StoppableThread},
+    },
+    vcore::vcerror::frontend::VCFeError,
+};
+
+use super::ingress::VCOscMessage;
+
+#[derive(Serialize, Deserialize)]
+struct RecordedOscMessage {
+    // Microseconds since the recording started (monotonic)
+    t_us: u64,
+    #[serde(flatten)]
+    msg: VCOscMessage,
+}
+
+struct OscRecorder {
+    file_name: String,
+    writer: BufWriter<File>,
+    started: Instant,
+    filter: Option<String>,
+    recorded: u64,
+}
+
+// Beyond this range replays either stall or send everything at once
+const REPLAY_SPEEDS: RangeInclusive<f64> = 0.01..=100.0;
+
+// Checked before taking the recorder lock so listeners don't contend on it while not recording
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+static OSC_REPLAY: StoppableThread = StoppableThread::new();
+
+lazy_static::lazy_static! {
+    static ref OSC_RECORDER: Mutex<Option<OscRecorder>> = Mutex::new(None);
+}
+
+pub fn recordings_dir() -> String {
+    format!("{}\\Recordings", get_config_dir())
+}
+
+/*
+ * Recording file names are generated by VibeCheck, anything that could leave the recordings dir is refused
+ */
+fn recording_path(file_name: &str) -> Result<String, VCFeError> {
+    if file_name.is_empty()
+        || file_name.contains(['/', '\\'])
+        || file_name.contains("..")
+        || !file_name.ends_with(".jsonl")
+    {
+        return Err(VCFeError::InvalidRecordingName);
+    }
+    Ok(format!("{}\\{}", recordings_dir(), file_name))
+}
+
+/*
+ * Starts recording every received OSC message
+ * Returns the recording file name
+ */
+pub fn start_recording(filter: Option<String>) -> Result<String, VCFeError> {
+    let mut recorder = OSC_RECORDER.lock();
+    if recorder.is_some() {
+        return Err(VCFeError::RecordingActive);
+    }
+
+    let dir = recordings_dir();
+    fs::create_dir_all(&dir).map_err(|_| VCFeError::WriteFailure)?;
+
+    let unix_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let (file_name, file) = create_recording_file(unix_millis)?;
+
+    info!("Recording OSC to {}", file_name);
+    *recorder = Some(OscRecorder {
+        file_name: file_name.clone(),
+        writer: BufWriter::new(file),
+        started: Instant::now(),
+        filter: filter.filter(|f| !f.is_empty()),
+        recorded: 0,
+    });
+    RECORDING.store(true, Ordering::Relaxed);
+
+    Ok(file_name)
+}
+
+/*
+ * Never overwrites an existing recording, a suffix is added if the name is taken
+ */
+fn create_recording_file(unix_millis: u128) -> Result<(String, File), VCFeError> {
+    for attempt in 0..100 {
+        let file_name = match attempt {
+            0 => format!("osc-{}.jsonl", unix_millis),
+            n => format!("osc-{}-{}.jsonl", unix_millis, n),
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(recording_path(&file_name)?)
+        {
+            Ok(file) => return Ok((file_name, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(_) => return Err(VCFeError::WriteFailure),
+        }
+    }
+    Err(VCFeError::WriteFailure)
+}
+
+/*
+ * Stops the recording and flushes it to disk
+ * Returns the number of recorded messages
+ */
+pub fn stop_recording() -> Result<u64, VCFeError> {
+    let Some(mut recorder) = OSC_RECORDER.lock().take() else {
+        return Err(VCFeError::RecordingNotActive);
+    };
+    RECORDING.store(false, Ordering::Relaxed);
+
+    recorder
+        .writer
+        .flush()
+        .map_err(|_| VCFeError::WriteFailure)?;
+    info!(
+        "Stopped recording {} ({} messages)",
+        recorder.file_name, recorder.recorded
+    );
+    Ok(recorder.recorded)
+}
+
+/*
+ * Appends received messages to the active recording
+ */
+pub fn record_osc_messages<'a>(msgs: impl Iterator<Item = &'a VCOscMessage>) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Some(recorder) = OSC_RECORDER.lock().as_mut() {
+        recorder.record(msgs);
+    }
+}
+
+impl OscRecorder {
+    fn record<'a>(&mut self, msgs: impl Iterator<Item = &'a VCOscMessage>) {
+        let t_us = self.started.elapsed().as_micros() as u64;
+        for msg in msgs {
+            if let Some(filter) = self.filter.as_ref() {
+                if !glob_match(filter, &msg.addr) {
+                    continue;
+                }
+            }
+
+            let line = match serde_json::to_string(&RecordedOscMessage {
+                t_us,
+                msg: msg.clone(),
+            }) {
+                Ok(line) => line,
+                Err(e) => {
+                    warn!("Failed to serialize recorded OSC message: {}", e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = writeln!(self.writer, "{}", line) {
+                warn!("Failed to write OSC recording: {}", e);
+                continue;
+            }
+            self.recorded += 1;
+        }
+    }
+}
+
+pub fn list_recordings() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(recordings_dir()) else {
+        return Vec::new();
+    };
+
+    let mut recordings: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".jsonl"))
+        .collect();
+    recordings.sort();
+    recordings
+}
+
+/*
+ * Replays a recording into the dispatch pipeline
+ * speed scales the time between messages (2.0 plays twice as fast), from 0.01 to 100
+ * API messages are replayed even while VibeCheck is disabled, toy messages are skipped and counted
+ */
+pub fn start_replay(
+    file_name: &str,
+    speed: f64,
+    filter: Option<String>,
+    app_handle: AppHandle,
+) -> Result<(), VCFeError> {
+    if !REPLAY_SPEEDS.contains(&speed) {
+        return Err(VCFeError::InvalidReplaySpeed);
+    }
+
+    let file = File::open(recording_path(file_name)?).map_err(|_| VCFeError::RecordingNotFound)?;
+
+    let file_name = file_name.to_string();
+    let filter = filter.filter(|f| !f.is_empty());
+    let started = OSC_REPLAY.start(move |stop| {
+        info!("Replaying {} at {}x", file_name, speed);
+        let (replayed, skipped) = replay(BufReader::new(file), speed, filter, stop, |msg| {
+            dispatch_internal_osc_message(msg, &app_handle)
+        });
+        info!("Replay of {} ended ({} messages)", file_name, replayed);
+        if skipped > 0 {
+            warn!(
+                "Replay of {} skipped {} toy messages: VibeCheck is not listening",
+                file_name, skipped
+            );
+        }
+
+        let _ = app_handle.emit_all(
+            "fe_replay_result",
+            FeReplayResult {
+                file_name,
+                replayed,
+                skipped,
+            },
+        );
+    });
+
+    if started {
+        Ok(())
+    } else {
+        Err(VCFeError::ReplayActive)
+    }
+}
+
+pub fn stop_replay() -> Result<(), VCFeError> {
+    if OSC_REPLAY.stop() {
+        Ok(())
+    } else {
+        Err(VCFeError::ReplayNotActive)
+    }
+}
+
+/*
+ * Dispatches the recorded messages at their recorded offsets
+ * Returns (replayed, skipped), dispatch returns false for messages that could not be delivered
+ */
+fn replay(
+    reader: impl BufRead,
+    speed: f64,
+    filter: Option<String>,
+    stop: &StopFlag,
+    mut dispatch: impl FnMut(VCOscMessage) -> bool,
+) -> (u64, u64) {
+    let started = Instant::now();
+    let mut replayed = 0;
+    let mut skipped = 0;
+
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let recorded = match serde_json::from_str::<RecordedOscMessage>(&line) {
+            Ok(recorded) => recorded,
+            Err(e) => {
+                warn!("Skipping invalid recording line: {}", e);
+                continue;
+            }
+        };
+
+        // Recordings are plain files so they go through the same checks as received messages
+        if recorded.msg.check().is_err() {
+            continue;
+        }
+        if let Some(filter) = filter.as_ref() {
+            if !glob_match(filter, &recorded.msg.addr) {
+                continue;
+            }
+        }
+
+        let due = Duration::from_secs_f64(recorded.t_us as f64 / 1_000_000.0 / speed);
+        if !stop.sleep(due.saturating_sub(started.elapsed())) {
+            return (replayed, skipped);
+        }
+
+        if dispatch(recorded.msg) {
+            replayed += 1;
+        } else {
+            skipped += 1;
+        }
+    }
+
+    (replayed, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toy_handling::ModeProcessorInputType;
+    use std::{path::PathBuf, thread};
+
+    struct RecordingFixture(PathBuf);
+
+    impl RecordingFixture {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!(
+                "vibecheck-recording-{}-{}.jsonl",
+                std::process::id(),
+                name
+            )))
+        }
+
+        fn recorder(&self, filter: Option<&str>) -> OscRecorder {
+            OscRecorder {
+                file_name: "test.jsonl".to_string(),
+                writer: BufWriter::new(File::create(&self.0).unwrap()),
+                started: Instant::now(),
+                filter: filter.map(str::to_string),
+                recorded: 0,
+            }
+        }
+
+        fn reader(&self) -> BufReader<File> {
+            BufReader::new(File::open(&self.0).unwrap())
+        }
+    }
+
+    impl Drop for RecordingFixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn msg(param: &str, value: ModeProcessorInputType) -> VCOscMessage {
+        VCOscMessage {
+            addr: format!("/avatar/parameters/{}", param),
+            value,
+        }
+    }
+
+    #[test]
+    fn replays_a_recording() {
+        let fixture = RecordingFixture::new("replay");
+        let mut recorder = fixture.recorder(Some("/avatar/parameters/Vibe*"));
+        recorder.record(
+            [
+                msg("Vibe", ModeProcessorInputType::Float(0.5)),
+                msg("Face", ModeProcessorInputType::Float(1.0)),
+            ]
+            .iter(),
+        );
+        thread::sleep(Duration::from_millis(200));
+        recorder.record(
+            [
+                msg("VibeSpeed", ModeProcessorInputType::Boolean(true)),
+                msg("VibeMode", ModeProcessorInputType::Int(2)),
+            ]
+            .iter(),
+        );
+        assert_eq!(recorder.recorded, 3);
+        // Lines that don't parse or don't pass ingress validation are skipped
+        writeln!(recorder.writer, "not json").unwrap();
+        writeln!(
+            recorder.writer,
+            r#"{{"t_us":0,"addr":"nope","value":{{"Float":1.0}}}}"#
+        )
+        .unwrap();
+        recorder.writer.flush().unwrap();
+
+        // Recorded 200ms apart, replayed at 4x
+        let started = Instant::now();
+        let mut dispatched = Vec::new();
+        let (replayed, skipped) = replay(
+            fixture.reader(),
+            4.0,
+            Some("*/Vibe?*".to_string()),
+            &StopFlag::new(),
+            |msg| {
+                dispatched.push((msg.addr.clone(), started.elapsed()));
+                !msg.addr.ends_with("VibeMode")
+            },
+        );
+        assert_eq!((replayed, skipped), (1, 1));
+        assert_eq!(dispatched[0].0, "/avatar/parameters/VibeSpeed");
+        assert_eq!(dispatched[1].0, "/avatar/parameters/VibeMode");
+        assert!(dispatched[0].1 >= Duration::from_millis(50));
+        assert!(dispatched[0].1 < Duration::from_millis(200));
+
+        let (replayed, skipped) = replay(fixture.reader(), 100.0, None, &StopFlag::new(), |_| true);
+        assert_eq!((replayed, skipped), (3, 0));
+    }
+
+    #[test]
+    fn stopped_replay_dispatches_nothing() {
+        let fixture = RecordingFixture::new("stopped");
+        let mut recorder = fixture.recorder(None);
+        recorder.record([msg("Vibe", ModeProcessorInputType::Float(0.5))].iter());
+        recorder.writer.flush().unwrap();
+
+        let stop = StopFlag::new();
+        stop.stop();
+        let (replayed, skipped) = replay(fixture.reader(), 1.0, None, &stop, |_| {
+            panic!("dispatched after stop")
+        });
+        assert_eq!((replayed, skipped), (0, 0));
+    }
+
+    #[test]
+    fn refuses_recording_names_outside_the_dir() {
+        for name in [
+            "",
+            "../osc-1.jsonl",
+            "..",
+            "sub/osc-1.jsonl",
+            "/osc-1.jsonl",
+            "sub\\osc-1.jsonl",
+            "osc-1.json",
+        ] {
+            assert!(
+                matches!(recording_path(name), Err(VCFeError::InvalidRecordingName)),
+                "{} was accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn bounds_replay_speed() {
+        for speed in [0.0, -1.0, 1e-300, 0.001, 101.0, f64::NAN, f64::INFINITY] {
+            assert!(!REPLAY_SPEEDS.contains(&speed), "{} was accepted", speed);
+        }
+        for speed in [0.01, 1.0, 100.0] {
+            assert!(REPLAY_SPEEDS.contains(&speed));
+        }
+    }
+}
@@ -0,0 +1,438 @@
+/*
+ * OSC repeater
+ * Forwards packets received on the VibeCheck listen port to other OSC apps so they can share the port.
+ */
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use rosc::{encoder, OscBundle, OscPacket};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+};
+use ts_rs::TS;
+
+use crate::{
+    frontend::frontend_types::FeOSCRepeaterStats,
+    osc_api::API_PREFIX,
+    util::{
+        glob::glob_match,
+        net::{reaches_bind, sender_bind_addr},
+    },
+    vcore::config::OSCRepeaterTarget,
+};
+
+/*
+ * Replaces an address prefix before forwarding
+ * Addresses not starting with from are forwarded unchanged
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct OSCAddressRewrite {
+    pub from: String,
+    pub to: String,
+}
+
+impl OSCAddressRewrite {
+    pub fn apply(&self, addr: &str) -> Option<String> {
+        addr.strip_prefix(self.from.as_str())
+            .map(|rest| format!("{}{}", self.to, rest))
+    }
+}
+
+/*
+ * Live state of a repeater target
+ * Shared between the listener and the frontend so targets can be toggled without restarting
+ */
+#[derive(Default)]
+pub struct RepeaterTargetState {
+    enabled: AtomicBool,
+    forwarded_packets: AtomicU64,
+    forwarded_bytes: AtomicU64,
+    filtered_packets: AtomicU64,
+    send_errors: AtomicU64,
+}
+
+impl RepeaterTargetState {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn to_fe(&self, name: &str) -> FeOSCRepeaterStats {
+        FeOSCRepeaterStats {
+            name: name.to_string(),
+            enabled: self.enabled.load(Ordering::Relaxed),
+            forwarded_packets: self.forwarded_packets.load(Ordering::Relaxed),
+            forwarded_bytes: self.forwarded_bytes.load(Ordering::Relaxed),
+            filtered_packets: self.filtered_packets.load(Ordering::Relaxed),
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    // Keyed by target name
+    pub static ref REPEATER_STATE: Mutex<HashMap<String, Arc<RepeaterTargetState>>> = Mutex::new(HashMap::new());
+}
+
+/*
+ * Gets the live state of a target, creating it with the configured enabled flag if it does not exist yet
+ */
+fn target_state(target: &OSCRepeaterTarget) -> Arc<RepeaterTargetState> {
+    let mut states = REPEATER_STATE.lock();
+    let state = states.entry(target.name.clone()).or_default().clone();
+    state.set_enabled(target.enabled);
+    state
+}
+
+/*
+ * Drops states of targets that were removed from the config
+ */
+pub fn retain_repeater_targets(targets: &[OSCRepeaterTarget]) {
+    REPEATER_STATE
+        .lock()
+        .retain(|name, _| targets.iter().any(|t| &t.name == name));
+}
+
+struct RepeaterTarget {
+    config: OSCRepeaterTarget,
+    // Set once resolved, never set for targets that would forward back to a listener
+    address: Arc<OnceLock<SocketAddr>>,
+    state: Arc<RepeaterTargetState>,
+}
+
+pub struct OSCRepeater {
+    // Bound on first use for the address families in use
+    sock_v4: OnceLock<Option<UdpSocket>>,
+    sock_v6: OnceLock<Option<UdpSocket>>,
+    targets: Vec<RepeaterTarget>,
+}
+
+/*
+ * Sets the target address unless it would forward packets back to one of the listen sockets
+ */
+fn set_target_address(
+    target: &OSCRepeaterTarget,
+    address: SocketAddr,
+    listen_binds: &[SocketAddr],
+    resolved: &OnceLock<SocketAddr>,
+) {
+    if listen_binds.iter().any(|bind| reaches_bind(address, *bind)) {
+        warn!(
+            "Repeater target {} ({}) is a VibeCheck listener, not forwarding to it",
+            target.name, address
+        );
+        return;
+    }
+    let _ = resolved.set(address);
+}
+
+impl OSCRepeater {
+    /*
+     * IP targets are ready immediately, hostnames are resolved in the background so binding
+     * listeners never waits on DNS. Packets for a target are dropped until it is resolved.
+     */
+    pub fn new(targets: &[OSCRepeaterTarget], listen_binds: &[SocketAddr]) -> Self {
+        retain_repeater_targets(targets);
+
+        let targets: Vec<RepeaterTarget> = targets
+            .iter()
+            .map(|t| {
+                let address = Arc::new(OnceLock::new());
+                match t.address.literal() {
+                    Some(literal) => set_target_address(t, literal, listen_binds, &address),
+                    None => {
+                        let target = t.clone();
+                        let listen_binds = listen_binds.to_vec();
+                        let address = address.clone();
+                        thread::spawn(move || match target.address.resolve() {
+                            Ok(resolved) => {
+                                debug!("Resolved repeater target {} to {}", target.name, resolved);
+                                set_target_address(&target, resolved, &listen_binds, &address);
+                            }
+                            Err(e) => warn!(
+                                "Failed to resolve repeater target {} ({}): {}",
+                                target.name, target.address, e
+                            ),
+                        });
+                    }
+                }
+                RepeaterTarget {
+                    config: t.clone(),
+                    address,
+                    state: target_state(t),
+                }
+            })
+            .collect();
+
+        if !targets.is_empty() {
+            info!("OSC repeater forwarding to {} targets", targets.len());
+        }
+
+        Self {
+            sock_v4: OnceLock::new(),
+            sock_v6: OnceLock::new(),
+            targets,
+        }
+    }
+
+    fn sock_for(&self, address: &SocketAddr) -> Option<&UdpSocket> {
+        let sock = if address.is_ipv6() {
+            &self.sock_v6
+        } else {
+            &self.sock_v4
+        };
+        sock.get_or_init(|| match UdpSocket::bind(sender_bind_addr(address)) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("Failed to bind OSC repeater socket: {}", e);
+                None
+            }
+        })
+        .as_ref()
+    }
+
+    /*
+     * Forwards a received datagram to every enabled target
+     * Targets without filter or rewrite receive the raw bytes untouched.
+     * The decoded packet is only needed (and re-encoded) for filtering / rewriting targets,
+     * or when it has VibeCheck API messages which are never forwarded.
+     */
+    pub fn forward(&self, raw: &[u8], pkt: Option<&OscPacket>) {
+        let has_api = pkt.is_some_and(has_api_message);
+
+        for RepeaterTarget {
+            config: target,
+            address,
+            state,
+        } in &self.targets
+        {
+            if !state.enabled.load(Ordering::Relaxed) {
+                continue;
+            }
+            let Some(address) = address.get() else {
+                continue;
+            };
+            let Some(sock) = self.sock_for(address) else {
+                continue;
+            };
+
+            let send_res = if target.filter.is_none() && target.rewrite.is_none() && !has_api {
+                sock.send_to(raw, address)
+            } else {
+                // Undecodable packets can't be matched against a filter
+                let Some(out_pkt) = pkt.and_then(|p| filter_rewrite(p, target)) else {
+                    state.filtered_packets.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                };
+                match encoder::encode(&out_pkt) {
+                    Ok(buf) => sock.send_to(&buf, address),
+                    Err(e) => {
+                        debug!("Failed to encode packet for {}: {:?}", target.name, e);
+                        state.send_errors.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                }
+            };
+
+            match send_res {
+                Ok(sent) => {
+                    state.forwarded_packets.fetch_add(1, Ordering::Relaxed);
+                    state
+                        .forwarded_bytes
+                        .fetch_add(sent as u64, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    debug!("Failed to forward packet to {}: {}", target.name, e);
+                    state.send_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+/*
+ * API messages are meant for VibeCheck and can carry a listener's API secret
+ */
+fn is_api_message(addr: &str) -> bool {
+    addr.strip_prefix(API_PREFIX)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn has_api_message(pkt: &OscPacket) -> bool {
+    match pkt {
+        OscPacket::Message(msg) => is_api_message(&msg.addr),
+        OscPacket::Bundle(bundle) => bundle.content.iter().any(has_api_message),
+    }
+}
+
+/*
+ * Applies a target's filter and rewrite to a packet
+ * API messages are dropped before filtering.
+ * Bundles keep their timetag and only the matching messages. Returns None when nothing matches.
+ */
+fn filter_rewrite(pkt: &OscPacket, target: &OSCRepeaterTarget) -> Option<OscPacket> {
+    match pkt {
+        OscPacket::Message(msg) => {
+            if is_api_message(&msg.addr) {
+                return None;
+            }
+            if let Some(filter) = target.filter.as_ref() {
+                if !glob_match(filter, &msg.addr) {
+                    return None;
+                }
+            }
+
+            let mut msg = msg.clone();
+            if let Some(addr) = target.rewrite.as_ref().and_then(|rw| rw.apply(&msg.addr)) {
+                msg.addr = addr;
+            }
+            Some(OscPacket::Message(msg))
+        }
+        OscPacket::Bundle(bundle) => {
+            let content: Vec<OscPacket> = bundle
+                .content
+                .iter()
+                .filter_map(|inner| filter_rewrite(inner, target))
+                .collect();
+
+            if content.is_empty() {
+                None
+            } else {
+                Some(OscPacket::Bundle(OscBundle {
+                    timetag: bundle.timetag,
+                    content,
+                }))
+            }
+        }
+    }
+}
+
+/*
+ * Stats for every configured repeater target
+ */
+pub fn repeater_stats(targets: &[OSCRepeaterTarget]) -> Vec<FeOSCRepeaterStats> {
+    targets
+        .iter()
+        .map(|t| match REPEATER_STATE.lock().get(&t.name) {
+            Some(state) => state.to_fe(&t.name),
+            // Not listening yet
+            None => {
+                let state = RepeaterTargetState::default();
+                state.set_enabled(t.enabled);
+                state.to_fe(&t.name)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscMessage, OscTime, OscType};
+
+    fn message(addr: &str) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.to_string(),
+            args: vec![OscType::Bool(true)],
+        })
+    }
+
+    fn addrs(pkt: &OscPacket) -> Vec<String> {
+        match pkt {
+            OscPacket::Message(msg) => vec![msg.addr.clone()],
+            OscPacket::Bundle(bundle) => bundle.content.iter().flat_map(addrs).collect(),
+        }
+    }
+
+    #[test]
+    fn api_messages_are_not_forwarded() {
+        let target = OSCRepeaterTarget {
+            name: "face tracking".to_string(),
+            address: "127.0.0.1:9100".parse().unwrap(),
+            enabled: true,
+            filter: None,
+            rewrite: None,
+        };
+        let secret = message("/avatar/parameters/vibecheck/api/hunter2/state");
+        assert!(has_api_message(&secret));
+        assert!(filter_rewrite(&secret, &target).is_none());
+
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::from((0, 1)),
+            content: vec![
+                secret,
+                message("/avatar/parameters/VoiceLevel"),
+                message("/avatar/parameters/vibecheck/apiary"),
+            ],
+        });
+        assert!(has_api_message(&bundle));
+        let forwarded = filter_rewrite(&bundle, &target).unwrap();
+        assert_eq!(
+            addrs(&forwarded),
+            [
+                "/avatar/parameters/VoiceLevel",
+                "/avatar/parameters/vibecheck/apiary"
+            ]
+        );
+        assert!(!has_api_message(&forwarded));
+    }
+
+    #[test]
+    fn filters_and_rewrites_forwarded_messages() {
+        let target = OSCRepeaterTarget {
+            name: "face tracking".to_string(),
+            address: "127.0.0.1:9100".parse().unwrap(),
+            enabled: true,
+            filter: Some("/avatar/parameters/FT/*".to_string()),
+            rewrite: Some(OSCAddressRewrite {
+                from: "/avatar/parameters/FT/".to_string(),
+                to: "/tracking/".to_string(),
+            }),
+        };
+
+        let forwarded = filter_rewrite(&message("/avatar/parameters/FT/JawOpen"), &target).unwrap();
+        assert_eq!(addrs(&forwarded), ["/tracking/JawOpen"]);
+        assert!(filter_rewrite(&message("/avatar/parameters/VoiceLevel"), &target).is_none());
+
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::from((1, 2)),
+            content: vec![
+                message("/avatar/parameters/VoiceLevel"),
+                message("/avatar/parameters/FT/EyeLidLeft"),
+                OscPacket::Bundle(OscBundle {
+                    timetag: OscTime::from((1, 2)),
+                    content: vec![message("/avatar/parameters/FT/MouthX")],
+                }),
+            ],
+        });
+        let Some(OscPacket::Bundle(forwarded)) = filter_rewrite(&bundle, &target) else {
+            panic!("bundle was not forwarded");
+        };
+        assert_eq!(forwarded.timetag, OscTime::from((1, 2)));
+        assert_eq!(
+            addrs(&OscPacket::Bundle(forwarded)),
+            ["/tracking/EyeLidLeft", "/tracking/MouthX"]
+        );
+
+        // Nothing left to forward
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::from((1, 2)),
+            content: vec![message("/avatar/parameters/VoiceLevel")],
+        });
+        assert!(filter_rewrite(&bundle, &target).is_none());
+
+        // A rewrite without a matching prefix leaves the address alone
+        let target = OSCRepeaterTarget {
+            filter: None,
+            ..target
+        };
+        let forwarded = filter_rewrite(&message("/avatar/parameters/VoiceLevel"), &target).unwrap();
+        assert_eq!(addrs(&forwarded), ["/avatar/parameters/VoiceLevel"]);
+    }
+}
@@ -0,0 +1,481 @@
+/*
+ * OSC over TCP
+ * OSC 1.1 frames packets with SLIP (double END) and OSC 1.0 prefixes each packet with its int32 size.
+ */
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, ErrorKind, Read},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tauri::AppHandle;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpStream as tTcpStream, UdpSocket as tUdpSocket},
+};
+use ts_rs::TS;
+
+use crate::{
+    osc_api::osc_api::handle_osc_packet, util::net::sender_bind_addr, vcore::config::OSCListener,
+};
+
+use super::{
+    ingress::{reject_source, SourceRejection},
+    logic::{ingress_datagram, LISTENER_POLL_INTERVAL},
+    repeater::OSCRepeater,
+};
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+// Larger frames are dropped (SLIP) or close the connection (length prefixed)
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+// Open connections per TCP listener, more are refused
+const MAX_CONNECTIONS: usize = 16;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, TS)]
+#[ts(export)]
+pub enum OSCFraming {
+    // OSC 1.1
+    Slip,
+    // OSC 1.0
+    LengthPrefixed,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, TS)]
+#[ts(export)]
+#[serde(tag = "kind", content = "framing")]
+pub enum OSCTransport {
+    #[default]
+    Udp,
+    Tcp(OSCFraming),
+}
+
+#[derive(Debug)]
+pub enum FramingError {
+    FrameTooLarge(usize),
+}
+
+/*
+ * Splits a TCP byte stream into OSC packets
+ * Bytes can be pushed in arbitrary chunks, complete frames are returned as they are found
+ */
+pub struct FrameDecoder {
+    framing: OSCFraming,
+    buf: Vec<u8>,
+    // SLIP state
+    escaped: bool,
+    discarding: bool,
+}
+
+impl FrameDecoder {
+    pub fn new(framing: OSCFraming) -> Self {
+        Self {
+            framing,
+            buf: Vec::new(),
+            escaped: false,
+            discarding: false,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, FramingError> {
+        match self.framing {
+            OSCFraming::Slip => Ok(self.push_slip(data)),
+            OSCFraming::LengthPrefixed => self.push_length_prefixed(data),
+        }
+    }
+
+    fn push_slip(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+
+        for &byte in data {
+            if byte == SLIP_END {
+                // Double END framing produces empty frames between packets
+                if !self.buf.is_empty() && !self.discarding {
+                    frames.push(std::mem::take(&mut self.buf));
+                }
+                self.buf.clear();
+                self.escaped = false;
+                self.discarding = false;
+                continue;
+            }
+
+            if self.discarding {
+                continue;
+            }
+
+            let byte = if self.escaped {
+                self.escaped = false;
+                match byte {
+                    SLIP_ESC_END => SLIP_END,
+                    SLIP_ESC_ESC => SLIP_ESC,
+                    // Protocol violation, keep the byte as is
+                    other => other,
+                }
+            } else if byte == SLIP_ESC {
+                self.escaped = true;
+                continue;
+            } else {
+                byte
+            };
+
+            self.buf.push(byte);
+            if self.buf.len() > MAX_FRAME_SIZE {
+                debug!("Dropping oversized SLIP frame");
+                self.buf.clear();
+                self.discarding = true;
+            }
+        }
+
+        frames
+    }
+
+    fn push_length_prefixed(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, FramingError> {
+        self.buf.extend_from_slice(data);
+        let mut frames = Vec::new();
+
+        loop {
+            if self.buf.len() < 4 {
+                break;
+            }
+
+            let size =
+                u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]) as usize;
+            // The stream can't be resynchronized after a bogus size
+            if size > MAX_FRAME_SIZE {
+                return Err(FramingError::FrameTooLarge(size));
+            }
+            if self.buf.len() < 4 + size {
+                break;
+            }
+
+            frames.push(self.buf[4..4 + size].to_vec());
+            self.buf.drain(..4 + size);
+        }
+
+        Ok(frames)
+    }
+}
+
+/*
+ * Frames an encoded OSC packet for sending over TCP
+ */
+pub fn encode_frame(framing: OSCFraming, packet: &[u8]) -> Vec<u8> {
+    match framing {
+        OSCFraming::Slip => {
+            let mut out = Vec::with_capacity(packet.len() + 2);
+            out.push(SLIP_END);
+            for &byte in packet {
+                match byte {
+                    SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                    SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                    other => out.push(other),
+                }
+            }
+            out.push(SLIP_END);
+            out
+        }
+        OSCFraming::LengthPrefixed => {
+            let mut out = Vec::with_capacity(packet.len() + 4);
+            out.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+            out.extend_from_slice(packet);
+            out
+        }
+    }
+}
+
+/*
+ * Counts a connection as open until it is dropped
+ */
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn take(open: &Arc<AtomicUsize>) -> Option<Self> {
+        open.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+            (n < MAX_CONNECTIONS).then_some(n + 1)
+        })
+        .ok()
+        .map(|_| Self(open.clone()))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/*
+ * Accepts TCP connections on a listener until the listeners are stopped
+ * Every connection gets its own thread feeding the same ingress as the UDP listeners,
+ * up to MAX_CONNECTIONS at a time
+ */
+pub fn tcp_listener_routine(
+    tcp_listener: TcpListener,
+    listener: OSCListener,
+    framing: OSCFraming,
+    repeater: Arc<OSCRepeater>,
+    app_handle: AppHandle,
+    stop: Arc<AtomicBool>,
+) {
+    // Non blocking accept so the routine notices when listening stops
+    if let Err(e) = tcp_listener.set_nonblocking(true) {
+        warn!(
+            "Failed to set TCP listener {} non blocking: {}",
+            listener.name, e
+        );
+        return;
+    }
+    let listener = Arc::new(listener);
+    let open_connections = Arc::new(AtomicUsize::new(0));
+
+    while !stop.load(Ordering::Relaxed) {
+        match tcp_listener.accept() {
+            Ok((stream, peer)) => {
+                if !listener.allows(peer.ip()) {
+                    reject_source(&listener.name, peer.ip(), SourceRejection::NotAllowed);
+                    continue;
+                }
+                let Some(slot) = ConnectionSlot::take(&open_connections) else {
+                    warn!(
+                        "Refusing OSC TCP connection from {} on {}: {} connections open",
+                        peer, listener.name, MAX_CONNECTIONS
+                    );
+                    continue;
+                };
+                info!("OSC TCP connection from {} on {}", peer, listener.name);
+
+                let listener = listener.clone();
+                let repeater = repeater.clone();
+                let app_handle = app_handle.clone();
+                let stop = stop.clone();
+                thread::spawn(move || {
+                    let _slot = slot;
+                    tcp_connection_routine(
+                        stream,
+                        peer,
+                        &listener,
+                        framing,
+                        &repeater,
+                        &app_handle,
+                        &stop,
+                    )
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => {
+                warn!("Failed to accept OSC TCP connection: {}", e);
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+    info!("Shutting down TCP listener {}", listener.name);
+}
+
+fn tcp_connection_routine(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    listener: &OSCListener,
+    framing: OSCFraming,
+    repeater: &OSCRepeater,
+    app_handle: &AppHandle,
+    stop: &AtomicBool,
+) {
+    // Accepted streams can inherit non blocking mode from the listener on some platforms
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(LISTENER_POLL_INTERVAL));
+
+    let mut decoder = FrameDecoder::new(framing);
+    let mut buf = [0u8; 4096];
+
+    loop {
+        let br = match stream.read(&mut buf) {
+            // Peer closed the connection
+            Ok(0) => return,
+            Ok(br) => br,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                continue;
+            }
+            Err(e) => {
+                debug!("OSC TCP connection closed: {}", e);
+                return;
+            }
+        };
+
+        let frames = match decoder.push(&buf[..br]) {
+            Ok(frames) => frames,
+            Err(e) => {
+                warn!("Closing OSC TCP connection on {}: {:?}", listener.name, e);
+                return;
+            }
+        };
+
+        for frame in frames {
+            let Some(pkt) = ingress_datagram(&frame, repeater) else {
+                continue;
+            };
+            handle_osc_packet(pkt, peer.ip(), listener, app_handle);
+        }
+    }
+}
+
+/*
+ * Outbound OSC connection over the configured transport
+ * Used for osc_data sent to the remote
+ */
+pub enum OSCSender {
+    Udp(tUdpSocket),
+    Tcp(tTcpStream, OSCFraming),
+}
+
+impl OSCSender {
+    pub async fn connect(remote: SocketAddr, transport: OSCTransport) -> io::Result<Self> {
+        match transport {
+            OSCTransport::Udp => {
+                let sock = tUdpSocket::bind(sender_bind_addr(&remote)).await?;
+                sock.connect(remote).await?;
+                Ok(Self::Udp(sock))
+            }
+            OSCTransport::Tcp(framing) => {
+                Ok(Self::Tcp(tTcpStream::connect(remote).await?, framing))
+            }
+        }
+    }
+
+    pub async fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Udp(sock) => sock.send(packet).await.map(|_| ()),
+            Self::Tcp(stream, framing) => stream.write_all(&encode_frame(*framing, packet)).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKETS: [&[u8]; 4] = [
+        b"/avatar/parameters/a\0\0\0\0,f\0\0\x3f\0\0\0",
+        &[SLIP_END, SLIP_ESC, SLIP_ESC_END, SLIP_ESC_ESC, 0, SLIP_END],
+        &[SLIP_ESC, SLIP_ESC],
+        b"x",
+    ];
+
+    /*
+     * Encodes every packet and feeds the stream to a decoder in chunks of chunk_len bytes
+     */
+    fn round_trip(framing: OSCFraming, chunk_len: usize) -> Vec<Vec<u8>> {
+        let stream: Vec<u8> = PACKETS
+            .iter()
+            .flat_map(|p| encode_frame(framing, p))
+            .collect();
+        let mut decoder = FrameDecoder::new(framing);
+        stream
+            .chunks(chunk_len)
+            .flat_map(|chunk| decoder.push(chunk).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn slip_round_trips_in_any_chunks() {
+        for chunk_len in [1, 2, 3, 7, 4096] {
+            assert_eq!(round_trip(OSCFraming::Slip, chunk_len), PACKETS);
+        }
+    }
+
+    #[test]
+    fn length_prefixed_round_trips_in_any_chunks() {
+        for chunk_len in [1, 2, 3, 7, 4096] {
+            assert_eq!(round_trip(OSCFraming::LengthPrefixed, chunk_len), PACKETS);
+        }
+    }
+
+    #[test]
+    fn slip_escapes_end_and_esc() {
+        assert_eq!(
+            encode_frame(OSCFraming::Slip, &[1, SLIP_END, SLIP_ESC]),
+            [
+                SLIP_END,
+                1,
+                SLIP_ESC,
+                SLIP_ESC_END,
+                SLIP_ESC,
+                SLIP_ESC_ESC,
+                SLIP_END
+            ]
+        );
+    }
+
+    #[test]
+    fn slip_drops_oversized_frames_and_recovers() {
+        let mut decoder = FrameDecoder::new(OSCFraming::Slip);
+        let oversized = vec![1u8; MAX_FRAME_SIZE + 1];
+        assert!(decoder
+            .push(&encode_frame(OSCFraming::Slip, &oversized))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            decoder
+                .push(&encode_frame(OSCFraming::Slip, b"ok"))
+                .unwrap(),
+            [b"ok"]
+        );
+    }
+
+    #[test]
+    fn length_prefixed_rejects_oversized_frames() {
+        let mut decoder = FrameDecoder::new(OSCFraming::LengthPrefixed);
+        let size = (MAX_FRAME_SIZE as u32 + 1).to_be_bytes();
+        assert!(matches!(
+            decoder.push(&size),
+            Err(FramingError::FrameTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn sender_frames_packets_over_localhost() {
+        for framing in [OSCFraming::Slip, OSCFraming::LengthPrefixed] {
+            let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = tcp_listener.local_addr().unwrap();
+            tokio::runtime::Runtime::new().unwrap().block_on(async {
+                let mut sender = OSCSender::connect(addr, OSCTransport::Tcp(framing))
+                    .await
+                    .unwrap();
+                for packet in PACKETS {
+                    sender.send(packet).await.unwrap();
+                }
+            });
+
+            // The sender was dropped so the stream ends after the frames
+            let (mut stream, _) = tcp_listener.accept().unwrap();
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            assert_eq!(FrameDecoder::new(framing).push(&received).unwrap(), PACKETS);
+        }
+    }
+
+    #[test]
+    fn connection_slots_are_capped_and_released() {
+        let open = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<ConnectionSlot> = (0..MAX_CONNECTIONS)
+            .map(|_| ConnectionSlot::take(&open).unwrap())
+            .collect();
+        assert!(ConnectionSlot::take(&open).is_none());
+        drop(slots);
+        assert_eq!(open.load(Ordering::Relaxed), 0);
+        assert!(ConnectionSlot::take(&open).is_some());
+    }
+}
@@ -0,0 +1,488 @@
+/*
+ * osc_data telemetry
+ * Periodically sends the state of toys with OSC data enabled to the telemetry targets.
+ * Addresses are built from a template and the sent fields are selected in the telemetry config.
+ */
+use futures_timer::Delay;
+use log::{debug, info, trace, warn};
+use parking_lot::Mutex;
+use rosc::{encoder, OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    sync::Arc,
+    time::Duration,
+};
+use tauri::{api::notification::Notification, AppHandle, Manager};
+use ts_rs::TS;
+
+use crate::{
+    config::{TelemetryConfig, TelemetryTarget},
+    frontend::{
+        frontend_types::{FeBatteryEvent, FeCoreEvent, FeToyEvent, FeVCToy},
+        ToFrontend,
+    },
+    toy_handling::{
+        battery::{record_battery_level, BatteryAlertLevel},
+        toyops::{VCFeatureType, VCToy},
+        ToyPower,
+    },
+    vcore::core::VibeCheckState,
+};
+
+use super::{
+    chatbox::{announce, ChatboxAnnouncement},
+    feedback::CONNECT_TIMEOUT,
+    tcp::OSCSender,
+};
+
+pub const DEFAULT_ADDRESS_TEMPLATE: &str =
+    "/avatar/parameters/vibecheck/osc_data/{toy}/{sub_id}/{field}";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, TS)]
+#[ts(export)]
+pub enum TelemetryField {
+    // Float 0-1, 0 for toys without a battery
+    Battery,
+    // Bool
+    Connected,
+    // Int dBm, skipped for toys that don't report it
+    Rssi,
+    // Int count of features currently driven above 0
+    ActiveFeatures,
+}
+
+impl TelemetryField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Battery => "battery",
+            Self::Connected => "connected",
+            Self::Rssi => "rssi",
+            Self::ActiveFeatures => "active_features",
+        }
+    }
+}
+
+/*
+ * Toy name as used in osc_data addresses
+ * eg. Lovense Connect Hush -> lovense_hush
+ */
+pub fn toy_slug(toy_name: &str) -> String {
+    toy_name
+        .replace("Lovense Connect", "lovense")
+        .replace(' ', "_")
+        .to_lowercase()
+}
+
+/*
+ * Fills the {toy}, {alias}, {sub_id} and {field} placeholders of a template
+ * {alias} falls back to {toy} for toys without an alias
+ */
+pub fn format_telemetry_addr(
+    template: &str,
+    toy_name: &str,
+    alias: Option<&str>,
+    sub_id: u8,
+    field: &str,
+) -> String {
+    let toy = toy_slug(toy_name);
+    template
+        .replace("{toy}", &toy)
+        .replace("{alias}", alias.unwrap_or(&toy))
+        .replace("{sub_id}", &sub_id.to_string())
+        .replace("{field}", field)
+}
+
+lazy_static::lazy_static! {
+    // (toy id, feature type, feature index) -> driven above 0
+    static ref ACTIVE_FEATURES: Mutex<HashMap<(u32, VCFeatureType, u32), bool>> = Mutex::new(HashMap::new());
+}
+
+/*
+ * Tracks the last level sent to a toy feature for the ActiveFeatures field
+ */
+pub fn record_feature_level(
+    toy_id: u32,
+    feature_type: VCFeatureType,
+    feature_index: u32,
+    level: f64,
+) {
+    ACTIVE_FEATURES
+        .lock()
+        .insert((toy_id, feature_type, feature_index), level > 0.0);
+}
+
+/*
+ * Forgets the feature levels of a toy or of every toy when None
+ */
+pub fn forget_feature_levels(toy_id: Option<u32>) {
+    let mut active = ACTIVE_FEATURES.lock();
+    match toy_id {
+        Some(toy_id) => active.retain(|(id, ..), _| *id != toy_id),
+        None => active.clear(),
+    }
+}
+
+fn active_feature_count(toy_id: u32) -> i32 {
+    ACTIVE_FEATURES
+        .lock()
+        .iter()
+        .filter(|((id, ..), active)| *id == toy_id && **active)
+        .count() as i32
+}
+
+/*
+ * Senders for the telemetry targets
+ * Connections are kept between refreshes and reopened on the next refresh after a failed send
+ */
+struct TelemetrySenders {
+    senders: Vec<(TelemetryTarget, Option<OSCSender>)>,
+}
+
+impl TelemetrySenders {
+    fn update_targets(&mut self, targets: &[TelemetryTarget]) {
+        let current: Vec<&TelemetryTarget> = self.senders.iter().map(|(t, _)| t).collect();
+        if current.len() == targets.len() && current.iter().zip(targets).all(|(a, b)| *a == b) {
+            return;
+        }
+        info!("Telemetry targets changed, reconnecting");
+        self.senders = targets.iter().map(|t| (t.clone(), None)).collect();
+    }
+
+    async fn send(&mut self, packets: &[Vec<u8>]) {
+        for (target, sender) in &mut self.senders {
+            if sender.is_none() {
+                // A dead TCP target must not stall the refresh
+                let connected = tokio::time::timeout(CONNECT_TIMEOUT, async {
+                    let addr = target.address.resolve_async().await?;
+                    OSCSender::connect(addr, target.transport).await
+                })
+                .await
+                .unwrap_or_else(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Connection timed out",
+                    ))
+                });
+                match connected {
+                    Ok(s) => {
+                        debug!("Connected telemetry sender to {}", target.address);
+                        *sender = Some(s);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to connect telemetry sender to {}: {}",
+                            target.address, e
+                        );
+                        continue;
+                    }
+                }
+            }
+
+            let Some(s) = sender.as_mut() else {
+                continue;
+            };
+            for packet in packets {
+                if let Err(e) = s.send(packet).await {
+                    warn!("Failed to send telemetry to {}: {}", target.address, e);
+                    *sender = None;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/*
+ * Refreshes toy battery levels for the frontend and sends telemetry for toys with OSC data enabled
+ * The config is read every refresh so interval, target and field changes apply without a restart
+ */
+pub async fn toy_refresh(
+    vibecheck_state_pointer: Arc<Mutex<VibeCheckState>>,
+    app_handle: AppHandle,
+) {
+    let mut senders = TelemetrySenders {
+        senders: Vec::new(),
+    };
+
+    loop {
+        let (config, remote_target, battery_alerts, notifications) = {
+            let vc_lock = vibecheck_state_pointer.lock();
+            (
+                vc_lock.config.telemetry.clone(),
+                TelemetryTarget {
+                    address: vc_lock.config.networking.remote.clone(),
+                    transport: vc_lock.config.networking.remote_transport,
+                },
+                vc_lock.config.battery_alerts.clone(),
+                vc_lock
+                    .config
+                    .desktop_notifications
+                    .then(|| vc_lock.identifier.clone()),
+            )
+        };
+        Delay::new(Duration::from_secs(config.interval_secs.max(1))).await;
+
+        let toys = {
+            let vc_lock = vibecheck_state_pointer.lock();
+            match vc_lock.core_toy_manager.as_ref() {
+                Some(tm) if !tm.online_toys.is_empty() => tm.online_toys.clone(),
+                _ => continue,
+            }
+        };
+
+        let mut packets = Vec::new();
+        for (.., mut toy) in toys {
+            // Can use this to differ between toys with batteries and toys without!
+            let toy_power = if toy.device_handle.has_battery_level() {
+                match toy.device_handle.battery_level().await {
+                    Ok(battery_lvl) => ToyPower::Battery(battery_lvl),
+                    Err(_e) => {
+                        warn!("Device battery_level() error: {:?}", _e);
+                        ToyPower::Pending
+                    }
+                }
+            } else {
+                ToyPower::NoBattery
+            };
+
+            toy.toy_power = toy_power.clone();
+
+            if let ToyPower::Battery(level) = toy_power {
+                if let Some(alert) = record_battery_level(
+                    toy.toy_id,
+                    &toy.toy_name,
+                    toy.sub_id,
+                    level,
+                    battery_alerts.thresholds.get(&toy.toy_name),
+                ) {
+                    battery_alert(&app_handle, &toy, level, alert, notifications.as_deref());
+
+                    // Sent regardless of the selected fields so avatars can react to alerts
+                    for (field, value) in [
+                        ("battery_low", alert != BatteryAlertLevel::Normal),
+                        ("battery_critical", alert == BatteryAlertLevel::Critical),
+                    ] {
+                        let addr = format_telemetry_addr(
+                            &config.address_template,
+                            &toy.toy_name,
+                            config.aliases.get(&toy.toy_name).map(String::as_str),
+                            toy.sub_id,
+                            field,
+                        );
+                        if let Ok(packet) = encoder::encode(&OscPacket::Message(OscMessage {
+                            addr,
+                            args: vec![OscType::Bool(value)],
+                        })) {
+                            packets.push(packet);
+                        }
+                    }
+                }
+            }
+
+            let _ = app_handle.emit_all(
+                "fe_toy_event",
+                FeToyEvent::Update({
+                    FeVCToy {
+                        toy_id: Some(toy.toy_id),
+                        toy_name: toy.toy_name.clone(),
+                        toy_anatomy: toy.config.as_ref().unwrap().anatomy.to_fe(),
+                        toy_power: toy_power.clone(),
+                        toy_connected: toy.toy_connected,
+                        features: toy.parsed_toy_features.features.to_frontend(),
+                        listening: toy.listening,
+                        osc_data: toy.osc_data,
+                        sub_id: toy.sub_id,
+                    }
+                }),
+            );
+
+            if !toy.osc_data {
+                trace!("OSC data disabled for toy {}", toy.toy_name);
+                continue;
+            }
+            trace!("Building OSC data for toy: {}", toy.toy_name);
+
+            for field in &config.fields {
+                let arg = match field {
+                    TelemetryField::Battery => OscType::Float(toy_power.to_float() as f32),
+                    TelemetryField::Connected => OscType::Bool(toy.toy_connected),
+                    TelemetryField::Rssi => {
+                        if !toy.device_handle.has_rssi_level() {
+                            continue;
+                        }
+                        match toy.device_handle.rssi_level().await {
+                            Ok(rssi) => OscType::Int(rssi),
+                            Err(e) => {
+                                debug!("Device rssi_level() error: {:?}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    TelemetryField::ActiveFeatures => {
+                        OscType::Int(active_feature_count(toy.toy_id))
+                    }
+                };
+
+                let addr = format_telemetry_addr(
+                    &config.address_template,
+                    &toy.toy_name,
+                    config.aliases.get(&toy.toy_name).map(String::as_str),
+                    toy.sub_id,
+                    field.name(),
+                );
+                if let Ok(packet) = encoder::encode(&OscPacket::Message(OscMessage {
+                    addr,
+                    args: vec![arg],
+                })) {
+                    packets.push(packet);
+                }
+            }
+        }
+
+        if packets.is_empty() {
+            continue;
+        }
+
+        if config.targets.is_empty() {
+            senders.update_targets(std::slice::from_ref(&remote_target));
+        } else {
+            senders.update_targets(&config.targets);
+        }
+        senders.send(&packets).await;
+        trace!(
+            "Sent {} telemetry messages to {} targets",
+            packets.len(),
+            senders.senders.len()
+        );
+    }
+}
+
+/*
+ * Tells the frontend, desktop and chatbox that a toy's battery alert level changed
+ * Recoveries only reach the frontend
+ */
+fn battery_alert(
+    app_handle: &AppHandle,
+    toy: &VCToy,
+    level: f64,
+    alert: BatteryAlertLevel,
+    notification_identifier: Option<&str>,
+) {
+    let _ = app_handle.emit_all(
+        "fe_core_event",
+        FeCoreEvent::Battery(FeBatteryEvent {
+            toy_id: toy.toy_id,
+            toy_name: toy.toy_name.clone(),
+            sub_id: toy.sub_id,
+            level,
+            alert,
+        }),
+    );
+
+    let (title, alert_name) = match alert {
+        BatteryAlertLevel::Normal => return,
+        BatteryAlertLevel::Low => ("Low Battery", "low"),
+        BatteryAlertLevel::Critical => ("Critical Battery", "critical"),
+    };
+    let battery = format!("{:.0}%", level * 100.0);
+
+    if let Some(identifier) = notification_identifier {
+        let _ = Notification::new(identifier)
+            .title(title)
+            .body(format!("{} ({})", toy.toy_name, battery))
+            .show();
+    }
+    announce(ChatboxAnnouncement::BatteryLow {
+        toy: &toy.toy_name,
+        battery: &battery,
+        alert: alert_name,
+    });
+}
+
+/*
+ * Trims the aliases and drops empty ones so those toys fall back to {toy}
+ */
+pub fn clean_aliases(aliases: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    aliases
+        .into_iter()
+        .map(|(toy, alias)| (toy, alias.trim().to_string()))
+        .filter(|(_, alias)| !alias.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: [TelemetryField; 4] = [
+        TelemetryField::Battery,
+        TelemetryField::Connected,
+        TelemetryField::Rssi,
+        TelemetryField::ActiveFeatures,
+    ];
+
+    #[test]
+    fn default_template_addresses_every_field() {
+        let addrs: Vec<String> = FIELDS
+            .iter()
+            .map(|field| {
+                format_telemetry_addr(
+                    DEFAULT_ADDRESS_TEMPLATE,
+                    "Lovense Connect Hush",
+                    None,
+                    1,
+                    field.name(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            addrs,
+            [
+                "/avatar/parameters/vibecheck/osc_data/lovense_hush/1/battery",
+                "/avatar/parameters/vibecheck/osc_data/lovense_hush/1/connected",
+                "/avatar/parameters/vibecheck/osc_data/lovense_hush/1/rssi",
+                "/avatar/parameters/vibecheck/osc_data/lovense_hush/1/active_features",
+            ]
+        );
+    }
+
+    #[test]
+    fn alias_falls_back_to_the_toy() {
+        let template = "/avatar/parameters/{alias}_{field}";
+        assert_eq!(
+            format_telemetry_addr(template, "We-Vibe Nova 2", Some("nova"), 0, "battery"),
+            "/avatar/parameters/nova_battery"
+        );
+        assert_eq!(
+            format_telemetry_addr(template, "We-Vibe Nova 2", None, 0, "battery"),
+            "/avatar/parameters/we-vibe_nova_2_battery"
+        );
+
+        // Placeholders can repeat and mix
+        assert_eq!(
+            format_telemetry_addr(
+                "/{toy}/{alias}/{sub_id}/{sub_id}",
+                "Hush",
+                Some("h"),
+                3,
+                "x"
+            ),
+            "/hush/h/3/3"
+        );
+    }
+
+    #[test]
+    fn empty_aliases_are_dropped() {
+        let aliases = BTreeMap::from([
+            ("Hush".to_string(), "  hush  ".to_string()),
+            ("Nora".to_string(), "   ".to_string()),
+            ("Lush".to_string(), String::new()),
+        ]);
+        assert_eq!(
+            clean_aliases(aliases),
+            BTreeMap::from([("Hush".to_string(), "hush".to_string())])
+        );
+    }
+}
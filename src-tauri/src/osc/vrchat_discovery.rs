@@ -0,0 +1,377 @@
+/*
+ * OSCQuery client for VRChat
+ * Discovers VRChat's OSCQuery service over mDNS and reads its OSC receive port and the current avatar's parameters.
+ */
+use log::{debug, info};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    util::net::{is_local_ip, HostPort},
+    vcore::vcerror::frontend::VCFeError,
+};
+
+const OSCQUERY_SERVICE_TYPE: &str = "_oscjson._tcp.local.";
+const VRCHAT_SERVICE_PREFIX: &str = "VRChat-Client-";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+pub const DEFAULT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Deserialize)]
+struct HostInfo {
+    #[serde(rename = "NAME", default)]
+    name: Option<String>,
+    #[serde(rename = "OSC_IP")]
+    osc_ip: String,
+    #[serde(rename = "OSC_PORT")]
+    osc_port: u16,
+}
+
+#[derive(Deserialize)]
+struct RemoteNode {
+    #[serde(rename = "FULL_PATH")]
+    full_path: String,
+    #[serde(rename = "TYPE", default)]
+    osc_type: Option<String>,
+    #[serde(rename = "VALUE", default)]
+    value: Option<Vec<serde_json::Value>>,
+    #[serde(rename = "CONTENTS", default)]
+    contents: HashMap<String, RemoteNode>,
+}
+
+impl RemoteNode {
+    /*
+     * Collects every typed node below this one
+     */
+    fn collect_parameters(&self, out: &mut Vec<(String, String)>) {
+        if let Some(osc_type) = self.osc_type.as_ref() {
+            out.push((self.full_path.clone(), osc_type.clone()));
+        }
+        for node in self.contents.values() {
+            node.collect_parameters(out);
+        }
+    }
+}
+
+/*
+ * What VRChat advertised over OSCQuery
+ */
+pub struct VRChatOSCQuery {
+    pub name: Option<String>,
+    pub http: HostPort,
+    // Where VRChat receives OSC (our remote)
+    pub osc_remote: HostPort,
+    pub avatar_id: Option<String>,
+    // (address, OSC type tag) sorted by address
+    pub parameters: Vec<(String, String)>,
+}
+
+/*
+ * Browses mDNS for a VRChat client's OSCQuery HTTP service
+ */
+pub fn discover_vrchat_http(timeout: Duration) -> Result<HostPort, VCFeError> {
+    let mdns = ServiceDaemon::new()
+        .map_err(|e| VCFeError::OSCQueryRequestFailure(format!("mDNS: {}", e)))?;
+    let receiver = mdns
+        .browse(OSCQUERY_SERVICE_TYPE)
+        .map_err(|e| VCFeError::OSCQueryRequestFailure(format!("mDNS browse: {}", e)))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut found = None;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(remaining) {
+            Ok(ServiceEvent::ServiceResolved(info)) => {
+                debug!("Resolved OSCQuery service {}", info.get_fullname());
+                if !info.get_fullname().starts_with(VRCHAT_SERVICE_PREFIX) {
+                    continue;
+                }
+                if let Some(ip) = info.get_addresses().iter().next() {
+                    found = Some(HostPort::from(SocketAddr::new(
+                        IpAddr::from(*ip),
+                        info.get_port(),
+                    )));
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    let _ = mdns.stop_browse(OSCQUERY_SERVICE_TYPE);
+    let _ = mdns.shutdown();
+    found.ok_or(VCFeError::VRChatNotFound)
+}
+
+/*
+ * VRChat advertises the address it binds, usually 127.0.0.1 or 0.0.0.0
+ * Unspecified is never a valid destination and loopback only is when the service runs on this machine
+ * In those cases the address the service was reached at is used instead
+ */
+fn osc_remote(host_info: &HostInfo, http: &HostPort) -> Result<HostPort, VCFeError> {
+    let host = match IpAddr::from_str(&host_info.osc_ip) {
+        Ok(ip) if ip.is_unspecified() => http.host(),
+        Ok(ip) if ip.is_loopback() => {
+            let service_is_local = http.resolve().is_ok_and(|addr| is_local_ip(addr.ip()));
+            if service_is_local {
+                host_info.osc_ip.as_str()
+            } else {
+                http.host()
+            }
+        }
+        _ => host_info.osc_ip.as_str(),
+    };
+
+    let osc_remote = HostPort::new(host, host_info.osc_port).map_err(|_| {
+        VCFeError::OSCQueryRequestFailure(format!("Invalid OSC_IP {}", host_info.osc_ip))
+    })?;
+    if host != host_info.osc_ip {
+        debug!(
+            "OSCQuery advertised OSC_IP {}, using {} instead",
+            host_info.osc_ip, host
+        );
+    }
+    Ok(osc_remote)
+}
+
+fn get_json<T: DeserializeOwned>(
+    client: &reqwest::blocking::Client,
+    url: &str,
+) -> Result<T, VCFeError> {
+    let body = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|e| VCFeError::OSCQueryRequestFailure(format!("{}: {}", url, e)))?;
+    serde_json::from_str(&body)
+        .map_err(|e| VCFeError::OSCQueryRequestFailure(format!("{}: {}", url, e)))
+}
+
+/*
+ * Reads the host info and avatar tree of an OSCQuery HTTP service
+ */
+pub fn query_vrchat(http: HostPort) -> Result<VRChatOSCQuery, VCFeError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| VCFeError::OSCQueryRequestFailure(e.to_string()))?;
+
+    let host_info: HostInfo = get_json(&client, &format!("http://{}/?HOST_INFO", http))?;
+    let osc_remote = osc_remote(&host_info, &http)?;
+
+    let avatar: RemoteNode = get_json(&client, &format!("http://{}/avatar", http))?;
+    let avatar_id = avatar
+        .contents
+        .get("change")
+        .and_then(|node| node.value.as_ref()?.first()?.as_str().map(str::to_string));
+
+    let mut parameters = Vec::new();
+    if let Some(node) = avatar.contents.get("parameters") {
+        node.collect_parameters(&mut parameters);
+    }
+    parameters.sort();
+
+    info!(
+        "OSCQuery {} sends OSC to {} with {} avatar parameters",
+        http,
+        osc_remote,
+        parameters.len()
+    );
+
+    Ok(VRChatOSCQuery {
+        name: host_info.name,
+        http,
+        osc_remote,
+        avatar_id,
+        parameters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    const AVATAR: &str = r#"{
+        "FULL_PATH": "/avatar",
+        "CONTENTS": {
+            "change": {"FULL_PATH": "/avatar/change", "TYPE": "s", "VALUE": ["avtr_test"]},
+            "parameters": {
+                "FULL_PATH": "/avatar/parameters",
+                "CONTENTS": {
+                    "VoiceLevel": {"FULL_PATH": "/avatar/parameters/VoiceLevel", "TYPE": "f"},
+                    "Toys": {
+                        "FULL_PATH": "/avatar/parameters/Toys",
+                        "CONTENTS": {
+                            "Vibe": {"FULL_PATH": "/avatar/parameters/Toys/Vibe", "TYPE": "f"}
+                        }
+                    },
+                    "AFK": {"FULL_PATH": "/avatar/parameters/AFK", "TYPE": "T"}
+                }
+            }
+        }
+    }"#;
+
+    fn host_info(osc_ip: &str) -> String {
+        format!(
+            r#"{{"NAME": "VRChat-Client-Test", "OSC_IP": "{}", "OSC_PORT": 9000}}"#,
+            osc_ip
+        )
+    }
+
+    /*
+     * Minimal OSCQuery HTTP service answering with fixed bodies
+     * Unknown paths get a 404
+     */
+    fn mock_oscquery(routes: Vec<(&'static str, String)>) -> HostPort {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+                let response = match routes.iter().find(|(route, _)| *route == path) {
+                    Some((_, body)) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        HostPort::from(addr)
+    }
+
+    // VCFeError isn't Debug so results can't be unwrapped
+    fn query_mock(http: HostPort) -> VRChatOSCQuery {
+        query_vrchat(http).unwrap_or_else(|_| panic!("OSCQuery request failed"))
+    }
+
+    #[test]
+    fn reads_host_info_and_avatar() {
+        let http = mock_oscquery(vec![
+            ("/?HOST_INFO", host_info("127.0.0.1")),
+            ("/avatar", AVATAR.to_string()),
+        ]);
+
+        let query = query_mock(http);
+        assert_eq!(query.name.as_deref(), Some("VRChat-Client-Test"));
+        assert_eq!(query.osc_remote.to_string(), "127.0.0.1:9000");
+        assert_eq!(query.avatar_id.as_deref(), Some("avtr_test"));
+        assert_eq!(
+            query.parameters,
+            vec![
+                ("/avatar/parameters/AFK".to_string(), "T".to_string()),
+                ("/avatar/parameters/Toys/Vibe".to_string(), "f".to_string()),
+                ("/avatar/parameters/VoiceLevel".to_string(), "f".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unspecified_osc_ip_uses_the_service_address() {
+        let http = mock_oscquery(vec![
+            ("/?HOST_INFO", host_info("0.0.0.0")),
+            ("/avatar", AVATAR.to_string()),
+        ]);
+
+        let query = query_mock(http);
+        assert_eq!(query.osc_remote.to_string(), "127.0.0.1:9000");
+    }
+
+    #[test]
+    fn avatar_without_parameters() {
+        let http = mock_oscquery(vec![
+            ("/?HOST_INFO", host_info("127.0.0.1")),
+            ("/avatar", r#"{"FULL_PATH": "/avatar"}"#.to_string()),
+        ]);
+
+        let query = query_mock(http);
+        assert!(query.avatar_id.is_none());
+        assert!(query.parameters.is_empty());
+    }
+
+    #[test]
+    fn missing_endpoint_fails() {
+        let http = mock_oscquery(vec![("/?HOST_INFO", host_info("127.0.0.1"))]);
+        assert!(matches!(
+            query_vrchat(http),
+            Err(VCFeError::OSCQueryRequestFailure(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_host_info_fails() {
+        let http = mock_oscquery(vec![
+            (
+                "/?HOST_INFO",
+                r#"{"NAME": "VRChat-Client-Test"}"#.to_string(),
+            ),
+            ("/avatar", AVATAR.to_string()),
+        ]);
+        assert!(matches!(
+            query_vrchat(http),
+            Err(VCFeError::OSCQueryRequestFailure(_))
+        ));
+    }
+
+    #[test]
+    fn loopback_osc_ip_of_remote_service_uses_the_service_address() {
+        // TEST-NET-1, never an address of this machine
+        let http = HostPort::new("192.0.2.10", 9001).unwrap();
+        let info = HostInfo {
+            name: None,
+            osc_ip: "127.0.0.1".to_string(),
+            osc_port: 9000,
+        };
+        assert_eq!(
+            osc_remote(&info, &http)
+                .unwrap_or_else(|_| panic!("invalid OSC_IP"))
+                .to_string(),
+            "192.0.2.10:9000"
+        );
+    }
+
+    #[test]
+    fn lan_osc_ip_is_kept() {
+        let http = HostPort::new("192.0.2.10", 9001).unwrap();
+        let info = HostInfo {
+            name: None,
+            osc_ip: "192.0.2.20".to_string(),
+            osc_port: 9000,
+        };
+        assert_eq!(
+            osc_remote(&info, &http)
+                .unwrap_or_else(|_| panic!("invalid OSC_IP"))
+                .to_string(),
+            "192.0.2.20:9000"
+        );
+    }
+}
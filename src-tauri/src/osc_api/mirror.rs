@@ -0,0 +1,176 @@
+/*
+ * API state mirroring
+ * Writes the state the VibeCheck API controls back to its avatar parameters so avatar menus
+ * reflect changes made from the desktop UI.
+ * Only changed values are sent, everything is resent once an avatar change has loaded.
+ */
+use futures_timer::Delay;
+use log::{debug, info};
+use parking_lot::Mutex;
+use rosc::{OscMessage, OscType};
+use std::{collections::BTreeMap, time::Duration};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    config::toy::VCToyAnatomy,
+    osc::feedback::send_to_remote,
+    toy_handling::toyops::VCToy,
+    vcore::core::{RunningState, VCStateMutex, VibeCheckState},
+};
+
+use super::API_PREFIX;
+
+// VRChat resets parameters to the avatar's defaults while the new avatar loads
+const AVATAR_LOAD_DELAY: Duration = Duration::from_secs(3);
+
+lazy_static::lazy_static! {
+    // API address -> value last mirrored to the avatar
+    static ref MIRRORED: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+}
+
+fn state_addr() -> String {
+    format!("{}/state", API_PREFIX)
+}
+
+fn anatomy_prefix() -> String {
+    format!("{}/anatomy/", API_PREFIX)
+}
+
+/*
+ * Whether value is what was last mirrored to addr
+ * VRChat sends mirrored values back like any other parameter change. An anatomy is mirrored as
+ * enabled while any of its features is, but setting it enables every feature, so the echo must
+ * not be applied.
+ */
+pub fn is_mirror_echo(addr: &str, value: bool) -> bool {
+    MIRRORED.lock().get(addr) == Some(&value)
+}
+
+/*
+ * Enabled flag of every anatomy that has online toys
+ * An anatomy is enabled while any feature of its toys is enabled
+ */
+fn anatomy_values<'a>(toys: impl Iterator<Item = &'a VCToy>) -> BTreeMap<String, bool> {
+    let mut values = BTreeMap::new();
+    for toy in toys {
+        let Some(config) = toy.config.as_ref() else {
+            continue;
+        };
+        if let VCToyAnatomy::NA = config.anatomy {
+            continue;
+        }
+        let enabled = toy
+            .parsed_toy_features
+            .features
+            .iter()
+            .any(|f| f.feature_enabled);
+        *values
+            .entry(format!("{}{:?}/enabled", anatomy_prefix(), config.anatomy))
+            .or_insert(false) |= enabled;
+    }
+    values
+}
+
+/*
+ * Records the values and returns the messages for the ones that changed, or all of them on resend
+ * Anatomies that no longer have toys are mirrored as disabled
+ */
+fn mirror_messages(
+    state: Option<bool>,
+    anatomies: BTreeMap<String, bool>,
+    resend: bool,
+) -> Vec<OscMessage> {
+    let mut mirrored = MIRRORED.lock();
+    let anatomy_prefix = anatomy_prefix();
+
+    let gone: Vec<(String, bool)> = mirrored
+        .keys()
+        .filter(|addr| addr.starts_with(&anatomy_prefix) && !anatomies.contains_key(*addr))
+        .map(|addr| (addr.clone(), false))
+        .collect();
+
+    state
+        .map(|running| (state_addr(), running))
+        .into_iter()
+        .chain(anatomies)
+        .chain(gone)
+        .filter(|(addr, value)| mirrored.insert(addr.clone(), *value) != Some(*value) || resend)
+        .map(|(addr, value)| OscMessage {
+            addr,
+            args: vec![OscType::Bool(value)],
+        })
+        .collect()
+}
+
+fn vibecheck_state_messages(vc: &VibeCheckState, resend: bool) -> Vec<OscMessage> {
+    let anatomies = match vc.core_toy_manager.as_ref() {
+        Some(tm) => anatomy_values(tm.online_toys.values()),
+        None => BTreeMap::new(),
+    };
+    mirror_messages(
+        Some(matches!(vc.running, RunningState::Running)),
+        anatomies,
+        resend,
+    )
+}
+
+/*
+ * Mirrors the enabled state and anatomy flags after they were changed
+ */
+pub fn mirror_api_state(vc: &VibeCheckState) {
+    let msgs = vibecheck_state_messages(vc, false);
+    if msgs.is_empty() {
+        return;
+    }
+    debug!("Mirroring {} API parameters", msgs.len());
+    send_to_remote(msgs);
+}
+
+/*
+ * Mirrors the anatomy flags of the toy management handler's toys after a toy update
+ */
+pub fn mirror_anatomy_state<'a>(toys: impl Iterator<Item = &'a VCToy>) {
+    let msgs = mirror_messages(None, anatomy_values(toys), false);
+    if msgs.is_empty() {
+        return;
+    }
+    debug!("Mirroring {} anatomy parameters", msgs.len());
+    send_to_remote(msgs);
+}
+
+/*
+ * Resends every mirrored value once the new avatar had time to load
+ */
+pub fn remirror_after_avatar_change(app_handle: &AppHandle) {
+    let vc_pointer = app_handle.state::<VCStateMutex>().0.clone();
+    tauri::async_runtime::spawn(async move {
+        Delay::new(AVATAR_LOAD_DELAY).await;
+        let msgs = {
+            let vc_lock = vc_pointer.lock();
+            vibecheck_state_messages(&vc_lock, true)
+        };
+        info!("Resending {} API parameters to the new avatar", msgs.len());
+        send_to_remote(msgs);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_last_mirrored_value_is_an_echo() {
+        let addr = format!("{}Anus/enabled", anatomy_prefix());
+        assert!(!is_mirror_echo(&addr, true));
+
+        let msgs = mirror_messages(None, BTreeMap::from([(addr.clone(), true)]), false);
+        assert_eq!(msgs.len(), 1);
+        assert!(is_mirror_echo(&addr, true));
+        assert!(!is_mirror_echo(&addr, false));
+
+        // Toys of the anatomy went away
+        mirror_messages(None, BTreeMap::new(), false);
+        assert!(is_mirror_echo(&addr, false));
+        assert!(!is_mirror_echo(&addr, true));
+    }
+}
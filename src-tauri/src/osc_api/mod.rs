@@ -1,76 +1,532 @@
-use log::{debug, info, trace};
-use rosc::OscMessage;
+use log::{debug, info, trace, warn};
 use tauri::{AppHandle, Manager};
 
-use crate::{config::toy::VCToyAnatomy, frontend::frontend_types::FeCoreEvent, vcore};
+use crate::{
+    config::toy::VCToyAnatomy,
+    frontend::frontend_types::{FeCoreEvent, FeScanEvent, FeStateEvent},
+    osc::{ingress::VCOscMessage, telemetry::toy_slug},
+    toy_handling::{
+        toyops::{ProcessingMode, ProcessingModeValues, VCFeatureType, VCToy, VCToyFeature},
+        ModeProcessorInputType,
+    },
+    vcore::{
+        self,
+        core::{RunningState, VCStateMutex},
+    },
+};
 
+pub mod mirror;
 pub mod osc_api;
 
+pub const API_PREFIX: &str = "/avatar/parameters/vibecheck/api";
+
+const FEATURE_TYPES: [VCFeatureType; 8] = [
+    VCFeatureType::Vibrator,
+    VCFeatureType::Rotator,
+    VCFeatureType::Linear,
+    VCFeatureType::Oscillate,
+    VCFeatureType::Constrict,
+    VCFeatureType::Inflate,
+    VCFeatureType::Position,
+    VCFeatureType::ScalarRotator,
+];
+
+/*
+ * Lowercase name without spaces, underscores or punctuation
+ * eg. "Lovense Connect Hush", "lovense_connect_hush" and "LovenseConnectHush" are all "lovenseconnecthush"
+ */
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/*
+ * API address of a toy as advertised over OSCQuery
+ * eg. /avatar/parameters/vibecheck/api/toy/lovense_hush/0
+ */
+pub fn toy_api_addr(toy_name: &str, sub_id: u8) -> String {
+    format!("{}/toy/{}/{}", API_PREFIX, toy_slug(toy_name), sub_id)
+}
+
+/*
+ * API address of a toy feature as advertised over OSCQuery
+ * eg. /avatar/parameters/vibecheck/api/toy/lovense_hush/0/feature/vibrator/1
+ */
+pub fn feature_api_addr(
+    toy_name: &str,
+    sub_id: u8,
+    feature_type: VCFeatureType,
+    feature_index: u32,
+) -> String {
+    format!(
+        "{}/feature/{}/{}",
+        toy_api_addr(toy_name, sub_id),
+        normalize_name(&format!("{:?}", feature_type)),
+        feature_index
+    )
+}
+
+/*
+ * Processing modes in the order of ProcessingMode
+ */
+fn processing_mode_from_int(mode: i32) -> Option<ProcessingMode> {
+    match mode {
+        0 => Some(ProcessingMode::Raw),
+        1 => Some(ProcessingMode::Smooth),
+        2 => Some(ProcessingMode::Rate),
+        3 => Some(ProcessingMode::Constant),
+        _ => None,
+    }
+}
+
+/*
+ * Toys are selected by alias, name or osc_data name, optionally narrowed to a sub id
+ */
+struct ToySelector {
+    toy: String,
+    sub_id: Option<u8>,
+}
+
+impl ToySelector {
+    fn matches(&self, toy: &VCToy, alias: Option<&String>) -> bool {
+        if self.sub_id.is_some_and(|sub_id| sub_id != toy.sub_id) {
+            return false;
+        }
+        normalize_name(&toy.toy_name) == self.toy
+            || normalize_name(&toy_slug(&toy.toy_name)) == self.toy
+            || alias.is_some_and(|alias| normalize_name(alias) == self.toy)
+    }
+}
+
+/*
+ * Features of a toy, all of them when no type is given
+ * Rotator also selects scalar rotators since they are shown as rotators
+ */
+struct FeatureSelector {
+    feature_type: Option<String>,
+    feature_index: Option<u32>,
+}
+
+impl FeatureSelector {
+    fn matches(&self, feature: &VCToyFeature) -> bool {
+        if self
+            .feature_index
+            .is_some_and(|index| index != feature.feature_index)
+        {
+            return false;
+        }
+        match &self.feature_type {
+            Some(feature_type) => {
+                *feature_type == normalize_name(&format!("{:?}", feature.feature_type))
+                    || (feature_type == "rotator"
+                        && feature.feature_type == VCFeatureType::ScalarRotator)
+            }
+            None => true,
+        }
+    }
+}
+
+enum ToyChange {
+    Enabled(bool),
+    MaxLevel(f64),
+    ParameterMode(usize, ProcessingMode),
+}
+
+/*
+ * toy/<toy>[/<sub_id>][/feature/<type>[/<index>]]/<setting>
+ */
+struct ToyEndpoint {
+    toy: ToySelector,
+    features: FeatureSelector,
+    change: ToyChange,
+}
+
+impl ToyEndpoint {
+    fn parse(tokens: &[String], value: &ModeProcessorInputType) -> Option<Self> {
+        let (toy, mut rest) = tokens.split_first()?;
+        let sub_id = rest.first().and_then(|t| t.parse::<u8>().ok());
+        if sub_id.is_some() {
+            rest = &rest[1..];
+        }
+
+        let mut features = FeatureSelector {
+            feature_type: None,
+            feature_index: None,
+        };
+        if let [keyword, feature_type, tail @ ..] = rest {
+            if keyword == "feature" {
+                let feature_type = normalize_name(feature_type);
+                if !FEATURE_TYPES
+                    .iter()
+                    .any(|t| normalize_name(&format!("{:?}", t)) == feature_type)
+                {
+                    return None;
+                }
+                features.feature_type = Some(feature_type);
+                rest = tail;
+                features.feature_index = rest.first().and_then(|t| t.parse::<u32>().ok());
+                if features.feature_index.is_some() {
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        let change = match rest {
+            [setting] if setting == "enabled" => ToyChange::Enabled(value.try_bool()?),
+            [setting] if setting == "max_level" => {
+                let level = value.try_float()?;
+                if !(0.0..=1.0).contains(&level) {
+                    return None;
+                }
+                ToyChange::MaxLevel(level)
+            }
+            [keyword, index, setting] if keyword == "parameter" && setting == "mode" => {
+                ToyChange::ParameterMode(
+                    index.parse().ok()?,
+                    processing_mode_from_int(value.try_int()?)?,
+                )
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            toy: ToySelector {
+                toy: normalize_name(toy),
+                sub_id,
+            },
+            features,
+            change,
+        })
+    }
+
+    /*
+     * Applies the change to the selected features of a toy
+     * Returns true when a feature was changed
+     */
+    fn apply(&self, toy: &mut VCToy) -> bool {
+        let mut altered = false;
+        for feature in toy
+            .parsed_toy_features
+            .features
+            .iter_mut()
+            .filter(|f| self.features.matches(f))
+        {
+            match &self.change {
+                ToyChange::Enabled(enabled) => feature.feature_enabled = *enabled,
+                ToyChange::MaxLevel(level) => {
+                    feature.feature_levels.maximum_level =
+                        level.max(feature.feature_levels.minimum_level)
+                }
+                ToyChange::ParameterMode(index, mode) => {
+                    let Some(param) = feature.osc_parameters.get_mut(*index) else {
+                        continue;
+                    };
+                    param.processing_mode_values = ProcessingModeValues::new_from(mode);
+                    param.processing_mode = mode.clone();
+                }
+            }
+            altered = true;
+        }
+        altered
+    }
+}
+
+/*
+ * Lowercased tokens of an API address after the API prefix
+ */
+fn api_tokens(addr: &str) -> Option<Vec<String>> {
+    let path = addr.strip_prefix(API_PREFIX)?;
+    Some(
+        path.split('/')
+            .filter(|token| !token.is_empty())
+            .map(|token| token.to_lowercase())
+            .collect(),
+    )
+}
+
 struct APIProcessor;
 
 impl APIProcessor {
-    pub fn parse(mut endpoint: OscMessage, app_handle: &AppHandle) {
-        let mut api_tokenize = endpoint
-            .addr
-            .split('/')
-            .map(|t| t.to_string())
-            .collect::<Vec<String>>();
-        api_tokenize.retain(|token| !token.is_empty());
+    pub fn parse(endpoint: VCOscMessage, app_handle: &AppHandle) {
+        let Some(api_tokenize) = api_tokens(&endpoint.addr) else {
+            return;
+        };
         debug!("[*] API tokenization: {:?}", api_tokenize);
-        if api_tokenize.len() == 5
-            && api_tokenize[2] == "vibecheck"
-            && api_tokenize[3] == "api"
-            && api_tokenize[4] == "state"
-        {
-            // /avatar/parameters/vibecheck/api/state
-            let Some(false) = endpoint.args.pop().unwrap().bool() else {
+
+        let tokens: Vec<&str> = api_tokenize.iter().map(String::as_str).collect();
+        match tokens.as_slice() {
+            ["state"] => {
+                // /avatar/parameters/vibecheck/api/state
+                let Some(enable) = endpoint.value.try_bool() else {
+                    return;
+                };
+                let running = matches!(
+                    app_handle.state::<VCStateMutex>().0.lock().running,
+                    RunningState::Running
+                );
+                let event = match (enable, running) {
+                    (true, false) => {
+                        info!("State true: Sending EnableAndScan event");
+                        FeStateEvent::EnableAndScan
+                    }
+                    (false, true) => {
+                        info!("State false: Sending Disable event");
+                        FeStateEvent::Disable
+                    }
+                    _ => return,
+                };
+                let _ = app_handle.emit_all("fe_core_event", FeCoreEvent::State(event));
+            }
+            ["anatomy", anatomy, "enabled"] => {
+                // /avatar/parameters/vibecheck/api/anatomy/Anal/enabled
+                trace!("[*] Checking anatomy token: {}", anatomy);
+                let anatomy = VCToyAnatomy::get_anatomy(&anatomy.to_string());
+                let Some(state_bool) = endpoint.value.try_bool() else {
+                    return;
+                };
+                if mirror::is_mirror_echo(&endpoint.addr, state_bool) {
+                    trace!("[*] Ignoring mirrored anatomy value: {}", endpoint.addr);
+                    return;
+                }
+                Self::alter_toys(app_handle, |toy, _| {
+                    let altered = toy.mutate_state_by_anatomy(&anatomy, state_bool);
+                    if altered {
+                        trace!(
+                            "[*] Mutating feature state from anatomy for toy: {}",
+                            toy.toy_name
+                        );
+                    }
+                    altered
+                });
+            }
+            ["scan"] => {
+                // /avatar/parameters/vibecheck/api/scan
+                if let Some(start) = endpoint.value.try_bool() {
+                    Self::scan(app_handle, start);
+                }
+            }
+            ["stop_all"] => {
+                // /avatar/parameters/vibecheck/api/stop_all
+                if let Some(true) = endpoint.value.try_bool() {
+                    Self::stop_all(app_handle);
+                }
+            }
+            ["toy", ..] => {
+                // /avatar/parameters/vibecheck/api/toy/lovense_hush/0/feature/vibrator/0/max_level
+                let Some(toy_endpoint) = ToyEndpoint::parse(&api_tokenize[1..], &endpoint.value)
+                else {
+                    debug!("Invalid toy API endpoint or value: {:?}", endpoint);
+                    return;
+                };
+                Self::alter_toys(app_handle, |toy, alias| {
+                    toy_endpoint.toy.matches(toy, alias) && toy_endpoint.apply(toy)
+                });
+            }
+            _ => debug!("Unknown API endpoint: {}", endpoint.addr),
+        }
+    }
+
+    /*
+     * Runs alter on the online toys with their alias and saves and broadcasts the toys it altered
+     */
+    fn alter_toys(
+        app_handle: &AppHandle,
+        mut alter: impl FnMut(&mut VCToy, Option<&String>) -> bool,
+    ) {
+        let altered_toys = {
+            let vc_pointer = app_handle.state::<VCStateMutex>().0.clone();
+            let mut vc_lock = vc_pointer.lock();
+            let aliases = vc_lock.config.telemetry.aliases.clone();
+            let Some(toy_manager) = vc_lock.core_toy_manager.as_mut() else {
                 return;
             };
-            info!("State false: Sending Disable event");
-            let _ = app_handle.emit_all(
-                "fe_core_event",
-                FeCoreEvent::State(crate::frontend::frontend_types::FeStateEvent::Disable),
-            );
-        } else if api_tokenize.len() == 7
-            && api_tokenize[4] == "anatomy"
-            && api_tokenize[6] == "enabled"
-        {
-            // /avatar/parameters/vibecheck/api/anatomy/Anal/enabled
-            trace!("[*] Checking anatomy token: {}", api_tokenize[5]);
-            let anatomy = VCToyAnatomy::get_anatomy(&api_tokenize[5]);
-            let mut altered_toys = Vec::new();
-
-            if let Some(state_bool) = endpoint.args.pop().unwrap().bool() {
-                let vc_pointer = app_handle
-                    .state::<crate::vcore::core::VCStateMutex>()
-                    .0
-                    .clone();
-                let mut vc_lock = vc_pointer.lock();
-
-                vc_lock
-                    .core_toy_manager
-                    .as_mut()
-                    .unwrap()
-                    .online_toys
-                    .iter_mut()
-                    .for_each(|toy| {
-                        if toy.1.mutate_state_by_anatomy(&anatomy, state_bool) {
-                            trace!(
-                                "[*] Mutating feature state from anatomy for toy: {}",
-                                toy.1.toy_name
-                            );
-                            altered_toys.push(toy.1.clone());
-                        }
-                    });
+
+            toy_manager
+                .online_toys
+                .values_mut()
+                .filter_map(|toy| {
+                    let alias = aliases.get(&toy.toy_name);
+                    alter(toy, alias).then(|| toy.clone())
+                })
+                .collect::<Vec<VCToy>>()
+        };
+
+        if altered_toys.is_empty() {
+            debug!("API endpoint did not alter any toys");
+        }
+        for toy in altered_toys {
+            info!("API altered toy: {}", toy.toy_name);
+            if let Err(e) = vcore::core::native_alter_toy(
+                app_handle.state::<VCStateMutex>(),
+                app_handle.clone(),
+                toy,
+            ) {
+                warn!("Failed to alter toy from API: {:?}", e);
             }
+        }
+    }
 
-            altered_toys.iter().for_each(|toy| {
-                let _ = vcore::core::native_alter_toy(
-                    app_handle.state::<crate::vcore::core::VCStateMutex>(),
-                    app_handle.clone(),
-                    toy.clone(),
-                );
-            });
+    /*
+     * Starts or stops scanning and tells the frontend so its scan state follows
+     */
+    fn scan(app_handle: &AppHandle, start: bool) {
+        let vc_pointer = app_handle.state::<VCStateMutex>().0.clone();
+        let vc_lock = vc_pointer.lock();
+        let Some(bp_client) = vc_lock.bp_client.as_ref() else {
+            return;
+        };
+
+        if start {
+            info!("API: Starting scan");
+            vc_lock.async_rt.spawn(bp_client.start_scanning());
+            let _ = app_handle.emit_all("fe_core_event", FeCoreEvent::Scan(FeScanEvent::Start));
+        } else {
+            info!("API: Stopping scan");
+            vc_lock.async_rt.spawn(bp_client.stop_scanning());
+            let _ = app_handle.emit_all("fe_core_event", FeCoreEvent::Scan(FeScanEvent::Stop));
+        }
+    }
+
+    fn stop_all(app_handle: &AppHandle) {
+        let vc_pointer = app_handle.state::<VCStateMutex>().0.clone();
+        let vc_lock = vc_pointer.lock();
+        if let Some(bp_client) = vc_lock.bp_client.as_ref() {
+            info!("API: Stopping all toys");
+            vc_lock.async_rt.spawn(bp_client.stop_all_devices());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osc::ingress::{
+        decode_packet,
+        fuzz::{random_datagrams, XorShift},
+        ingress_packet,
+    };
+
+    #[test]
+    fn parses_toy_endpoints() {
+        let tokens = api_tokens(&format!(
+            "{}/toy/Lovense_Hush/1/feature/Vibrator/0/MAX_LEVEL",
+            API_PREFIX
+        ))
+        .unwrap();
+        let endpoint =
+            ToyEndpoint::parse(&tokens[1..], &ModeProcessorInputType::Float(0.5)).unwrap();
+        assert_eq!(endpoint.toy.toy, "lovensehush");
+        assert_eq!(endpoint.toy.sub_id, Some(1));
+        assert_eq!(endpoint.features.feature_type.as_deref(), Some("vibrator"));
+        assert_eq!(endpoint.features.feature_index, Some(0));
+        assert!(matches!(endpoint.change, ToyChange::MaxLevel(l) if l == 0.5));
+    }
+
+    #[test]
+    fn parses_advertised_toy_endpoints() {
+        let feature = feature_api_addr("Lovense Connect Hush", 2, VCFeatureType::ScalarRotator, 1);
+        for (addr, value) in [
+            (
+                format!("{}/enabled", toy_api_addr("Lovense Connect Hush", 2)),
+                ModeProcessorInputType::Boolean(false),
+            ),
+            (
+                format!("{}/max_level", feature),
+                ModeProcessorInputType::Float(0.5),
+            ),
+            (
+                format!("{}/parameter/0/mode", feature),
+                ModeProcessorInputType::Int(1),
+            ),
+        ] {
+            let tokens = api_tokens(&addr).unwrap();
+            let endpoint = ToyEndpoint::parse(&tokens[1..], &value).unwrap();
+            assert_eq!(endpoint.toy.toy, "lovensehush", "{}", addr);
+            assert_eq!(endpoint.toy.sub_id, Some(2), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_toy_endpoint_values() {
+        let parse = |path: &str, value: ModeProcessorInputType| {
+            let tokens = api_tokens(&format!("{}/{}", API_PREFIX, path)).unwrap();
+            ToyEndpoint::parse(&tokens[1..], &value).is_some()
+        };
+        assert!(!parse(
+            "toy/hush/max_level",
+            ModeProcessorInputType::Float(1.5)
+        ));
+        assert!(!parse(
+            "toy/hush/enabled",
+            ModeProcessorInputType::Float(1.0)
+        ));
+        assert!(!parse(
+            "toy/hush/feature/tentacle/enabled",
+            ModeProcessorInputType::Boolean(true)
+        ));
+        assert!(!parse(
+            "toy/hush/parameter/0/mode",
+            ModeProcessorInputType::Int(7)
+        ));
+        assert!(parse(
+            "toy/hush/parameter/0/mode",
+            ModeProcessorInputType::Int(2)
+        ));
+    }
+
+    /*
+     * Everything ingress accepts must make it through API parsing without panicking
+     */
+    #[test]
+    fn fuzz_ingress_to_api_parsing() {
+        let mut rng = XorShift(0x5eed);
+        let tokens = [
+            "toy",
+            "hush",
+            "0",
+            "feature",
+            "vibrator",
+            "1",
+            "enabled",
+            "max_level",
+            "parameter",
+            "mode",
+            "",
+            "x",
+        ];
+
+        for seed in 1..=64 {
+            for raw in random_datagrams(seed, 256) {
+                let Some(pkt) = decode_packet(&raw) else {
+                    continue;
+                };
+                for timed in ingress_packet(pkt) {
+                    let Some(api_tokenize) = api_tokens(&timed.msg.addr) else {
+                        continue;
+                    };
+                    if let Some(("toy", rest)) = api_tokenize
+                        .split_first()
+                        .map(|(first, rest)| (first.as_str(), rest))
+                    {
+                        let _ = ToyEndpoint::parse(rest, &timed.msg.value);
+                    }
+                }
+            }
+        }
+
+        // Random toy endpoint paths with every value type
+        for _ in 0..4096 {
+            let path: Vec<String> = (0..rng.below(8))
+                .map(|_| rng.pick(&tokens).to_string())
+                .collect();
+            let value = match rng.below(3) {
+                0 => ModeProcessorInputType::Float(rng.below(300) as f64 / 100.0 - 1.0),
+                1 => ModeProcessorInputType::Boolean(rng.below(2) == 0),
+                _ => ModeProcessorInputType::Int(rng.below(10) as i32 - 2),
+            };
+            let _ = ToyEndpoint::parse(&path, &value);
         }
     }
 }
@@ -1,57 +1,285 @@
-use crate::{osc::logic::recv_osc_cmd, toy_handling::ToySig};
-use log::{info, trace};
-use std::net::UdpSocket;
+use crate::{
+    osc::{
+        ingress::{
+            ingress_packet, reject_source, SourceRejection, VCOscMessage, AVATAR_CHANGE_ADDR,
+        },
+        learn::learn_osc_messages,
+        logic::recv_osc_cmd,
+        param_browser::observe_osc_messages,
+        recording::record_osc_messages,
+        repeater::OSCRepeater,
+    },
+    toy_handling::ToySig,
+    vcore::config::OSCListener,
+};
+use log::{error as logerr, info, trace, warn};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use rosc::OscPacket;
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
 use tauri::{AppHandle, Manager};
 use tokio::sync::broadcast::Sender as BSender;
 
-use super::APIProcessor;
+use super::{mirror::remirror_after_avatar_change, APIProcessor, API_PREFIX};
 
+/*
+ * Receives and handles a single datagram
+ */
 pub fn vibecheck_osc_api(
     bind_sock: &UdpSocket,
+    listener: &OSCListener,
+    repeater: &OSCRepeater,
     app_handle: &AppHandle,
-    toy_bcst_tx: &BSender<ToySig>,
-) -> bool {
-    match recv_osc_cmd(bind_sock) {
-        Some(msg) => {
-            // Stop toys on avatar change
-            if msg.addr.starts_with("/avatar/change") {
-                info!("Avatar Changed: Halting toy actions");
+) {
+    if let Some((pkt, source)) = recv_osc_cmd(bind_sock, listener, repeater) {
+        handle_osc_packet(pkt, source, listener, app_handle);
+    }
+}
+
+/*
+ * Validates, rewrites and dispatches the messages of a received packet
+ */
+pub fn handle_osc_packet(
+    pkt: OscPacket,
+    source: IpAddr,
+    listener: &OSCListener,
+    app_handle: &AppHandle,
+) {
+    let mut msgs = ingress_packet(pkt);
+    if let Some(rewrite) = listener.rewrite.as_ref() {
+        for timed in &mut msgs {
+            timed.msg.rewrite(rewrite);
+        }
+    }
+    msgs.retain_mut(|timed| authorize_api_message(&mut timed.msg, source, listener));
+
+    record_osc_messages(msgs.iter().map(|timed| &timed.msg));
+    observe_osc_messages(msgs.iter().map(|timed| &timed.msg), app_handle);
+    learn_osc_messages(msgs.iter().map(|timed| &timed.msg));
+
+    let mut scheduled = Vec::new();
+    let mut immediate = Vec::new();
+    for timed in msgs {
+        match timed.delay() {
+            Some(delay) => scheduled.push((delay, timed.msg)),
+            None => immediate.push(timed.msg),
+        }
+    }
+
+    if !scheduled.is_empty() {
+        schedule_osc_messages(scheduled, app_handle);
+    }
+
+    for msg in immediate {
+        dispatch_osc_message(msg, app_handle);
+    }
+}
+
+/*
+ * Checks and strips the API secret of API messages on listeners that require it
+ * API addresses then have to be /avatar/parameters/vibecheck/api/<secret>/...
+ */
+fn authorize_api_message(msg: &mut VCOscMessage, source: IpAddr, listener: &OSCListener) -> bool {
+    let Some(secret) = listener.required_api_secret() else {
+        return true;
+    };
+    let Some(path) = msg.addr.strip_prefix(API_PREFIX) else {
+        return true;
+    };
+
+    let authorized = path
+        .strip_prefix('/')
+        .and_then(|p| p.strip_prefix(secret))
+        .filter(|rest| rest.starts_with('/'))
+        .map(|rest| format!("{}{}", API_PREFIX, rest));
+    match authorized {
+        Some(addr) => {
+            msg.addr = addr;
+            true
+        }
+        None => {
+            reject_source(&listener.name, source, SourceRejection::ApiSecret);
+            false
+        }
+    }
+}
+
+// Messages waiting for their bundle timetag, more are delivered immediately
+const MAX_SCHEDULED_MESSAGES: usize = 4096;
+
+struct ScheduledOscMessages {
+    // (deliver at, arrival order) -> message
+    queue: BTreeMap<(Instant, u64), VCOscMessage>,
+    next_seq: u64,
+    scheduler_started: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref SCHEDULED: Mutex<ScheduledOscMessages> = Mutex::new(ScheduledOscMessages {
+        queue: BTreeMap::new(),
+        next_seq: 0,
+        scheduler_started: false,
+    });
+    static ref SCHEDULED_WAKE: Condvar = Condvar::new();
+}
+
+/*
+ * Queues messages from bundles with a future timetag for the scheduler thread
+ * The scheduler is started with the first scheduled message
+ */
+fn schedule_osc_messages(scheduled: Vec<(Duration, VCOscMessage)>, app_handle: &AppHandle) {
+    let now = Instant::now();
+    let mut overflow = Vec::new();
+    {
+        let mut sched = SCHEDULED.lock();
+        for (delay, msg) in scheduled {
+            if sched.queue.len() >= MAX_SCHEDULED_MESSAGES {
+                overflow.push(msg);
+                continue;
+            }
+            let seq = sched.next_seq;
+            sched.next_seq += 1;
+            sched.queue.insert((now + delay, seq), msg);
+        }
+        trace!("{} bundled OSC messages scheduled", sched.queue.len());
+
+        if !sched.scheduler_started {
+            sched.scheduler_started = true;
+            let app_handle = app_handle.clone();
+            thread::spawn(move || osc_scheduler_routine(app_handle));
+        }
+    }
+    SCHEDULED_WAKE.notify_one();
+
+    if !overflow.is_empty() {
+        warn!(
+            "OSC schedule is full, delivering {} bundled messages immediately",
+            overflow.len()
+        );
+    }
+    for msg in overflow {
+        dispatch_osc_message(msg, app_handle);
+    }
+}
+
+/*
+ * Delivers scheduled messages in timetag order, sleeping until the earliest is due
+ */
+fn osc_scheduler_routine(app_handle: AppHandle) {
+    let mut sched = SCHEDULED.lock();
+    loop {
+        let Some(&(deliver_at, _)) = sched.queue.keys().next() else {
+            SCHEDULED_WAKE.wait(&mut sched);
+            continue;
+        };
+        if deliver_at > Instant::now() {
+            SCHEDULED_WAKE.wait_until(&mut sched, deliver_at);
+            continue;
+        }
+
+        let Some((_, msg)) = sched.queue.pop_first() else {
+            continue;
+        };
+        MutexGuard::unlocked(&mut sched, || dispatch_osc_message(msg, &app_handle));
+    }
+}
+
+/*
+ * Routes a single OSC message to the VibeCheck API or broadcasts it to the toys
+ * The API is handled in every state, toy messages are dropped while VibeCheck is disabled
+ */
+pub fn dispatch_osc_message(msg: VCOscMessage, app_handle: &AppHandle) {
+    // Stop toys on avatar change
+    if msg.addr == AVATAR_CHANGE_ADDR {
+        info!("Avatar Changed: Halting toy actions");
+        {
+            let vc_pointer = app_handle
+                .state::<crate::vcore::core::VCStateMutex>()
+                .0
+                .clone();
+            let vc_lock = vc_pointer.lock();
+            if let Some(bp_client) = vc_lock.bp_client.as_ref() {
+                if let Err(e) = vc_lock
+                    .async_rt
+                    .block_on(async { bp_client.stop_all_devices().await })
                 {
-                    let vc_pointer = app_handle
-                        .state::<crate::vcore::core::VCStateMutex>()
-                        .0
-                        .clone();
-                    let vc_lock = vc_pointer.lock();
-                    vc_lock
-                        .async_rt
-                        .block_on(async {
-                            vc_lock.bp_client.as_ref().unwrap().stop_all_devices().await
-                        })
-                        .unwrap();
-                }
-                true
-            } else if msg.addr.starts_with("/avatar/parameters/vibecheck/api/") {
-                trace!("[*] VibeCheck API: {:?}", msg);
-                APIProcessor::parse(msg, app_handle);
-                true
-            } else {
-                // Not a vibecheck OSC command, broadcast to toys
-                if toy_bcst_tx.send(ToySig::OSCMsg(msg)).is_err() {
-                    info!("BCST TX is disconnected. Shutting down toy input routine!");
-                    // Shutting down handler_routine
-                    false
-                } else {
-                    true
+                    logerr!("Failed to stop toys on avatar change: {}", e);
                 }
             }
         }
-        None => {
-            if toy_bcst_tx.receiver_count() == 0 {
-                info!("BCST TX is disconnected (RECV C=0). Shutting down toy input routine!");
-                false
-            } else {
-                true
-            }
+        remirror_after_avatar_change(app_handle);
+    } else if is_api_addr(&msg.addr) {
+        trace!("[*] VibeCheck API: {:?}", msg);
+        APIProcessor::parse(msg, app_handle);
+    } else {
+        // Not a vibecheck OSC command, broadcast to toys
+        dispatch_toy_message(msg);
+    }
+}
+
+/*
+ * API messages are API_PREFIX followed by an endpoint path
+ */
+fn is_api_addr(addr: &str) -> bool {
+    addr.strip_prefix(API_PREFIX)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/*
+ * Broadcasts a message to the toy routines
+ * Returns false when VibeCheck is not listening and the message was dropped
+ */
+pub fn dispatch_toy_message(msg: VCOscMessage) -> bool {
+    match OSC_DISPATCH_TX.lock().as_ref() {
+        Some(toy_bcst_tx) => {
+            let _ = toy_bcst_tx.send(ToySig::OSCMsg(msg));
+            true
         }
+        None => false,
+    }
+}
+
+lazy_static::lazy_static! {
+    // Toy broadcast sender while VibeCheck is enabled, None while disabled
+    static ref OSC_DISPATCH_TX: Mutex<Option<BSender<ToySig>>> = Mutex::new(None);
+}
+
+/*
+ * Set by the toy management handler when it starts and stops listening
+ */
+pub fn set_dispatch_tx(toy_bcst_tx: Option<BSender<ToySig>>) {
+    *OSC_DISPATCH_TX.lock() = toy_bcst_tx;
+}
+
+/*
+ * Dispatches a message that did not come from a listener socket (eg. replayed recordings)
+ * API messages are always handled like received ones, so a replayed "state" message can enable VibeCheck
+ * Returns false when a toy message was dropped because VibeCheck is not listening
+ */
+pub fn dispatch_internal_osc_message(msg: VCOscMessage, app_handle: &AppHandle) -> bool {
+    let toy_message = msg.addr != AVATAR_CHANGE_ADDR && !is_api_addr(&msg.addr);
+    if toy_message {
+        return dispatch_toy_message(msg);
+    }
+    dispatch_osc_message(msg, app_handle);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_api_addresses() {
+        assert!(is_api_addr(&format!("{}/state", API_PREFIX)));
+        assert!(is_api_addr(&format!("{}/toy/hush/0/enabled", API_PREFIX)));
+        assert!(!is_api_addr(API_PREFIX));
+        assert!(!is_api_addr("/avatar/parameters/vibecheck/apiary"));
+        assert!(!is_api_addr("/avatar/parameters/Touch"));
+        assert!(!is_api_addr(AVATAR_CHANGE_ADDR));
     }
 }
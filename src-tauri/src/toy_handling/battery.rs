@@ -0,0 +1,321 @@
+/*
+ * Battery monitoring
+ * Battery levels polled by the toy refresh are kept as samples so the frontend can estimate the remaining runtime.
+ * Toys with a threshold raise an alert when they drop below it and can have their intensity capped once critical.
+ */
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use ts_rs::TS;
+
+use crate::frontend::frontend_types::{FeBatteryHistory, FeBatterySample};
+
+use super::toyops::{LevelTweaks, VCFeatureType};
+
+// Samples older than this are dropped
+const SAMPLE_WINDOW_MS: u64 = 2 * 60 * 60 * 1000;
+const MAX_SAMPLES: usize = 720;
+// Samples the remaining time estimate is based on
+const ESTIMATE_WINDOW_MS: u64 = 30 * 60 * 1000;
+// An alert re-arms once the battery is this far above its threshold (charging)
+const ALERT_HYSTERESIS: f64 = 0.05;
+
+/*
+ * Battery alert thresholds keyed by toy name
+ * Alerts are announced in the chatbox through its battery_low template
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default, TS)]
+#[ts(export)]
+pub struct BatteryAlertConfig {
+    pub thresholds: BTreeMap<String, BatteryThreshold>,
+}
+
+/*
+ * Levels are 0-1
+ * critical_max_level caps the intensity of the toy's features while it is below critical
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, TS)]
+#[ts(export)]
+pub struct BatteryThreshold {
+    pub low: f64,
+    pub critical: Option<f64>,
+    pub critical_max_level: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, TS)]
+#[ts(export)]
+pub enum BatteryAlertLevel {
+    Normal,
+    Low,
+    Critical,
+}
+
+impl BatteryThreshold {
+    /*
+     * Alert level for a battery level given the current alert level
+     * Levels only go back up once the battery rose past the threshold by the hysteresis
+     */
+    fn alert_level(&self, level: f64, current: BatteryAlertLevel) -> BatteryAlertLevel {
+        let below = |threshold: f64, active: bool| {
+            if active {
+                level < threshold + ALERT_HYSTERESIS
+            } else {
+                level < threshold
+            }
+        };
+
+        if self
+            .critical
+            .is_some_and(|c| below(c, current == BatteryAlertLevel::Critical))
+        {
+            BatteryAlertLevel::Critical
+        } else if below(self.low, current != BatteryAlertLevel::Normal) {
+            BatteryAlertLevel::Low
+        } else {
+            BatteryAlertLevel::Normal
+        }
+    }
+}
+
+struct ToyBattery {
+    samples: VecDeque<(u64, f64)>,
+    alert: BatteryAlertLevel,
+}
+
+lazy_static::lazy_static! {
+    // (toy name, sub id) -> battery samples and alert level
+    static ref TOY_BATTERIES: Mutex<HashMap<(String, u8), ToyBattery>> = Mutex::new(HashMap::new());
+    // toy id -> max level while critical
+    static ref LEVEL_CAPS: Mutex<HashMap<u32, f64>> = Mutex::new(HashMap::new());
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/*
+ * Records a battery sample and updates the toy's alert level and intensity cap
+ * Returns the new alert level when it changed
+ */
+pub fn record_battery_level(
+    toy_id: u32,
+    toy_name: &str,
+    sub_id: u8,
+    level: f64,
+    threshold: Option<&BatteryThreshold>,
+) -> Option<BatteryAlertLevel> {
+    let now = unix_ms();
+    let mut batteries = TOY_BATTERIES.lock();
+    let battery = batteries
+        .entry((toy_name.to_string(), sub_id))
+        .or_insert_with(|| ToyBattery {
+            samples: VecDeque::new(),
+            alert: BatteryAlertLevel::Normal,
+        });
+
+    battery.samples.push_back((now, level));
+    while battery.samples.len() > MAX_SAMPLES
+        || battery
+            .samples
+            .front()
+            .is_some_and(|(t, _)| now.saturating_sub(*t) > SAMPLE_WINDOW_MS)
+    {
+        battery.samples.pop_front();
+    }
+
+    let alert = threshold.map_or(BatteryAlertLevel::Normal, |t| {
+        t.alert_level(level, battery.alert)
+    });
+
+    match threshold.and_then(|t| t.critical_max_level) {
+        Some(cap) if alert == BatteryAlertLevel::Critical => {
+            LEVEL_CAPS.lock().insert(toy_id, cap);
+        }
+        _ => {
+            LEVEL_CAPS.lock().remove(&toy_id);
+        }
+    }
+
+    if alert == battery.alert {
+        return None;
+    }
+    match alert {
+        BatteryAlertLevel::Normal => info!("{} battery recovered: {:.2}", toy_name, level),
+        _ => warn!("{} battery {:?}: {:.2}", toy_name, alert, level),
+    }
+    battery.alert = alert;
+    Some(alert)
+}
+
+/*
+ * Drops the intensity cap of a toy that went offline
+ */
+pub fn forget_level_cap(toy_id: u32) {
+    LEVEL_CAPS.lock().remove(&toy_id);
+}
+
+/*
+ * Applies a toy's critical battery cap to feature levels
+ * Flipped features reach the toy as 1 - level so their levels are raised instead
+ * Position and linear features aren't intensities and are left alone
+ */
+pub fn cap_feature_levels(
+    toy_id: u32,
+    feature_type: VCFeatureType,
+    flip_float: bool,
+    mut levels: LevelTweaks,
+) -> LevelTweaks {
+    if matches!(
+        feature_type,
+        VCFeatureType::Position | VCFeatureType::Linear
+    ) {
+        return levels;
+    }
+    let Some(cap) = LEVEL_CAPS.lock().get(&toy_id).copied() else {
+        return levels;
+    };
+
+    if flip_float {
+        let floor = 1.0 - cap;
+        levels.minimum_level = levels.minimum_level.max(floor);
+        levels.maximum_level = levels.maximum_level.max(floor);
+        levels.idle_level = levels.idle_level.max(floor);
+    } else {
+        levels.minimum_level = levels.minimum_level.min(cap);
+        levels.maximum_level = levels.maximum_level.min(cap);
+        levels.idle_level = levels.idle_level.min(cap);
+    }
+    levels
+}
+
+/*
+ * Seconds until empty from the drain rate over the estimate window
+ * None while the battery isn't draining or there aren't enough samples
+ * Samples are wall clock times so a clock stepping back must not underflow
+ */
+fn estimate_remaining_secs(samples: &VecDeque<(u64, f64)>) -> Option<u64> {
+    let (last_t, last_level) = *samples.back()?;
+    let (first_t, first_level) = *samples
+        .iter()
+        .find(|(t, _)| last_t.saturating_sub(*t) <= ESTIMATE_WINDOW_MS)?;
+
+    let elapsed_ms = last_t.saturating_sub(first_t);
+    let drained = first_level - last_level;
+    if elapsed_ms == 0 || drained <= 0.0 {
+        return None;
+    }
+    Some((last_level / drained * elapsed_ms as f64 / 1000.0) as u64)
+}
+
+pub fn battery_history() -> Vec<FeBatteryHistory> {
+    let batteries = TOY_BATTERIES.lock();
+    let mut history: Vec<FeBatteryHistory> = batteries
+        .iter()
+        .map(|((toy_name, sub_id), battery)| FeBatteryHistory {
+            toy_name: toy_name.clone(),
+            sub_id: *sub_id,
+            alert: battery.alert,
+            estimated_remaining_secs: estimate_remaining_secs(&battery.samples),
+            samples: battery
+                .samples
+                .iter()
+                .map(|(unix_ms, level)| FeBatterySample {
+                    unix_ms: *unix_ms,
+                    level: *level,
+                })
+                .collect(),
+        })
+        .collect();
+    history.sort_by(|a, b| (&a.toy_name, a.sub_id).cmp(&(&b.toy_name, b.sub_id)));
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLD: BatteryThreshold = BatteryThreshold {
+        low: 0.2,
+        critical: Some(0.1),
+        critical_max_level: Some(0.5),
+    };
+
+    // Feeds levels through the threshold like consecutive samples
+    fn alert_levels(levels: &[f64]) -> Vec<BatteryAlertLevel> {
+        let mut current = BatteryAlertLevel::Normal;
+        levels
+            .iter()
+            .map(|level| {
+                current = THRESHOLD.alert_level(*level, current);
+                current
+            })
+            .collect()
+    }
+
+    #[test]
+    fn alerts_when_crossing_thresholds() {
+        use BatteryAlertLevel::*;
+        assert_eq!(
+            alert_levels(&[0.5, 0.21, 0.2, 0.19, 0.11, 0.09, 0.0]),
+            [Normal, Normal, Normal, Low, Low, Critical, Critical]
+        );
+
+        let low_only = BatteryThreshold {
+            critical: None,
+            ..THRESHOLD
+        };
+        assert_eq!(low_only.alert_level(0.0, Normal), Low);
+    }
+
+    #[test]
+    fn recovers_through_the_hysteresis_band() {
+        use BatteryAlertLevel::*;
+        // Hovering around a threshold doesn't flap between levels
+        assert_eq!(
+            alert_levels(&[0.09, 0.11, 0.14, 0.16, 0.19, 0.24, 0.26, 0.19]),
+            [Critical, Critical, Critical, Low, Low, Low, Normal, Low]
+        );
+    }
+
+    fn samples(samples: &[(u64, f64)]) -> VecDeque<(u64, f64)> {
+        samples.iter().copied().collect()
+    }
+
+    #[test]
+    fn estimates_remaining_time_from_the_drain_rate() {
+        // 10% in 10 minutes with 50% left
+        let drained = samples(&[(0, 0.6), (5 * 60_000, 0.55), (10 * 60_000, 0.5)]);
+        assert_eq!(estimate_remaining_secs(&drained), Some(50 * 60));
+
+        // Only the estimate window counts
+        let old = samples(&[(0, 1.0), (60 * 60_000, 0.6), (70 * 60_000, 0.5)]);
+        assert_eq!(estimate_remaining_secs(&old), Some(50 * 60));
+    }
+
+    #[test]
+    fn no_estimate_without_drain() {
+        assert_eq!(estimate_remaining_secs(&samples(&[])), None);
+        assert_eq!(estimate_remaining_secs(&samples(&[(0, 0.5)])), None);
+        // Charging and steady batteries
+        assert_eq!(
+            estimate_remaining_secs(&samples(&[(0, 0.4), (60_000, 0.5)])),
+            None
+        );
+        assert_eq!(
+            estimate_remaining_secs(&samples(&[(0, 0.5), (60_000, 0.5)])),
+            None
+        );
+        // The clock stepped back between samples
+        assert_eq!(
+            estimate_remaining_secs(&samples(&[(60_000, 0.6), (0, 0.5)])),
+            None
+        );
+    }
+}
@@ -0,0 +1,90 @@
+/*
+ * Dry-run mode
+ * OSC input is processed as usual but the final toy commands are logged and sent to the frontend instead of the devices.
+ * Offline toys are driven from their saved configs while dry-run is enabled.
+ */
+use log::info;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::frontend::frontend_types::FeDryRunCommand;
+
+use super::toyops::VCFeatureType;
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref DRY_RUN_APP_HANDLE: Mutex<Option<AppHandle>> = Mutex::new(None);
+}
+
+#[cfg(test)]
+lazy_static::lazy_static! {
+    // Tests run without an app handle so the recorded commands are kept here instead
+    static ref TEST_COMMANDS: Mutex<Vec<FeDryRunCommand>> = Mutex::new(Vec::new());
+}
+
+/*
+ * Takes the commands recorded for a toy, tests use their own toy names as they run in parallel
+ */
+#[cfg(test)]
+pub fn take_test_commands(toy_name: &str) -> Vec<FeDryRunCommand> {
+    let mut commands = TEST_COMMANDS.lock();
+    let (taken, rest) = commands.drain(..).partition(|c| c.toy_name == toy_name);
+    *commands = rest;
+    taken
+}
+
+pub fn dry_run_enabled() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+pub fn set_dry_run_enabled(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+    info!("Dry-run {}", if enabled { "enabled" } else { "disabled" });
+}
+
+pub fn set_dry_run_app_handle(app_handle: AppHandle) {
+    *DRY_RUN_APP_HANDLE.lock() = Some(app_handle);
+}
+
+/*
+ * Records a toy command that would have been sent
+ * level is the final level after clamping and flipping
+ */
+pub fn record_dry_run_command(
+    toy_name: &str,
+    toy_id: Option<u32>,
+    feature_type: VCFeatureType,
+    feature_index: u32,
+    actuator: &str,
+    level: f64,
+) {
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    info!(
+        "DRY-RUN [{}] {} FI[{}] AT[{}] L[{:.2}]",
+        timestamp_ms, toy_name, feature_index, actuator, level
+    );
+
+    let command = FeDryRunCommand {
+        timestamp_ms,
+        toy_name: toy_name.to_string(),
+        toy_id,
+        feature_index,
+        feature_type: feature_type.to_fe(),
+        actuator: actuator.to_string(),
+        level,
+    };
+
+    #[cfg(test)]
+    TEST_COMMANDS.lock().push(command.clone());
+
+    if let Some(app_handle) = DRY_RUN_APP_HANDLE.lock().as_ref() {
+        let _ = app_handle.emit_all("fe_dry_run_event", command);
+    }
+}
@@ -1,10 +1,20 @@
+use crate::config::toy::VCToyConfig;
 use crate::config::OSCNetworking;
 use crate::frontend::frontend_types::FeCoreEvent;
 use crate::frontend::frontend_types::FeScanEvent;
 use crate::frontend::frontend_types::FeToyEvent;
 use crate::frontend::frontend_types::FeVCToy;
 use crate::frontend::ToFrontend;
-use crate::osc::logic::toy_input_routine;
+use crate::osc::chatbox::{announce, ChatboxAnnouncement};
+use crate::osc::feedback::{configure_osc_feedback, send_feature_level, send_toy_state};
+use crate::osc::ingress::VCOscMessage;
+use crate::osc::oscquery::update_oscquery_tree;
+use crate::osc::param_browser::refresh_parameter_consumers;
+use crate::osc::telemetry::{forget_feature_levels, record_feature_level};
+use crate::osc_api::mirror::mirror_anatomy_state;
+use crate::osc_api::osc_api::set_dispatch_tx;
+use crate::toy_handling::battery::{cap_feature_levels, forget_level_cap};
+use crate::toy_handling::dry_run::{dry_run_enabled, record_dry_run_command};
 use crate::toy_handling::toy_manager::ToyManager;
 use crate::toy_handling::toyops::LevelTweaks;
 use crate::toy_handling::toyops::ToyParameter;
@@ -25,12 +35,9 @@ use futures_timer::Delay;
 use log::debug;
 use log::{error as logerr, info, trace, warn};
 use parking_lot::Mutex;
-use rosc::OscMessage;
-use rosc::OscType;
 use std::collections::HashMap;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 use tauri::api::notification::Notification;
@@ -81,7 +88,8 @@ impl ToyRateLimiter {
         let mps = self.messages_per_second.load(Ordering::Relaxed);
         let interval_ms = 1000 / mps;
 
-        if now - last >= interval_ms {
+        // Another thread may have stored a later time since now was taken
+        if now.saturating_sub(last) >= interval_ms {
             self.last_update.store(now, Ordering::Relaxed);
             true
         } else {
@@ -191,6 +199,7 @@ pub async fn client_event_handler(
                             .unwrap()
                             .online_toys
                             .insert(toy.toy_id, toy.clone());
+                        refresh_parameter_consumers(vc_lock.core_toy_manager.as_ref().unwrap());
                     }
                     trace!("Toy inserted into VibeCheckState toys");
 
@@ -228,21 +237,24 @@ pub async fn client_event_handler(
                         }
                     }
 
+                    announce(ChatboxAnnouncement::ToyConnected {
+                        toy: &toy.toy_name,
+                        battery: &toy.toy_power.to_string(),
+                    });
                     info!("Toy Connected: {} | {}", toy.toy_name, toy.toy_id);
                 }
                 ButtplugClientEvent::DeviceRemoved(dev) => {
                     // Get scan on disconnect and toy
                     let (sod, toy) = {
                         let mut vc_lock = vibecheck_state_pointer.lock();
-                        (
-                            vc_lock.config.scan_on_disconnect,
-                            vc_lock
-                                .core_toy_manager
-                                .as_mut()
-                                .unwrap()
-                                .online_toys
-                                .remove(&dev.index()),
-                        )
+                        let toy = vc_lock
+                            .core_toy_manager
+                            .as_mut()
+                            .unwrap()
+                            .online_toys
+                            .remove(&dev.index());
+                        refresh_parameter_consumers(vc_lock.core_toy_manager.as_ref().unwrap());
+                        (vc_lock.config.scan_on_disconnect, toy)
                     };
 
                     // Check if toy is valid
@@ -254,6 +266,7 @@ pub async fn client_event_handler(
 
                         let _ =
                             app_handle.emit_all("fe_toy_event", FeToyEvent::Remove(dev.index()));
+                        announce(ChatboxAnnouncement::ToyDisconnected { toy: &toy.toy_name });
 
                         {
                             let vc_lock = vibecheck_state_pointer.lock();
@@ -502,6 +515,16 @@ async fn mode_processor<'toy_parameter>(
                     )
                     .await
                 } // Input Processor & Boolean
+                ModeProcessorInputType::Int(i_input) => {
+                    // Input Processor & Int
+                    mode_processor_logic(
+                        ModeProcessorInputType::Int(i_input),
+                        processing_mode_values,
+                        feature_levels,
+                        flip_input,
+                    )
+                    .await
+                } // Input Processor & Int
             }
         }
         // Input is from parameter parsing
@@ -527,6 +550,17 @@ async fn mode_processor<'toy_parameter>(
                     )
                     .await
                 } // Raw Input & Boolean
+                ModeProcessorInputType::Int(i_input) => {
+                    // Raw Input & Int
+                    // Resolve through the parameter's value table first (Int => Float level)
+                    mode_processor_logic(
+                        toy_parameter.resolve_int(i_input),
+                        &mut toy_parameter.processing_mode_values,
+                        feature_levels,
+                        flip_input,
+                    )
+                    .await
+                } // Raw Input & Int
             }
         }
     }
@@ -553,6 +587,14 @@ async fn mode_processor_logic(
                     Some(0.0)
                 }
             }
+            // Int without a value table is on/off like a Boolean
+            ModeProcessorInputType::Int(i) => {
+                if i != 0 {
+                    Some(1.0)
+                } else {
+                    Some(0.0)
+                }
+            }
         },
         // Smoothing Mode Handling
         // Smooth = do smoothing logic with input and processor
@@ -577,6 +619,7 @@ async fn mode_processor_logic(
                     }
                 }
                 ModeProcessorInputType::Boolean(_b) => None, // No support for Smoothing mode and Boolean
+                ModeProcessorInputType::Int(_i) => None, // No support for Smoothing mode and unmapped Int
             }
             // Return processed input
         }
@@ -602,6 +645,7 @@ async fn mode_processor_logic(
                     }
                 }
                 ModeProcessorInputType::Boolean(_b) => None, // No support for Rate and Boolean
+                ModeProcessorInputType::Int(_i) => None,     // No support for Rate and unmapped Int
             }
         }
         // Constant Mode Handling
@@ -620,6 +664,13 @@ async fn mode_processor_logic(
                     Some(0.0)
                 }
             }
+            ModeProcessorInputType::Int(i) => {
+                if i != 0 {
+                    Some(feature_levels.constant_level)
+                } else {
+                    Some(0.0)
+                }
+            }
         },
     }
 }
@@ -632,6 +683,87 @@ async fn mode_processor_logic(
 */
 // Uses TME send and recv channel
 
+/*
+ * Listening routine for an offline toy while dry-run is enabled
+ * Uses the saved toy config and never touches a device
+ */
+async fn offline_toy_routine(
+    toy_name: String,
+    mut toy_bcst_rx: BReceiver<ToySig>,
+    mut vc_toy_features: VCToyFeatures,
+) {
+    let target = ToyCommandTarget::Offline(Arc::from(toy_name.as_str()));
+    loop {
+        match toy_bcst_rx.recv().await {
+            Ok(ToySig::OSCMsg(msg)) => {
+                parse_osc_message(&msg, target.clone(), &mut vc_toy_features).await
+            }
+            // Offline toy configs are only altered while the toy is online
+            Ok(ToySig::UpdateToy(_)) => {}
+            Err(sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(sync::broadcast::error::RecvError::Closed) => break,
+        }
+    }
+    info!("Offline toy {} left dry-run listening routine", toy_name);
+}
+
+/*
+ * Spawns dry-run routines for saved toy configs that are not online
+ */
+fn spawn_offline_toys(
+    toy_manager: &ToyManager,
+    toy_async_rt: &Runtime,
+    toy_bcst_tx: &BSender<ToySig>,
+    offline_toy_ths: &mut HashMap<String, JoinHandle<()>>,
+) {
+    spawn_offline_configs(
+        toy_manager
+            .configs
+            .values()
+            .filter(|config| !toy_manager.check_toy_online(&config.toy_name)),
+        toy_async_rt,
+        toy_bcst_tx,
+        offline_toy_ths,
+    );
+}
+
+/*
+ * Spawns a dry-run routine for each config that is not listening yet
+ */
+fn spawn_offline_configs<'a>(
+    configs: impl Iterator<Item = &'a VCToyConfig>,
+    toy_async_rt: &Runtime,
+    toy_bcst_tx: &BSender<ToySig>,
+    offline_toy_ths: &mut HashMap<String, JoinHandle<()>>,
+) {
+    for config in configs {
+        if offline_toy_ths.contains_key(&config.toy_name) {
+            continue;
+        }
+        let mut features = config.features.clone();
+        features.init_penetration_systems();
+        offline_toy_ths.insert(
+            config.toy_name.clone(),
+            toy_async_rt.spawn(offline_toy_routine(
+                config.toy_name.clone(),
+                toy_bcst_tx.subscribe(),
+                features,
+            )),
+        );
+        info!(
+            "Offline toy: {} started dry-run listening..",
+            config.toy_name
+        );
+    }
+}
+
+fn abort_offline_toys(offline_toy_ths: &mut HashMap<String, JoinHandle<()>>) {
+    for (toy_name, th) in offline_toy_ths.drain() {
+        th.abort();
+        info!("[OFFLINE TOY: {}] Stopped dry-run listening.", toy_name);
+    }
+}
+
 pub async fn toy_management_handler(
     tme_send: UnboundedSender<ToyManagementEvent>,
     mut tme_recv: UnboundedReceiver<ToyManagementEvent>,
@@ -655,8 +787,13 @@ pub async fn toy_management_handler(
                     continue;
                 };
                 match ts {
-                    ToySig::OSCMsg(mut msg) => {
-                        parse_osc_message(&mut msg, dev.clone(), &mut vc_toy_features).await
+                    ToySig::OSCMsg(msg) => {
+                        parse_osc_message(
+                            &msg,
+                            ToyCommandTarget::Device(dev.clone()),
+                            &mut vc_toy_features,
+                        )
+                        .await
                     }
                     ToySig::UpdateToy(toy) => update_toy(toy, dev.clone(), &mut vc_toy_features),
                 }
@@ -669,6 +806,7 @@ pub async fn toy_management_handler(
     }; // Toy listening routine
 
     let mut listening = false;
+    configure_osc_feedback(&vc_config);
 
     // Management loop
     loop {
@@ -676,22 +814,30 @@ pub async fn toy_management_handler(
         if let Some(event) = tme_recv.recv().await {
             match event {
                 // Handle Toy Update Signals
-                ToyManagementEvent::Tu(tu) => match tu {
-                    ToyUpdate::AddToy(toy) => {
-                        core_toy_manager.online_toys.insert(toy.toy_id, toy);
-                    }
-                    ToyUpdate::RemoveToy(id) => {
-                        core_toy_manager.online_toys.remove(&id);
-                    }
-                    ToyUpdate::AlterToy(toy) => {
-                        core_toy_manager.online_toys.insert(toy.toy_id, toy);
+                ToyManagementEvent::Tu(tu) => {
+                    match tu {
+                        ToyUpdate::AddToy(toy) => {
+                            send_toys_state(std::iter::once(&toy), false);
+                            core_toy_manager.online_toys.insert(toy.toy_id, toy);
+                        }
+                        ToyUpdate::RemoveToy(id) => {
+                            if let Some(toy) = core_toy_manager.online_toys.remove(&id) {
+                                send_toy_removed(&toy);
+                            }
+                        }
+                        ToyUpdate::AlterToy(toy) => {
+                            core_toy_manager.online_toys.insert(toy.toy_id, toy);
+                        }
                     }
-                },
+                    update_oscquery_tree(core_toy_manager.online_toys.values());
+                    mirror_anatomy_state(core_toy_manager.online_toys.values());
+                }
                 // Handle Management Signals
                 ToyManagementEvent::Sig(tm_sig) => {
                     match tm_sig {
                         TmSig::StartListening(osc_net) => {
                             vc_config = osc_net;
+                            configure_osc_feedback(&vc_config);
                             listening = true;
                         }
                         TmSig::StopListening => {
@@ -720,6 +866,8 @@ pub async fn toy_management_handler(
 
         // Toy threads
         let mut running_toy_ths: HashMap<u32, JoinHandle<()>> = HashMap::new();
+        // Offline toy threads (dry-run only)
+        let mut offline_toy_ths: HashMap<String, JoinHandle<()>> = HashMap::new();
 
         // Broadcast channels for toy commands
         let (toy_bcst_tx, _toy_bcst_rx): (BSender<ToySig>, BReceiver<ToySig>) =
@@ -740,21 +888,20 @@ pub async fn toy_management_handler(
             );
             info!("Toy: {} started listening..", *toy.0);
         }
+        send_toys_state(core_toy_manager.online_toys.values(), true);
+
+        if dry_run_enabled() {
+            spawn_offline_toys(
+                &core_toy_manager,
+                &toy_async_rt,
+                &toy_bcst_tx,
+                &mut offline_toy_ths,
+            );
+        }
 
-        // Create OSC listener thread
-        let toy_bcst_tx_osc = toy_bcst_tx.clone();
-        info!("Spawning OSC listener..");
-        let vc_conf_clone = vc_config.clone();
-        let tme_send_clone = tme_send.clone();
-        let app_handle_clone = app_handle.clone();
-        thread::spawn(move || {
-            toy_input_routine(
-                toy_bcst_tx_osc,
-                tme_send_clone,
-                app_handle_clone,
-                vc_conf_clone,
-            )
-        });
+        // The OSC listeners are always bound, toy messages reach the toys from now on
+        set_dispatch_tx(Some(toy_bcst_tx.clone()));
+        let _ = tme_send.send(ToyManagementEvent::Sig(TmSig::Listening));
 
         loop {
             // Recv event (listening)
@@ -765,6 +912,13 @@ pub async fn toy_management_handler(
                 ToyManagementEvent::Tu(tu) => {
                     match tu {
                         ToyUpdate::AddToy(toy) => {
+                            // The toy is online now so its dry-run routine is replaced
+                            if let Some(th) = offline_toy_ths
+                                .remove(&toy.toy_name.replace("Lovense Connect", "Lovense"))
+                            {
+                                th.abort();
+                            }
+                            send_toys_state(std::iter::once(&toy), true);
                             core_toy_manager.online_toys.insert(toy.toy_id, toy.clone());
                             let f_run = f(
                                 toy.device_handle,
@@ -780,7 +934,6 @@ pub async fn toy_management_handler(
                             info!("Toy: {} started listening..", toy.toy_id);
                         }
                         ToyUpdate::RemoveToy(id) => {
-                            // OSC Listener thread will only die on StopListening event
                             if let Some(toy) = running_toy_ths.remove(&id) {
                                 toy.abort();
                                 match toy.await {
@@ -791,7 +944,9 @@ pub async fn toy_management_handler(
                                 }
                                 info!("[TOY ID: {}] Stopped listening. (ToyUpdate::RemoveToy)", id);
                                 running_toy_ths.remove(&id);
-                                core_toy_manager.online_toys.remove(&id);
+                                if let Some(toy) = core_toy_manager.online_toys.remove(&id) {
+                                    send_toy_removed(&toy);
+                                }
                             }
                         }
                         ToyUpdate::AlterToy(toy) => {
@@ -808,12 +963,15 @@ pub async fn toy_management_handler(
                             core_toy_manager.online_toys.insert(toy.toy_id, toy);
                         }
                     }
+                    update_oscquery_tree(core_toy_manager.online_toys.values());
+                    mirror_anatomy_state(core_toy_manager.online_toys.values());
                 }
                 // Handle Management Signals
                 ToyManagementEvent::Sig(tm_sig) => {
                     match tm_sig {
                         TmSig::StartListening(osc_net) => {
                             vc_config = osc_net;
+                            configure_osc_feedback(&vc_config);
                             // Already listening
                         }
                         TmSig::StopListening => {
@@ -833,9 +991,13 @@ pub async fn toy_management_handler(
                                 info!("[TOY ID: {}] Stopped listening. (TMSIG)", toy.0);
                             }
                             running_toy_ths.clear();
-                            drop(_toy_bcst_rx); // Causes OSC listener to die
+                            abort_offline_toys(&mut offline_toy_ths);
+                            set_dispatch_tx(None); // Toy messages are dropped while not listening
+                            drop(_toy_bcst_rx);
                             toy_async_rt.shutdown_background();
                             listening = false;
+                            forget_feature_levels(None);
+                            send_toys_state(core_toy_manager.online_toys.values(), false);
                             info!("Toys: {}", core_toy_manager.online_toys.len());
                             break; //Stop Listening
                         }
@@ -857,12 +1019,28 @@ pub async fn toy_management_handler(
                                 info!("[TOY ID: {}] Stopped listening. (TMSIG)", toy.0);
                             }
                             running_toy_ths.clear();
-                            drop(_toy_bcst_rx); // Causes OSC listener to die
+                            abort_offline_toys(&mut offline_toy_ths);
+                            set_dispatch_tx(None); // Toy messages are dropped while not listening
+                            drop(_toy_bcst_rx);
                             toy_async_rt.shutdown_background();
                             listening = false;
+                            forget_feature_levels(None);
+                            send_toys_state(core_toy_manager.online_toys.values(), false);
                             info!("Toys: {}", core_toy_manager.online_toys.len());
                             break; //Stop Listening
                         }
+                        TmSig::DryRun(enabled) => {
+                            if enabled {
+                                spawn_offline_toys(
+                                    &core_toy_manager,
+                                    &toy_async_rt,
+                                    &toy_bcst_tx,
+                                    &mut offline_toy_ths,
+                                );
+                            } else {
+                                abort_offline_toys(&mut offline_toy_ths);
+                            }
+                        }
                         _ => {}
                     }
                 } // Event handled
@@ -873,8 +1051,8 @@ pub async fn toy_management_handler(
 
 #[inline(always)]
 async fn parse_osc_message(
-    msg: &mut OscMessage,
-    dev: Arc<ButtplugClientDevice>,
+    msg: &VCOscMessage,
+    target: ToyCommandTarget,
     vc_toy_features: &mut VCToyFeatures,
 ) {
     // Parse OSC msgs to toys commands
@@ -885,9 +1063,8 @@ async fn parse_osc_message(
      * Mode processor is a function now so it can be used in both!
      */
 
-    // msg args pop should go here
-    //let newest_msg_addr = msg.addr.clone();
-    let newest_msg_val = msg.args.pop().unwrap();
+    // Value was validated at ingress
+    let newest_msg_val = msg.value;
 
     /*
      * Input mode processing
@@ -902,9 +1079,9 @@ async fn parse_osc_message(
         vc_toy_features.get_features_with_input_processors(&msg.addr)
     {
         match newest_msg_val {
-            OscType::Float(lvl) => {
+            ModeProcessorInputType::Float(lvl) => {
                 for feature in input_processor_system_features {
-                    let float_level = ((lvl * 100.0).round() / 100.0) as f64;
+                    let float_level = (lvl * 100.0).round() / 100.0;
                     // pen_system is checked for None in get_features_with_penetration_systems method.
                     // Give access to internal mode values here (input, internal_values)
                     if let Some(i_mode_processed_value) = feature
@@ -922,12 +1099,13 @@ async fn parse_osc_message(
                             feature.penetration_system.pen_system_processing_mode
                         {
                             command_toy(
-                                dev.clone(),
+                                target.clone(),
                                 feature.feature_type,
                                 i_mode_processed_value,
                                 feature.feature_index,
                                 feature.flip_input_float,
                                 feature.feature_levels,
+                                feature.output_parameter.as_deref(),
                             )
                             .await;
                         } else {
@@ -945,12 +1123,13 @@ async fn parse_osc_message(
                             .await
                             {
                                 command_toy(
-                                    dev.clone(),
+                                    target.clone(),
                                     feature.feature_type,
                                     i,
                                     feature.feature_index,
                                     feature.flip_input_float,
                                     feature.feature_levels,
+                                    feature.output_parameter.as_deref(),
                                 )
                                 .await;
                             }
@@ -959,7 +1138,7 @@ async fn parse_osc_message(
                 }
             }
             // Boolean can be supported in the process trait method
-            OscType::Bool(b) => {
+            ModeProcessorInputType::Boolean(b) => {
                 for feature in input_processor_system_features {
                     // Boolean to float transformation here
                     if let Some(i_mode_processed_value) = feature
@@ -974,12 +1153,13 @@ async fn parse_osc_message(
                             feature.penetration_system.pen_system_processing_mode
                         {
                             command_toy(
-                                dev.clone(),
+                                target.clone(),
                                 feature.feature_type,
                                 i_mode_processed_value,
                                 feature.feature_index,
                                 feature.flip_input_float,
                                 feature.feature_levels,
+                                feature.output_parameter.as_deref(),
                             )
                             .await;
                         } else if let Some(i) = mode_processor(
@@ -993,27 +1173,29 @@ async fn parse_osc_message(
                         .await
                         {
                             command_toy(
-                                dev.clone(),
+                                target.clone(),
                                 feature.feature_type,
                                 i,
                                 feature.feature_index,
                                 feature.flip_input_float,
                                 feature.feature_levels,
+                                feature.output_parameter.as_deref(),
                             )
                             .await;
                         }
                     }
                 }
             }
-            _ => (),
-        } // End match OscType for Input processors
+            // Penetration systems are driven by float and bool contacts only
+            ModeProcessorInputType::Int(_) => {}
+        } // End match input type for Input processors
     } // End Input processing
 
     if let Some(features) = vc_toy_features.get_features_from_param(&msg.addr) {
         match newest_msg_val {
-            OscType::Float(lvl) => {
-                // Clamp float accuracy to hundredths and cast as 64 bit float
-                let float_level = ((lvl * 100.0).round() / 100.0) as f64;
+            ModeProcessorInputType::Float(lvl) => {
+                // Clamp float accuracy to hundredths
+                let float_level = (lvl * 100.0).round() / 100.0;
                 //debug!("Received and cast float lvl: {:.5}", float_level);
 
                 for feature in features {
@@ -1043,19 +1225,20 @@ async fn parse_osc_message(
                         .await
                         {
                             command_toy(
-                                dev.clone(),
+                                target.clone(),
                                 feature.feature_type,
                                 mode_processed_value,
                                 feature.feature_index,
                                 feature.flip_input_float,
                                 feature.feature_levels,
+                                feature.output_parameter.as_deref(),
                             )
                             .await;
                         }
                     } // If no matching toy parameter skip feature
                 }
             }
-            OscType::Bool(b) => {
+            ModeProcessorInputType::Boolean(b) => {
                 info!("Got a Bool! {} = {}", msg.addr, b);
                 for feature in features {
                     // Get ToyParameter here
@@ -1083,19 +1266,60 @@ async fn parse_osc_message(
                         .await
                         {
                             command_toy(
-                                dev.clone(),
+                                target.clone(),
                                 feature.feature_type,
                                 i,
                                 feature.feature_index,
                                 feature.flip_input_float,
                                 feature.feature_levels,
+                                feature.output_parameter.as_deref(),
+                            )
+                            .await;
+                        }
+                    }
+                }
+            }
+            ModeProcessorInputType::Int(i) => {
+                trace!("Got an Int! {} = {}", msg.addr, i);
+                for feature in features {
+                    // Get ToyParameter here
+                    let mut toy_parameter = feature
+                        .osc_parameters
+                        .iter_mut()
+                        .filter_map(|param| {
+                            if param.parameter == msg.addr {
+                                Some(param)
+                            } else {
+                                None
+                            }
+                        })
+                        .collect::<Vec<&mut ToyParameter>>();
+
+                    if let Some(first_toy_param) = toy_parameter.first_mut() {
+                        if let Some(level) = mode_processor(
+                            ModeProcessorInput::RawInput(
+                                ModeProcessorInputType::Int(i),
+                                first_toy_param,
+                            ),
+                            feature.feature_levels,
+                            feature.flip_input_float,
+                        )
+                        .await
+                        {
+                            command_toy(
+                                target.clone(),
+                                feature.feature_type,
+                                level,
+                                feature.feature_index,
+                                feature.flip_input_float,
+                                feature.feature_levels,
+                                feature.output_parameter.as_deref(),
                             )
                             .await;
                         }
                     }
                 }
             }
-            _ => {} // Skip parameter because unsuppported OSC type
         }
     }
 }
@@ -1112,22 +1336,85 @@ fn update_toy(toy: ToyUpdate, dev: Arc<ButtplugClientDevice>, vc_toy_features: &
     info!("Altered toy: {}", new_toy.toy_id);
 }
 
+/*
+ * Sends the connected and listening state of the toys that have OSC data enabled
+ */
+fn send_toys_state<'a>(toys: impl Iterator<Item = &'a VCToy>, listening: bool) {
+    for toy in toys.filter(|toy| toy.osc_data) {
+        send_toy_state(&toy.toy_name, toy.sub_id, toy.toy_connected, listening);
+    }
+}
+
+fn send_toy_removed(toy: &VCToy) {
+    forget_feature_levels(Some(toy.toy_id));
+    forget_level_cap(toy.toy_id);
+    if toy.osc_data {
+        send_toy_state(&toy.toy_name, toy.sub_id, false, false);
+    }
+}
+
+/*
+ * What a toy command is sent to
+ * Offline toys only exist as a saved config so their commands can only be dry-run
+ */
+#[derive(Clone)]
+pub enum ToyCommandTarget {
+    Device(Arc<ButtplugClientDevice>),
+    Offline(Arc<str>),
+}
+
 /*
  * Sends commands to toys
  */
 pub async fn command_toy(
-    dev: Arc<ButtplugClientDevice>,
+    target: ToyCommandTarget,
     feature_type: VCFeatureType,
     float_level: f64,
     feature_index: u32,
     flip_float: bool,
     feature_levels: LevelTweaks,
+    output_parameter: Option<&str>,
 ) {
     if !TOY_RATE_LIMITER.can_send() {
         trace!("Rate limited, skipping command");
         return;
     }
 
+    // Critical battery caps only apply to real devices
+    let feature_levels = match &target {
+        ToyCommandTarget::Device(dev) => {
+            cap_feature_levels(dev.index(), feature_type, flip_float, feature_levels)
+        }
+        ToyCommandTarget::Offline(_) => feature_levels,
+    };
+
+    // Level the toy ends up at after idle, min / max and flip are applied
+    let output_level = clamp_and_flip(float_level, flip_float, feature_levels);
+
+    if let Some(output_parameter) = output_parameter {
+        send_feature_level(output_parameter, output_level, float_level == 0.0);
+    }
+
+    let dev = match target {
+        ToyCommandTarget::Device(dev) if !dry_run_enabled() => dev,
+        target => {
+            let (toy_name, toy_id) = match &target {
+                ToyCommandTarget::Device(dev) => (dev.name().as_str(), Some(dev.index())),
+                ToyCommandTarget::Offline(toy_name) => (toy_name.as_ref(), None),
+            };
+            record_dry_run_command(
+                toy_name,
+                toy_id,
+                feature_type,
+                feature_index,
+                actuator_name(feature_type),
+                output_level,
+            );
+            return;
+        }
+    };
+    record_feature_level(dev.index(), feature_type, feature_index, output_level);
+
     match feature_type {
         VCFeatureType::Vibrator => {
             scalar_parse_levels_send_toy_cmd(
@@ -1219,3 +1506,360 @@ pub async fn command_toy(
         }
     }
 }
+
+/*
+ * Name of the actuator a feature type is commanded through
+ */
+fn actuator_name(feature_type: VCFeatureType) -> &'static str {
+    match feature_type {
+        VCFeatureType::Vibrator => "Vibrate",
+        VCFeatureType::Rotator => "Rotate",
+        VCFeatureType::Linear => "Linear",
+        VCFeatureType::Oscillate => "Oscillate",
+        VCFeatureType::Constrict => "Constrict",
+        VCFeatureType::Inflate => "Inflate",
+        VCFeatureType::Position => "Position",
+        VCFeatureType::ScalarRotator => "ScalarRotate",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::frontend_types::FeVCFeatureType;
+    use crate::osc::ingress::{
+        decode_packet, fuzz::random_datagrams, ingress_packet, AVATAR_CHANGE_ADDR,
+    };
+    use crate::osc_api::{osc_api::dispatch_toy_message, API_PREFIX};
+    use crate::toy_handling::dry_run::{set_dry_run_enabled, take_test_commands};
+    use crate::toy_handling::input_processor::penetration_systems::{
+        PenetrationSystem, PenetrationSystemType,
+    };
+    use crate::toy_handling::toyops::{IntValueMapping, VCToyFeature};
+
+    fn toy_parameter(
+        parameter: &str,
+        processing_mode: ProcessingMode,
+        int_values: Vec<IntValueMapping>,
+    ) -> ToyParameter {
+        ToyParameter {
+            parameter: parameter.to_string(),
+            processing_mode_values: ProcessingModeValues::new_from(&processing_mode),
+            processing_mode,
+            int_values,
+        }
+    }
+
+    fn toy_feature(
+        feature_index: u32,
+        osc_parameters: Vec<ToyParameter>,
+        penetration_system: PenetrationSystemType,
+    ) -> VCToyFeature {
+        VCToyFeature {
+            feature_index,
+            feature_enabled: true,
+            feature_type: VCFeatureType::Vibrator,
+            osc_parameters,
+            penetration_system: PenetrationSystem {
+                pen_system_type: penetration_system,
+                pen_system_processing_mode: ProcessingMode::Smooth,
+                ..Default::default()
+            },
+            flip_input_float: false,
+            feature_levels: LevelTweaks::default(),
+            smooth_enabled: false,
+            rate_enabled: false,
+            output_parameter: None,
+        }
+    }
+
+    /*
+     * One feature per processing mode and input processor for the fuzz toy parameters
+     */
+    fn fuzz_features() -> VCToyFeatures {
+        let level = "/avatar/parameters/fuzz/level";
+        let gesture = "/avatar/parameters/fuzz/gesture";
+        let mut features = vec![
+            toy_feature(
+                0,
+                vec![toy_parameter(level, ProcessingMode::Raw, vec![])],
+                PenetrationSystemType::NONE,
+            ),
+            toy_feature(
+                1,
+                vec![toy_parameter(level, ProcessingMode::Smooth, vec![])],
+                PenetrationSystemType::NONE,
+            ),
+            toy_feature(
+                2,
+                vec![toy_parameter(level, ProcessingMode::Rate, vec![])],
+                PenetrationSystemType::NONE,
+            ),
+            toy_feature(
+                3,
+                vec![toy_parameter(level, ProcessingMode::Constant, vec![])],
+                PenetrationSystemType::NONE,
+            ),
+            toy_feature(
+                4,
+                vec![toy_parameter(
+                    gesture,
+                    ProcessingMode::Raw,
+                    vec![
+                        IntValueMapping {
+                            value: 0,
+                            level: 0.0,
+                        },
+                        IntValueMapping {
+                            value: 1,
+                            level: 0.5,
+                        },
+                    ],
+                )],
+                PenetrationSystemType::NONE,
+            ),
+            toy_feature(
+                5,
+                vec![toy_parameter(gesture, ProcessingMode::Constant, vec![])],
+                PenetrationSystemType::NONE,
+            ),
+            toy_feature(6, vec![], PenetrationSystemType::TPS),
+            toy_feature(7, vec![], PenetrationSystemType::SPS),
+        ];
+        features[1].flip_input_float = true;
+        features[2].feature_levels.minimum_level = 0.2;
+        features[2].feature_levels.maximum_level = 0.8;
+        features[3].feature_levels.idle_level = 0.1;
+
+        let mut features = VCToyFeatures { features };
+        features.init_penetration_systems();
+        features
+    }
+
+    /*
+     * Everything ingress accepts must make it through the toy dispatch, the mode processors and command_toy
+     */
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fuzz_dispatch_to_dry_run_toy() {
+        let toy_name = "dispatch fuzz toy";
+        set_dry_run_enabled(true);
+        // Every command has to reach the dry-run record
+        TOY_RATE_LIMITER.update_rate(u64::MAX);
+
+        // The API and avatar changes need the app state, API parsing is fuzzed on its own
+        let msgs: Vec<VCOscMessage> = (1..=64)
+            .flat_map(|seed| random_datagrams(seed, 256))
+            .filter_map(|raw| decode_packet(&raw))
+            .flat_map(ingress_packet)
+            .map(|timed| timed.msg)
+            .filter(|msg| msg.addr != AVATAR_CHANGE_ADDR && !msg.addr.starts_with(API_PREFIX))
+            .collect();
+        assert!(!msgs.is_empty());
+
+        let (toy_bcst_tx, toy_bcst_rx) = sync::broadcast::channel(msgs.len());
+        set_dispatch_tx(Some(toy_bcst_tx));
+        let routine = tokio::spawn(offline_toy_routine(
+            toy_name.to_string(),
+            toy_bcst_rx,
+            fuzz_features(),
+        ));
+
+        for msg in msgs {
+            assert!(dispatch_toy_message(msg));
+        }
+        // Dropping the sender ends the routine once it handled every message
+        set_dispatch_tx(None);
+        assert!(routine.await.is_ok(), "toy routine panicked");
+
+        let commands = take_test_commands(toy_name);
+        assert!(!commands.is_empty());
+        for command in commands {
+            assert!(
+                (0.0..=1.0).contains(&command.level),
+                "feature {} got level {}",
+                command.feature_index,
+                command.level
+            );
+        }
+    }
+
+    /*
+     * Int parameters go through the value table on the raw path, without one they are on/off
+     */
+    #[tokio::test]
+    async fn int_parameters_on_the_raw_path() {
+        let toy_name = "int raw path toy";
+        set_dry_run_enabled(true);
+        TOY_RATE_LIMITER.update_rate(u64::MAX);
+
+        let gesture = "/avatar/parameters/int/gesture";
+        let mut features = VCToyFeatures {
+            features: vec![
+                toy_feature(
+                    0,
+                    vec![toy_parameter(
+                        gesture,
+                        ProcessingMode::Raw,
+                        vec![
+                            IntValueMapping {
+                                value: 2,
+                                level: 0.4,
+                            },
+                            IntValueMapping {
+                                value: 3,
+                                level: 1.0,
+                            },
+                        ],
+                    )],
+                    PenetrationSystemType::NONE,
+                ),
+                toy_feature(
+                    1,
+                    vec![toy_parameter(gesture, ProcessingMode::Raw, vec![])],
+                    PenetrationSystemType::NONE,
+                ),
+            ],
+        };
+        features.features[1].feature_levels.maximum_level = 0.8;
+
+        let target = ToyCommandTarget::Offline(Arc::from(toy_name));
+        for value in [2, 3, 7, 0] {
+            let msg = VCOscMessage {
+                addr: gesture.to_string(),
+                value: ModeProcessorInputType::Int(value),
+            };
+            parse_osc_message(&msg, target.clone(), &mut features).await;
+        }
+
+        let levels: Vec<(u32, f64)> = take_test_commands(toy_name)
+            .iter()
+            .map(|command| (command.feature_index, command.level))
+            .collect();
+        assert_eq!(
+            levels,
+            [
+                (0, 0.4),
+                (1, 0.8),
+                (0, 1.0),
+                (1, 0.8),
+                // Unmapped value stops the mapped feature
+                (0, 0.0),
+                (1, 0.8),
+                (0, 0.0),
+                (1, 0.0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_commands_are_recorded() {
+        let toy_name = "offline command toy";
+        set_dry_run_enabled(true);
+        TOY_RATE_LIMITER.update_rate(u64::MAX);
+
+        let levels = LevelTweaks {
+            minimum_level: 0.2,
+            maximum_level: 0.8,
+            idle_level: 0.1,
+            ..Default::default()
+        };
+        let target = ToyCommandTarget::Offline(Arc::from(toy_name));
+        for (feature_type, level, feature_index, flip) in [
+            (VCFeatureType::Vibrator, 0.5, 0, false),
+            (VCFeatureType::Vibrator, 1.0, 0, false),
+            (VCFeatureType::Vibrator, 0.0, 0, false),
+            (VCFeatureType::Rotator, 0.3, 2, true),
+        ] {
+            command_toy(
+                target.clone(),
+                feature_type,
+                level,
+                feature_index,
+                flip,
+                levels,
+                None,
+            )
+            .await;
+        }
+
+        let commands = take_test_commands(toy_name);
+        let recorded: Vec<(u32, &str, f64)> = commands
+            .iter()
+            .map(|c| (c.feature_index, c.actuator.as_str(), c.level))
+            .collect();
+        // Levels are recorded after clamping, idle and flip
+        assert_eq!(
+            recorded,
+            [
+                (0, "Vibrate", 0.5),
+                (0, "Vibrate", 0.8),
+                (0, "Vibrate", 0.1),
+                (2, "Rotate", 0.7),
+            ]
+        );
+        assert!(commands.iter().all(|c| c.toy_id.is_none()));
+        assert_eq!(commands[3].feature_type, FeVCFeatureType::Rotator);
+    }
+
+    #[test]
+    fn offline_toys_record_commands_from_saved_configs() {
+        set_dry_run_enabled(true);
+        TOY_RATE_LIMITER.update_rate(u64::MAX);
+
+        let level = "/avatar/parameters/offline/level";
+        let config = |toy_name: &str, feature_index: u32| VCToyConfig {
+            toy_name: toy_name.to_string(),
+            features: VCToyFeatures {
+                features: vec![toy_feature(
+                    feature_index,
+                    vec![toy_parameter(level, ProcessingMode::Raw, vec![])],
+                    PenetrationSystemType::NONE,
+                )],
+            },
+            osc_data: false,
+            anatomy: Default::default(),
+        };
+        let configs = [config("offline toy a", 0), config("offline toy b", 1)];
+
+        let toy_async_rt = Runtime::new().unwrap();
+        let (toy_bcst_tx, _toy_bcst_rx) = sync::broadcast::channel(16);
+        let mut offline_toy_ths = HashMap::new();
+        spawn_offline_configs(
+            configs.iter(),
+            &toy_async_rt,
+            &toy_bcst_tx,
+            &mut offline_toy_ths,
+        );
+        // Toys that are already listening are not spawned twice
+        spawn_offline_configs(
+            configs.iter(),
+            &toy_async_rt,
+            &toy_bcst_tx,
+            &mut offline_toy_ths,
+        );
+        assert_eq!(offline_toy_ths.len(), 2);
+        assert_eq!(toy_bcst_tx.receiver_count(), 3);
+
+        toy_bcst_tx
+            .send(ToySig::OSCMsg(VCOscMessage {
+                addr: level.to_string(),
+                value: ModeProcessorInputType::Float(0.6),
+            }))
+            .unwrap_or_else(|_| panic!("no offline toy is listening"));
+        // The routines end once every message was handled
+        drop(toy_bcst_tx);
+        toy_async_rt.block_on(async {
+            for (_, th) in offline_toy_ths.drain() {
+                assert!(th.await.is_ok(), "offline toy routine panicked");
+            }
+        });
+
+        for (toy_name, feature_index) in [("offline toy a", 0), ("offline toy b", 1)] {
+            let recorded: Vec<(u32, f64)> = take_test_commands(toy_name)
+                .iter()
+                .map(|c| (c.feature_index, c.level))
+                .collect();
+            assert_eq!(recorded, [(feature_index, 0.6)]);
+        }
+    }
+}
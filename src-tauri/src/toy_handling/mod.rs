@@ -3,6 +3,8 @@ use ts_rs::TS;
 
 use self::toyops::{ProcessingModeValues, ToyParameter};
 
+pub mod battery;
+pub mod dry_run;
 pub mod errors;
 pub mod handling;
 pub mod input_processor;
@@ -24,7 +26,7 @@ pub enum RateParser {
 pub enum ToySig {
     //ToyCommand(ToyFeature),
     UpdateToy(crate::vcore::core::ToyUpdate),
-    OSCMsg(rosc::OscMessage),
+    OSCMsg(crate::osc::ingress::VCOscMessage),
 }
 
 pub enum ModeProcessorInput<'processor> {
@@ -33,9 +35,11 @@ pub enum ModeProcessorInput<'processor> {
 }
 
 #[derive(Debug, Clone, TS, Serialize, Deserialize, Copy)]
+#[ts(export)]
 pub enum ModeProcessorInputType {
     Float(f64),
     Boolean(bool),
+    Int(i32),
 }
 
 impl ModeProcessorInputType {
@@ -52,6 +56,13 @@ impl ModeProcessorInputType {
             _ => None,
         }
     }
+
+    pub fn try_int(&self) -> Option<i32> {
+        match self {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, TS, Clone)]
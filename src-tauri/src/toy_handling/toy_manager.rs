@@ -63,12 +63,13 @@ impl ToyManager {
                 Err(_e) => continue,
             };
 
-            let config: VCToyConfig = match serde_json::from_str(&con) {
+            let mut config: VCToyConfig = match serde_json::from_str(&con) {
                 Ok(vc_toy_config) => vc_toy_config,
                 Err(_) => {
                     continue;
                 }
             };
+            config.sanitize();
 
             trace!(
                 "Loaded & parsed toy config [{}] successfully!",
@@ -92,7 +93,7 @@ impl ToyManager {
         self.fetoy_vec_from_offline_toys()
     }
 
-    fn check_toy_online(&self, config_toy_name: &String) -> bool {
+    pub fn check_toy_online(&self, config_toy_name: &String) -> bool {
         for online_toy in self.online_toys.iter() {
             if *config_toy_name == online_toy.1.toy_name.replace("Lovense Connect", "Lovense") {
                 return true;
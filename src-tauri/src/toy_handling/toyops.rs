@@ -25,7 +25,7 @@ use crate::{
 
 use crate::toy_handling::input_processor::penetration_systems::PenetrationSystem;
 
-use super::ToyPower;
+use super::{ModeProcessorInputType, ToyPower};
 
 #[derive(Clone, Debug)]
 pub struct VCToy {
@@ -61,6 +61,7 @@ impl VCToy {
                                 indexer
                             ),
                             processing_mode: ProcessingMode::Raw,
+                            int_values: Vec::new(),
                             processing_mode_values: ProcessingModeValues::default(),
                         }],
                         indexer,
@@ -254,24 +255,7 @@ impl VCToy {
                 self.parsed_toy_features = conf.features.clone();
 
                 // Allocate / Instantiate new Penetration system structure based on configuration data
-                for feature in &mut self.parsed_toy_features.features {
-                    match feature.penetration_system.pen_system_type {
-                        PenetrationSystemType::NONE => feature.penetration_system.pen_system = None,
-                        PenetrationSystemType::SPS => {
-                            feature.penetration_system.pen_system =
-                                Some(Box::<SPSProcessor>::default())
-                        }
-                        PenetrationSystemType::TPS => {
-                            feature.penetration_system.pen_system =
-                                Some(Box::<TPSProcessor>::default())
-                        }
-                    }
-
-                    feature.penetration_system.pen_system_processing_mode_values =
-                        ProcessingModeValues::new_from(
-                            &feature.penetration_system.pen_system_processing_mode,
-                        );
-                }
+                self.parsed_toy_features.init_penetration_systems();
 
                 self.osc_data = conf.osc_data;
                 info!("Populated toy with loaded config from file!");
@@ -299,13 +283,14 @@ impl VCToy {
         } else {
             let con = fs::read_to_string(config_path).unwrap();
 
-            let config: VCToyConfig = match serde_json::from_str(&con) {
+            let mut config: VCToyConfig = match serde_json::from_str(&con) {
                 Ok(vc_toy_config) => vc_toy_config,
                 Err(_) => {
                     self.config = None;
                     return Err(vcerror::backend::VibeCheckToyConfigError::DeserializeError);
                 }
             };
+            config.sanitize();
             debug!("Loaded & parsed toy config successfully!");
             self.config = Some(config);
             Ok(())
@@ -412,10 +397,24 @@ impl ToFrontend<FeProcessingMode> for ProcessingMode {
     }
 }
 
+/*
+ * Maps a discrete int parameter value to a feature level
+ * eg. GestureLeft 2 => 0.4, GestureLeft 3 => 1.0
+ */
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, TS, PartialEq)]
+#[ts(export)]
+pub struct IntValueMapping {
+    pub value: i32,
+    pub level: f64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, TS)]
 pub struct ToyParameter {
     pub parameter: String,
     pub processing_mode: ProcessingMode,
+    // Value table for int parameters (Empty = int treated as on/off)
+    #[serde(default)]
+    pub int_values: Vec<IntValueMapping>,
     // Temporary values for calculations for Processing Modes
     #[serde(skip)]
     pub processing_mode_values: ProcessingModeValues,
@@ -425,6 +424,24 @@ impl ToyParameter {
     fn is_assigned_param(&self, param: &String) -> bool {
         self.parameter == *param
     }
+
+    /*
+     * Resolves an int input through the parameter's value table
+     * Unmapped values stop the feature
+     * Without a value table the int is passed on as is
+     */
+    pub fn resolve_int(&self, value: i32) -> ModeProcessorInputType {
+        if self.int_values.is_empty() {
+            return ModeProcessorInputType::Int(value);
+        }
+
+        let level = self
+            .int_values
+            .iter()
+            .find(|mapping| mapping.value == value)
+            .map_or(0.0, |mapping| mapping.level);
+        ModeProcessorInputType::Float(level)
+    }
 }
 
 impl ToFrontend<Vec<FeToyParameter>> for Vec<ToyParameter> {
@@ -437,6 +454,7 @@ impl ToFrontend<Vec<FeToyParameter>> for Vec<ToyParameter> {
             out.push(FeToyParameter {
                 parameter: tp.parameter.clone(),
                 processing_mode: tp.processing_mode.to_frontend(),
+                int_values: tp.int_values.clone(),
             });
         }
 
@@ -474,6 +492,9 @@ pub struct VCToyFeature {
     pub smooth_enabled: bool,
     // Rate mode enabled (This will be removed with multi-param update)
     pub rate_enabled: bool,
+    // OSC address the final feature level is sent to as feedback for the avatar
+    #[serde(default)]
+    pub output_parameter: Option<String>,
 }
 
 impl VCToyFeature {
@@ -492,6 +513,7 @@ impl VCToyFeature {
             feature_levels: LevelTweaks::default(),
             smooth_enabled: true,
             rate_enabled: false,
+            output_parameter: None,
             //processor: FeatureProcessor::default(),
         }
     }
@@ -543,6 +565,9 @@ impl FromFrontend<FeVCToyFeature> for VCToyFeature {
         self.feature_levels.from_fe(frontend_type.feature_levels);
         self.smooth_enabled = frontend_type.smooth_enabled;
         self.rate_enabled = frontend_type.rate_enabled;
+        self.output_parameter = frontend_type
+            .output_parameter
+            .filter(|p| !p.trim().is_empty());
         true
     }
 }
@@ -559,6 +584,7 @@ impl FromFrontend<Vec<FeToyParameter>> for Vec<ToyParameter> {
             self.push(ToyParameter {
                 parameter: toy_param.parameter,
                 processing_mode: toy_param.processing_mode.to_backend(),
+                int_values: toy_param.int_values,
                 processing_mode_values: ProcessingModeValues::new_from(
                     &toy_param.processing_mode.to_backend(),
                 ),
@@ -581,6 +607,7 @@ impl ToFrontend<HashMap<String, FeToyParameter>> for HashMap<String, ToyParamete
                 FeToyParameter {
                     parameter: p.clone(),
                     processing_mode: tp.processing_mode.to_frontend(),
+                    int_values: tp.int_values.clone(),
                 },
             );
         }
@@ -624,7 +651,7 @@ impl VCFeatureType {
         }
     }
 
-    fn to_fe(&self) -> FeVCFeatureType {
+    pub fn to_fe(&self) -> FeVCFeatureType {
         match self {
             VCFeatureType::Constrict => FeVCFeatureType::Constrict,
             VCFeatureType::Inflate => FeVCFeatureType::Inflate,
@@ -648,6 +675,24 @@ pub struct ToyConfig {
     If no config put toy to Auto params
 */
 
+// Ranges of the level tweak sliders in the feature form
+pub const LEVEL_RANGE: (f64, f64) = (0.0, 1.0);
+pub const SMOOTH_RATE_RANGE: (f64, f64) = (1.0, 20.0);
+pub const LINEAR_POSITION_SPEED_RANGE: (u32, u32) = (10, 1000);
+pub const RATE_TUNE_RANGE: (f64, f64) = (0.1, 2.0);
+pub const CONSTANT_LEVEL_RANGE: (f64, f64) = (0.01, 1.0);
+
+/*
+ * Clamps a level into a range, NaN becomes the minimum
+ */
+pub fn clamp_level(value: f64, (min, max): (f64, f64)) -> f64 {
+    if value.is_nan() {
+        min
+    } else {
+        value.clamp(min, max)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Copy, TS)]
 pub struct LevelTweaks {
     pub minimum_level: f64,
@@ -684,6 +729,26 @@ impl LevelTweaks {
         self.constant_level = fe_lt.constant_level
     }
 
+    /*
+     * Clamps levels from older or hand edited configs into the ranges the frontend allows
+     * Returns true when a level was changed
+     */
+    pub fn clamp_to_ranges(&mut self) -> bool {
+        let before = *self;
+        self.minimum_level = clamp_level(self.minimum_level, LEVEL_RANGE);
+        self.maximum_level = clamp_level(self.maximum_level, LEVEL_RANGE);
+        self.minimum_level = self.minimum_level.min(self.maximum_level);
+        self.idle_level = clamp_level(self.idle_level, LEVEL_RANGE);
+        self.smooth_rate = clamp_level(self.smooth_rate, SMOOTH_RATE_RANGE);
+        self.linear_position_speed = self
+            .linear_position_speed
+            .clamp(LINEAR_POSITION_SPEED_RANGE.0, LINEAR_POSITION_SPEED_RANGE.1);
+        self.rate_tune = clamp_level(self.rate_tune, RATE_TUNE_RANGE);
+        self.constant_level = clamp_level(self.constant_level, CONSTANT_LEVEL_RANGE);
+        // NaN levels never compare equal so they always count as changed
+        *self != before
+    }
+
     pub fn to_fe(&self) -> FeLevelTweaks {
         FeLevelTweaks {
             minimum_level: self.minimum_level,
@@ -790,6 +855,29 @@ impl VCToyFeatures {
         }
     }
 
+    /*
+     * Instantiates the penetration systems of features loaded from a config
+     * The processors are not serialized so only their type is known after loading
+     */
+    pub fn init_penetration_systems(&mut self) {
+        for feature in &mut self.features {
+            match feature.penetration_system.pen_system_type {
+                PenetrationSystemType::NONE => feature.penetration_system.pen_system = None,
+                PenetrationSystemType::SPS => {
+                    feature.penetration_system.pen_system = Some(Box::<SPSProcessor>::default())
+                }
+                PenetrationSystemType::TPS => {
+                    feature.penetration_system.pen_system = Some(Box::<TPSProcessor>::default())
+                }
+            }
+
+            feature.penetration_system.pen_system_processing_mode_values =
+                ProcessingModeValues::new_from(
+                    &feature.penetration_system.pen_system_processing_mode,
+                );
+        }
+    }
+
     pub fn get_features_with_input_processors(
         &mut self,
         param: &String,
@@ -888,6 +976,7 @@ impl ToFrontend<Vec<FeVCToyFeature>> for Vec<VCToyFeature> {
                 feature_levels: f.feature_levels.to_fe(),
                 smooth_enabled: f.smooth_enabled,
                 rate_enabled: f.rate_enabled,
+                output_parameter: f.output_parameter.clone(),
             });
         });
         fe_features
@@ -926,3 +1015,61 @@ impl FromFrontend<FeVCToyFeature> for VCToyFeatures {
         success
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gesture_parameter(int_values: Vec<IntValueMapping>) -> ToyParameter {
+        ToyParameter {
+            parameter: "/avatar/parameters/GestureLeft".to_string(),
+            processing_mode: ProcessingMode::Raw,
+            int_values,
+            processing_mode_values: ProcessingModeValues::Raw,
+        }
+    }
+
+    #[test]
+    fn resolves_mapped_ints_to_levels() {
+        let param = gesture_parameter(vec![
+            IntValueMapping {
+                value: 2,
+                level: 0.4,
+            },
+            IntValueMapping {
+                value: 3,
+                level: 1.0,
+            },
+        ]);
+
+        assert!(matches!(param.resolve_int(2), ModeProcessorInputType::Float(l) if l == 0.4));
+        assert!(matches!(param.resolve_int(3), ModeProcessorInputType::Float(l) if l == 1.0));
+    }
+
+    #[test]
+    fn unmapped_ints_stop_the_feature() {
+        let param = gesture_parameter(vec![IntValueMapping {
+            value: 2,
+            level: 0.4,
+        }]);
+
+        for value in [0, 1, 7, -1, i32::MIN, i32::MAX] {
+            assert!(
+                matches!(param.resolve_int(value), ModeProcessorInputType::Float(l) if l == 0.0),
+                "{} resolved to a level",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn ints_pass_through_without_a_value_table() {
+        let param = gesture_parameter(vec![]);
+
+        for value in [0, 2, -1, i32::MAX] {
+            assert!(
+                matches!(param.resolve_int(value), ModeProcessorInputType::Int(i) if i == value)
+            );
+        }
+    }
+}
@@ -18,7 +18,7 @@ where
 
 /*
  * Old method for config path
- * Still used for clearing OSC avatar configs
+ * Still used for the VRChat OSC avatar configs
 */
 pub fn get_user_home_dir() -> String {
     let bd = BaseDirs::new().expect("[-] Could not get user's directories.");
@@ -29,6 +29,16 @@ pub fn get_user_home_dir() -> String {
     bd.to_string()
 }
 
+/*
+ * Where VRChat keeps the per user OSC avatar configs (usr_*)
+ */
+pub fn get_vrchat_osc_dir() -> String {
+    format!(
+        "{}\\AppData\\LocalLow\\VRChat\\VRChat\\OSC\\",
+        get_user_home_dir()
+    )
+}
+
 pub fn get_config_dir() -> String {
     let context_gen = tauri::generate_context!();
     resolve_path(
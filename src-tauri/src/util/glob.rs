@@ -0,0 +1,78 @@
+/*
+ * Minimal glob matching for OSC addresses
+ * '*' matches any run of characters (including '/') and '?' matches a single character
+ */
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last '*' in the pattern and the text position it is matched against
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last '*' swallow one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    // Trailing '*'s match the empty remainder
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob_match("/avatar/parameters/*", "/avatar/parameters/"));
+        assert!(glob_match(
+            "/avatar/parameters/*",
+            "/avatar/parameters/VRCFT/JawOpen"
+        ));
+        assert!(glob_match("*/JawOpen", "/avatar/parameters/VRCFT/JawOpen"));
+        assert!(glob_match(
+            "/avatar/*/Jaw*",
+            "/avatar/parameters/VRCFT/JawOpen"
+        ));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("/avatar/parameters/*", "/avatar/change"));
+        assert!(!glob_match("*/JawOpen", "/avatar/parameters/JawOpenX"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob_match("/input/Jump?", "/input/Jump1"));
+        assert!(glob_match("/input/???", "/input/abc"));
+        assert!(!glob_match("/input/Jump?", "/input/Jump"));
+        assert!(!glob_match("/input/Jump?", "/input/Jump12"));
+        assert!(!glob_match("/input/???", "/input/ab"));
+    }
+
+    #[test]
+    fn patterns_are_anchored() {
+        assert!(glob_match("/avatar/change", "/avatar/change"));
+        assert!(!glob_match("/avatar/change", "/avatar/changed"));
+        assert!(!glob_match("/avatar/change", "x/avatar/change"));
+        assert!(!glob_match("avatar", "/avatar/change"));
+    }
+
+    #[test]
+    fn empty_pattern_only_matches_empty_text() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "/avatar/change"));
+        assert!(glob_match("*", ""));
+    }
+}
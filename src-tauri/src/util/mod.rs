@@ -1,3 +1,5 @@
 pub mod bluetooth;
 pub mod fs;
+pub mod glob;
 pub mod net;
+pub mod stoppable;
@@ -1,4 +1,9 @@
-use std::net::{TcpListener, UdpSocket};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, ToSocketAddrs, UdpSocket},
+    str::FromStr,
+};
 
 pub enum InterfaceL4Proto {
     UDP(String),
@@ -21,3 +26,368 @@ pub fn find_available_udp_port(interface_addr: String) -> Option<u16> {
     (10000..11000)
         .find(|port| is_port_available(InterfaceL4Proto::UDP(interface_addr.to_owned()), *port))
 }
+
+/*
+ * A host (IP literal or resolvable hostname) and a port
+ * Kept as written so hostnames are resolved again every time they are used.
+ * Serialized as "host:port" ("[v6]:port" for IPv6 literals) which is also how IPv4 socket addresses were stored.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HostPort {
+    host: String,
+    port: u16,
+}
+
+#[derive(Debug)]
+pub struct InvalidHostPort;
+
+impl fmt::Display for InvalidHostPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected host:port or [ipv6]:port")
+    }
+}
+
+impl HostPort {
+    pub fn new(host: &str, port: u16) -> Result<Self, InvalidHostPort> {
+        let host = host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(host);
+
+        if !is_valid_host(host) {
+            return Err(InvalidHostPort);
+        }
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /*
+     * The address when the host is an IP literal (no resolution needed)
+     */
+    pub fn literal(&self) -> Option<SocketAddr> {
+        IpAddr::from_str(&self.host)
+            .ok()
+            .map(|ip| SocketAddr::new(ip, self.port))
+    }
+
+    /*
+     * Resolves the host, preferring the first address returned
+     */
+    pub fn resolve(&self) -> io::Result<SocketAddr> {
+        if let Some(addr) = self.literal() {
+            return Ok(addr);
+        }
+
+        (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Host has no addresses"))
+    }
+
+    pub async fn resolve_async(&self) -> io::Result<SocketAddr> {
+        if let Some(addr) = self.literal() {
+            return Ok(addr);
+        }
+
+        tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Host has no addresses"))
+    }
+}
+
+impl FromStr for HostPort {
+    type Err = InvalidHostPort;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, port) = s.rsplit_once(':').ok_or(InvalidHostPort)?;
+
+        // Unbracketed IPv6 literals are ambiguous with the port separator
+        if host.contains(':') && !host.starts_with('[') {
+            return Err(InvalidHostPort);
+        }
+
+        let port = port.parse::<u16>().map_err(|_| InvalidHostPort)?;
+        Self::new(host, port)
+    }
+}
+
+impl TryFrom<String> for HostPort {
+    type Error = InvalidHostPort;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_str(&s)
+    }
+}
+
+impl From<HostPort> for String {
+    fn from(hp: HostPort) -> Self {
+        hp.to_string()
+    }
+}
+
+impl From<SocketAddr> for HostPort {
+    fn from(addr: SocketAddr) -> Self {
+        Self {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+        }
+    }
+}
+
+impl fmt::Display for HostPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/*
+ * IP literal or a DNS hostname (letters, digits, '-' and '.' labels)
+ */
+pub fn is_valid_host(host: &str) -> bool {
+    if IpAddr::from_str(host).is_ok() {
+        return true;
+    }
+
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/*
+ * Unwraps IPv4-mapped IPv6 addresses (sources on dual-stack sockets) to IPv4
+ */
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/*
+ * An IP network in CIDR notation, a plain IP is a single address network
+ * Serialized as written so allowlists stored as plain IPs keep loading.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+#[derive(Debug)]
+pub struct InvalidIpCidr;
+
+impl fmt::Display for InvalidIpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected ip or ip/prefix")
+    }
+}
+
+impl IpCidr {
+    fn max_prefix_len(addr: IpAddr) -> u8 {
+        match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (canonical_ip(self.addr), canonical_ip(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = InvalidIpCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.trim().split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s.trim(), None),
+        };
+        let written = IpAddr::from_str(addr).map_err(|_| InvalidIpCidr)?;
+        let addr = canonical_ip(written);
+
+        // IPv4-mapped networks are unwrapped so their prefix loses the 96 mapping bits
+        let mapped_bits = if written.is_ipv6() && addr.is_ipv4() {
+            96
+        } else {
+            0
+        };
+        let prefix_len = match prefix_len {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .and_then(|p| p.checked_sub(mapped_bits))
+                .ok_or(InvalidIpCidr)?,
+            None => Self::max_prefix_len(addr),
+        };
+        if prefix_len > Self::max_prefix_len(addr) {
+            return Err(InvalidIpCidr);
+        }
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl TryFrom<String> for IpCidr {
+    type Error = InvalidIpCidr;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_str(&s)
+    }
+}
+
+impl From<IpCidr> for String {
+    fn from(cidr: IpCidr) -> Self {
+        cidr.to_string()
+    }
+}
+
+impl From<IpAddr> for IpCidr {
+    fn from(addr: IpAddr) -> Self {
+        let addr = canonical_ip(addr);
+        Self {
+            addr,
+            prefix_len: Self::max_prefix_len(addr),
+        }
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix_len == Self::max_prefix_len(self.addr) {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix_len)
+        }
+    }
+}
+
+/*
+ * Whether an IP belongs to this machine
+ * Binding to an address only succeeds for local addresses
+ */
+pub fn is_local_ip(ip: IpAddr) -> bool {
+    ip.is_loopback() || ip.is_unspecified() || UdpSocket::bind(SocketAddr::new(ip, 0)).is_ok()
+}
+
+/*
+ * Whether packets sent to target arrive on a socket bound to bind
+ * Unspecified binds receive on every local address of the machine
+ */
+pub fn reaches_bind(target: SocketAddr, bind: SocketAddr) -> bool {
+    if target.port() != bind.port() {
+        return false;
+    }
+    let target_ip = canonical_ip(target.ip());
+    let bind_ip = canonical_ip(bind.ip());
+
+    if bind_ip.is_unspecified() {
+        is_local_ip(target_ip)
+    } else {
+        target_ip == bind_ip || target_ip.is_unspecified()
+    }
+}
+
+/*
+ * Unspecified local address of the same family to send to target from
+ */
+pub fn sender_bind_addr(target: &SocketAddr) -> SocketAddr {
+    match target {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        SocketAddr::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn specific_binds_only_match_their_address() {
+        let bind = addr("127.0.0.1:9001");
+        assert!(reaches_bind(addr("127.0.0.1:9001"), bind));
+        assert!(reaches_bind(addr("0.0.0.0:9001"), bind));
+        assert!(reaches_bind(addr("[::ffff:127.0.0.1]:9001"), bind));
+        assert!(!reaches_bind(addr("127.0.0.1:9002"), bind));
+        assert!(!reaches_bind(addr("127.0.0.2:9001"), bind));
+    }
+
+    #[test]
+    fn unspecified_binds_match_local_addresses() {
+        for bind in [addr("0.0.0.0:9001"), addr("[::]:9001")] {
+            assert!(reaches_bind(addr("127.0.0.1:9001"), bind));
+            assert!(reaches_bind(addr("127.0.0.5:9001"), bind));
+            assert!(reaches_bind(addr("[::1]:9001"), bind));
+            assert!(!reaches_bind(addr("127.0.0.1:9000"), bind));
+            // TEST-NET-3, never assigned to a local interface
+            assert!(!reaches_bind(addr("203.0.113.7:9001"), bind));
+        }
+    }
+
+    #[test]
+    fn parses_cidr_networks() {
+        let net = IpCidr::from_str("10.0.0.0/8").unwrap();
+        assert!(net.contains("10.1.2.3".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert_eq!(net.to_string(), "10.0.0.0/8");
+        assert_eq!(IpCidr::from_str("::1").unwrap().to_string(), "::1");
+        assert!(IpCidr::from_str("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn parses_host_ports() {
+        assert_eq!(
+            HostPort::from_str("[::1]:9000").unwrap().literal(),
+            Some(addr("[::1]:9000"))
+        );
+        assert_eq!(
+            HostPort::from_str("localhost:9000").unwrap().to_string(),
+            "localhost:9000"
+        );
+        assert!(HostPort::from_str("::1:9000").is_err());
+        assert!(HostPort::from_str("bad host:9000").is_err());
+    }
+}
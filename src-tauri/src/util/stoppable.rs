@@ -0,0 +1,162 @@
+/*
+ * Single-instance background threads that can be stopped from another thread
+ * Used by OSC replay and injection, only one run of each may be active at a time
+ */
+use parking_lot::{const_mutex, Mutex};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+// Longest uninterrupted sleep, so a stop request is noticed quickly
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone)]
+pub struct StopFlag(Arc<AtomicBool>);
+
+impl StopFlag {
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicBool::new(false)))
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /*
+     * Sleeps for duration in short steps
+     * Returns false if a stop was requested before or during the sleep
+     */
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let mut remaining = duration;
+        while !remaining.is_zero() {
+            if self.is_stopped() {
+                return false;
+            }
+            let step = remaining.min(STOP_POLL_INTERVAL);
+            thread::sleep(step);
+            remaining -= step;
+        }
+        !self.is_stopped()
+    }
+}
+
+impl Default for StopFlag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct StoppableThread {
+    active: Mutex<Option<StopFlag>>,
+}
+
+impl StoppableThread {
+    pub const fn new() -> Self {
+        Self {
+            active: const_mutex(None),
+        }
+    }
+
+    /*
+     * Runs f on a new thread with its stop flag
+     * Returns false without running f if a previous run is still active
+     */
+    pub fn start<F>(&'static self, f: F) -> bool
+    where
+        F: FnOnce(&StopFlag) + Send + 'static,
+    {
+        let stop = {
+            let mut active = self.active.lock();
+            if active.is_some() {
+                return false;
+            }
+            let stop = StopFlag::new();
+            *active = Some(stop.clone());
+            stop
+        };
+
+        thread::spawn(move || {
+            f(&stop);
+
+            // A stopped run may already have been replaced by a new one
+            let mut active = self.active.lock();
+            if active.as_ref().is_some_and(|a| Arc::ptr_eq(&a.0, &stop.0)) {
+                *active = None;
+            }
+        });
+
+        true
+    }
+
+    /*
+     * Returns false if nothing was running
+     */
+    pub fn stop(&self) -> bool {
+        match self.active.lock().take() {
+            Some(stop) => {
+                stop.stop();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for StoppableThread {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time::Instant};
+
+    #[test]
+    fn sleep_runs_to_completion() {
+        let stop = StopFlag::new();
+        let started = Instant::now();
+        assert!(stop.sleep(Duration::from_millis(150)));
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn sleep_is_interrupted() {
+        let stop = StopFlag::new();
+        let remote = stop.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            remote.stop();
+        });
+
+        let started = Instant::now();
+        assert!(!stop.sleep(Duration::from_secs(10)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_one_run_at_a_time() {
+        static TASK: StoppableThread = StoppableThread::new();
+        let (tx, rx) = mpsc::channel();
+
+        assert!(TASK.start(move |stop| {
+            stop.sleep(Duration::from_secs(10));
+            tx.send(()).unwrap();
+        }));
+        assert!(!TASK.start(|_| {}));
+
+        assert!(TASK.stop());
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(!TASK.stop());
+    }
+}
@@ -1,38 +1,252 @@
 use log::{error as logerr, info, trace, warn};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{
+    collections::BTreeMap,
     fs,
-    net::{Ipv4Addr, SocketAddrV4},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
 };
 
 use crate::{
-    frontend::frontend_types::FeOSCNetworking,
-    util::fs::{file_exists, get_config_dir, path_exists},
+    frontend::frontend_types::{
+        FeOSCListener, FeOSCNetworking, FeOSCRepeaterTarget, FeTelemetryConfig, FeTelemetryTarget,
+    },
+    osc::{
+        chatbox::ChatboxConfig,
+        repeater::OSCAddressRewrite,
+        tcp::OSCTransport,
+        telemetry::{TelemetryField, DEFAULT_ADDRESS_TEMPLATE},
+    },
+    toy_handling::battery::BatteryAlertConfig,
+    util::{
+        fs::{file_exists, get_config_dir, path_exists},
+        net::{canonical_ip, HostPort, IpCidr},
+    },
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OSCNetworking {
-    pub bind: SocketAddrV4,
-    pub remote: SocketAddrV4,
+    #[serde(deserialize_with = "deserialize_listeners")]
+    pub bind: Vec<OSCListener>,
+    pub remote: HostPort,
+    // Transport for outbound osc_data
+    #[serde(default)]
+    pub remote_transport: OSCTransport,
     pub osc_query_enabled: bool,
+    #[serde(default)]
+    pub repeater: Vec<OSCRepeaterTarget>,
+    // Max sends per second of each feature output parameter
+    #[serde(default = "default_feedback_rate")]
+    pub feedback_rate: u64,
+}
+
+pub fn default_feedback_rate() -> u64 {
+    10
+}
+
+/*
+ * Downstream OSC app that received packets are forwarded to
+ * filter is an address glob, packets with no matching message are not forwarded
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct OSCRepeaterTarget {
+    pub name: String,
+    pub address: HostPort,
+    pub enabled: bool,
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub rewrite: Option<OSCAddressRewrite>,
+}
+
+impl OSCRepeaterTarget {
+    pub fn to_fe(&self) -> FeOSCRepeaterTarget {
+        FeOSCRepeaterTarget {
+            name: self.name.clone(),
+            address: self.address.to_string(),
+            enabled: self.enabled,
+            filter: self.filter.clone(),
+            rewrite: self.rewrite.clone(),
+        }
+    }
+}
+
+/*
+ * osc_data telemetry for toys with OSC data enabled
+ * targets default to the networking remote when empty
+ * aliases map toy names to the {alias} used in the address template
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TelemetryConfig {
+    pub interval_secs: u64,
+    #[serde(default)]
+    pub targets: Vec<TelemetryTarget>,
+    pub address_template: String,
+    pub fields: Vec<TelemetryField>,
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 15,
+            targets: Vec::new(),
+            address_template: DEFAULT_ADDRESS_TEMPLATE.to_string(),
+            fields: vec![TelemetryField::Battery],
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    pub fn to_fe(&self) -> FeTelemetryConfig {
+        FeTelemetryConfig {
+            interval_secs: self.interval_secs,
+            targets: self
+                .targets
+                .iter()
+                .map(|t| FeTelemetryTarget {
+                    address: t.address.to_string(),
+                    transport: t.transport,
+                })
+                .collect(),
+            address_template: self.address_template.clone(),
+            fields: self.fields.clone(),
+            aliases: self.aliases.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TelemetryTarget {
+    pub address: HostPort,
+    #[serde(default)]
+    pub transport: OSCTransport,
+}
+
+/*
+ * A named OSC input listener
+ * allowlist restricts the source IPs and networks accepted (empty allows all)
+ * api_secret has to prefix API addresses on listeners not bound to loopback
+ * rewrite replaces an address prefix of every received message before dispatch
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct OSCListener {
+    pub name: String,
+    pub address: SocketAddr,
+    #[serde(default)]
+    pub allowlist: Vec<IpCidr>,
+    #[serde(default)]
+    pub api_secret: Option<String>,
+    #[serde(default)]
+    pub rewrite: Option<OSCAddressRewrite>,
+    #[serde(default)]
+    pub transport: OSCTransport,
+}
+
+impl Default for OSCListener {
+    fn default() -> Self {
+        Self {
+            name: "VRChat".to_string(),
+            address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9001),
+            allowlist: Vec::new(),
+            api_secret: None,
+            rewrite: None,
+            transport: OSCTransport::Udp,
+        }
+    }
+}
+
+impl OSCListener {
+    pub fn allows(&self, source: IpAddr) -> bool {
+        self.allowlist.is_empty() || self.allowlist.iter().any(|net| net.contains(source))
+    }
+
+    /*
+     * The API secret when it is enforced
+     * Loopback binds only receive from this machine so they never need it
+     */
+    pub fn required_api_secret(&self) -> Option<&str> {
+        if canonical_ip(self.address.ip()).is_loopback() {
+            return None;
+        }
+        self.api_secret.as_deref()
+    }
+
+    pub fn to_fe(&self) -> FeOSCListener {
+        FeOSCListener {
+            name: self.name.clone(),
+            address: self.address.to_string(),
+            allowlist: self.allowlist.iter().map(|net| net.to_string()).collect(),
+            api_secret: self.api_secret.clone(),
+            rewrite: self.rewrite.clone(),
+            transport: self.transport,
+        }
+    }
+}
+
+/*
+ * Configs before multiple listeners stored bind as a single address
+ * Always yields at least one listener
+ */
+fn deserialize_listeners<'de, D>(deserializer: D) -> Result<Vec<OSCListener>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BindCompat {
+        Single(SocketAddr),
+        Listeners(Vec<OSCListener>),
+    }
+
+    Ok(match BindCompat::deserialize(deserializer)? {
+        BindCompat::Single(address) => vec![OSCListener {
+            address,
+            ..Default::default()
+        }],
+        BindCompat::Listeners(listeners) if listeners.is_empty() => {
+            vec![OSCListener::default()]
+        }
+        BindCompat::Listeners(listeners) => listeners,
+    })
 }
 
 impl Default for OSCNetworking {
     fn default() -> Self {
         Self {
-            bind: SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 9001),
-            remote: SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 9000),
+            bind: vec![OSCListener::default()],
+            remote: HostPort::from(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9000)),
+            remote_transport: OSCTransport::Udp,
             osc_query_enabled: true,
+            repeater: Vec::new(),
+            feedback_rate: default_feedback_rate(),
         }
     }
 }
 
 impl OSCNetworking {
+    /*
+     * The listener advertised through OSCQuery
+     * OSCQuery only advertises IPv4 so this is the first listener with an IPv4 (or IPv4-mapped) address
+     */
+    pub fn oscquery_listener_mut(&mut self) -> Option<(&mut OSCListener, Ipv4Addr)> {
+        self.bind
+            .iter_mut()
+            .find_map(|l| match canonical_ip(l.address.ip()) {
+                IpAddr::V4(ip) => Some((l, ip)),
+                IpAddr::V6(_) => None,
+            })
+    }
+
     pub fn to_fe(&self) -> FeOSCNetworking {
         FeOSCNetworking {
-            bind: self.bind.to_string(),
+            bind: self.bind.iter().map(|l| l.to_fe()).collect(),
             remote: self.remote.to_string(),
+            remote_transport: self.remote_transport,
             osc_query_enabled: self.osc_query_enabled,
+            repeater: self.repeater.iter().map(|t| t.to_fe()).collect(),
+            feedback_rate: self.feedback_rate,
         }
     }
 }
@@ -44,10 +258,25 @@ pub struct VibeCheckConfig {
     pub scan_on_disconnect: bool,
     pub minimize_on_exit: bool,
     pub desktop_notifications: bool,
-    pub lc_override: Option<Ipv4Addr>,
+    // Lovense Connect host (IP or hostname)
+    pub lc_override: Option<String>,
     pub show_toy_advanced: bool,
     pub show_feature_advanced: bool,
-    pub messages_per_second: u64
+    pub messages_per_second: u64,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub battery_alerts: BatteryAlertConfig,
+    #[serde(default)]
+    pub chatbox: ChatboxConfig,
+}
+
+/*
+ * Lovense Connect host with the forced port
+ * Port is forced because buttplug forces non http atm
+ */
+pub fn lc_host_port(host: &str) -> Option<HostPort> {
+    HostPort::new(host, 20010).ok()
 }
 
 pub fn config_load() -> VibeCheckConfig {
@@ -82,6 +311,9 @@ pub fn config_load() -> VibeCheckConfig {
                 show_toy_advanced: false,
                 show_feature_advanced: false,
                 messages_per_second: 10,
+                telemetry: TelemetryConfig::default(),
+                battery_alerts: BatteryAlertConfig::default(),
+                chatbox: ChatboxConfig::default(),
             })
             .unwrap(),
         )
@@ -95,9 +327,9 @@ pub fn config_load() -> VibeCheckConfig {
         Ok(fc) => match serde_json::from_str::<VibeCheckConfig>(&fc) {
             Ok(o) => {
                 info!("Config Loaded Successfully!");
-                if let Some(h) = o.lc_override {
-                    std::env::set_var("VCLC_HOST_PORT", format!("{}:20010", h).as_str());
-                    info!("Setting VCLC_HOST_PORT: {}", format!("{}:20010", h));
+                if let Some(lc_host) = o.lc_override.as_deref().and_then(lc_host_port) {
+                    std::env::set_var("VCLC_HOST_PORT", lc_host.to_string().as_str());
+                    info!("Setting VCLC_HOST_PORT: {}", lc_host);
                 }
                 o
             }
@@ -118,6 +350,9 @@ pub fn config_load() -> VibeCheckConfig {
                     show_toy_advanced: false,
                     show_feature_advanced: false,
                     messages_per_second: 10,
+                    telemetry: TelemetryConfig::default(),
+                    battery_alerts: BatteryAlertConfig::default(),
+                    chatbox: ChatboxConfig::default(),
                 };
 
                 fs::write(&vc_config_file, serde_json::to_string(&def_conf).unwrap()).unwrap();
@@ -141,7 +376,10 @@ pub fn config_load() -> VibeCheckConfig {
                 lc_override: None,
                 show_toy_advanced: false,
                 show_feature_advanced: false,
-                messages_per_second: 10
+                messages_per_second: 10,
+                telemetry: TelemetryConfig::default(),
+                battery_alerts: BatteryAlertConfig::default(),
+                chatbox: ChatboxConfig::default(),
             };
             fs::write(&vc_config_file, serde_json::to_string(&def_conf).unwrap()).unwrap();
             trace!("Wrote VibeCheck config file");
@@ -153,8 +391,8 @@ pub fn config_load() -> VibeCheckConfig {
 pub mod toy {
 
     use crate::{
-        frontend::frontend_types::FeVCToyAnatomy,
-        toy_handling::toyops::VCToyFeatures,
+        frontend::{frontend_types::FeVCToyAnatomy, validation::validate, ToFrontend},
+        toy_handling::toyops::{clamp_level, VCToyFeatures, LEVEL_RANGE},
         util::fs::{file_exists, get_config_dir},
         vcore::vcerror,
     };
@@ -204,8 +442,8 @@ pub mod toy {
                 "footl" => Self::FootL,
                 "footr" => Self::FootR,
                 "hands" => Self::Hands,
-                "handl" => Self::HandLeft,
-                "handr" => Self::HandRight,
+                "handl" | "handleft" => Self::HandLeft,
+                "handr" | "handright" => Self::HandRight,
                 "labia" => Self::Labia,
                 "mouth" => Self::Mouth,
                 "na" => Self::NA,
@@ -310,17 +548,48 @@ pub mod toy {
             } else {
                 let con = std::fs::read_to_string(config_path).unwrap();
 
-                let config: VCToyConfig = match serde_json::from_str(&con) {
+                let mut config: VCToyConfig = match serde_json::from_str(&con) {
                     Ok(vc_toy_config) => vc_toy_config,
                     Err(_) => {
                         return Err(vcerror::backend::VibeCheckToyConfigError::DeserializeError);
                     }
                 };
+                config.sanitize();
                 debug!("Loaded & parsed toy config successfully!");
                 Ok(config)
             }
         }
 
+        /*
+         * Clamps levels into the ranges the frontend allows and warns about anything else the
+         * toy alteration validation finds. Loaded configs are kept either way so a config that
+         * fails validation is never replaced by a default one.
+         */
+        pub fn sanitize(&mut self) {
+            for feature in self.features.features.iter_mut() {
+                if feature.feature_levels.clamp_to_ranges() {
+                    warn!(
+                        "Toy config {} had out of range levels for {:?} {}, clamped them",
+                        self.toy_name, feature.feature_type, feature.feature_index
+                    );
+                }
+                for mapping in feature
+                    .osc_parameters
+                    .iter_mut()
+                    .flat_map(|p| p.int_values.iter_mut())
+                {
+                    mapping.level = clamp_level(mapping.level, LEVEL_RANGE);
+                }
+            }
+
+            if let Err(field_errors) = validate(self.features.features.to_frontend().as_slice()) {
+                warn!(
+                    "Toy config {} failed validation: {:?}",
+                    self.toy_name, field_errors
+                );
+            }
+        }
+
         pub fn save_offline_toy_config(&self) {
             let config_path = format!(
                 "{}\\ToyConfigs\\{}.json",
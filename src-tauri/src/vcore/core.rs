@@ -1,23 +1,61 @@
 use buttplug::client::ButtplugClient;
 use log::{debug, error as logerr, info, trace, warn};
 use std::fs;
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{SocketAddr, SocketAddrV4};
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
-
-use crate::frontend::frontend_types::{FeToyEvent, FeVCFeatureType, FeVCToy, FeVibeCheckConfig};
-use crate::frontend::ToFrontend;
-use crate::osc::logic::{toy_refresh, vc_disabled_osc_command_listen};
+use std::time::Duration;
+
+use crate::frontend::frontend_types::{
+    FeAvatarOSCConfig, FeAvatarParameter, FeAvatarToySuggestion, FeBatteryHistory,
+    FeMissingParameter, FeOSCBindFailure, FeOSCConfigClearResult, FeOSCListener,
+    FeOSCRepeaterStats, FeOSCRepeaterTarget, FePenetrationSystem, FeTelemetryConfig, FeToyEvent,
+    FeToyFeatureRef, FeVCFeatureType, FeVCToy, FeVCToyAnatomy, FeVRChatDiscovery,
+    FeVibeCheckConfig,
+};
+use crate::frontend::{FromFrontend, ToFrontend};
+use crate::osc::avatar_backup::{backups_dir, clear_osc_configs};
+use crate::osc::avatar_config::read_avatar_configs;
+use crate::osc::chatbox::{
+    announce, configure_chatbox, ChatboxAnnouncement, MIN_CHATBOX_COOLDOWN_MS,
+};
+use crate::osc::feedback::configure_osc_data_addresses;
+use crate::osc::logic::OSCListeners;
+use crate::osc::oscquery::{update_oscquery_tree, OSCQueryHttp};
+use crate::osc::param_browser::refresh_parameter_consumers;
+use crate::osc::repeater::{repeater_stats, REPEATER_STATE};
+use crate::osc::tcp::OSCTransport;
+use crate::osc::telemetry::{clean_aliases, toy_refresh};
+use crate::osc::vrchat_discovery::{discover_vrchat_http, query_vrchat};
+use crate::osc_api::mirror::mirror_api_state;
+use crate::toy_handling::battery::{battery_history, BatteryAlertConfig};
+use crate::toy_handling::dry_run::{set_dry_run_app_handle, set_dry_run_enabled};
+use crate::toy_handling::input_processor::penetration_systems::PenetrationSystemType;
 use crate::toy_handling::toy_manager::ToyManager;
-use crate::toy_handling::toyops::VCFeatureType;
-use crate::toy_handling::{errors::HandlerErr, handling::command_toy};
+use crate::toy_handling::toyops::{
+    ProcessingMode, ProcessingModeValues, ToyParameter, VCFeatureType, VCToyFeature, VCToyFeatures,
+};
+use crate::toy_handling::ToyPower;
+use crate::toy_handling::{
+    errors::HandlerErr,
+    handling::{command_toy, ToyCommandTarget},
+};
 use crate::util::bluetooth;
-use crate::util::fs::{get_config_dir, get_user_home_dir};
-use crate::util::net::{find_available_tcp_port, find_available_udp_port};
+use crate::util::fs::{get_config_dir, get_vrchat_osc_dir};
+use crate::util::net::{
+    canonical_ip, find_available_tcp_port, find_available_udp_port, reaches_bind, HostPort, IpCidr,
+};
+use crate::vcore::vcerror::frontend::{FieldError, FieldErrorKind};
 use crate::vcore::vcerror::{backend, frontend};
 use crate::{
-    config::{OSCNetworking, VibeCheckConfig},
+    config::{
+        lc_host_port,
+        toy::{VCToyAnatomy, VCToyConfig},
+        OSCListener, OSCNetworking, OSCRepeaterTarget, TelemetryConfig, TelemetryTarget,
+        VibeCheckConfig,
+    },
     toy_handling::handling::{client_event_handler, toy_management_handler},
     toy_handling::toyops::VCToy,
 };
@@ -39,6 +77,8 @@ pub struct VibeCheckState {
 
     pub config: VibeCheckConfig,
     pub osc_query_handler: Option<OSCQuery>,
+    // Serves the OSCQuery tree on the HTTP address advertised by osc_query_handler
+    pub osc_query_http: Option<OSCQueryHttp>,
     //pub connection_modes: ConnectionModes,
     pub bp_client: Option<ButtplugClient>,
 
@@ -51,8 +91,8 @@ pub struct VibeCheckState {
     pub error_rx: Receiver<VCError>,
     pub error_tx: Sender<VCError>,
     //================================================
-    // Disabled listener thread handle
-    pub disabled_osc_listener_h_thread: Option<JoinHandle<()>>,
+    // OSC listen sockets, bound in every running state
+    pub osc_listeners: Option<OSCListeners>,
     //================================================
     // Client Event Handler
     pub client_eh_thread: Option<JoinHandle<()>>,
@@ -83,6 +123,9 @@ pub struct VibeCheckState {
 
 impl VibeCheckState {
     pub fn new(config: VibeCheckConfig) -> Self {
+        configure_chatbox(config.chatbox.clone());
+        configure_osc_data_addresses(&config.telemetry);
+
         // Toys hashmap
         //let core_toy_manager = ToyHandler::new();
 
@@ -107,6 +150,7 @@ impl VibeCheckState {
             identifier: String::new(),
             config,
             osc_query_handler: None,
+            osc_query_http: None,
             //connection_modes,
             bp_client: None,
             running: RunningState::Stopped,
@@ -117,8 +161,8 @@ impl VibeCheckState {
             error_tx,
 
             //======================================
-            // Disabled listener thread
-            disabled_osc_listener_h_thread: None,
+            // OSC listeners
+            osc_listeners: None,
             //======================================
             // Client Event Handler
             client_eh_thread: None,
@@ -170,39 +214,17 @@ impl VibeCheckState {
         info!("TMH started");
     }
 
-    pub fn start_disabled_listener(&mut self) {
-        if self.disabled_osc_listener_h_thread.is_some() {
-            return;
-        }
-
-        self.disabled_osc_listener_h_thread =
-            Some(self.async_rt.spawn(vc_disabled_osc_command_listen(
-                self.app_handle.as_ref().unwrap().clone(),
-                self.config.networking.clone(),
-            )));
-    }
-
-    pub async fn stop_disabled_listener(&mut self) {
-        if self.disabled_osc_listener_h_thread.is_none() {
-            return;
-        }
-
-        let dol_thread = self.disabled_osc_listener_h_thread.take().unwrap();
-        dol_thread.abort();
-        match dol_thread.await {
-            Ok(()) => info!("DOL thread finished"),
-            Err(e) => warn!("DOL thread failed to reach completion: {}", e),
-        }
-    }
-
     pub fn set_state_pointer(&mut self, vibecheck_state_pointer: Arc<Mutex<VibeCheckState>>) {
         self.vibecheck_state_pointer = Some(vibecheck_state_pointer);
     }
     pub fn set_app_handle(&mut self, app_handle: AppHandle) {
+        set_dry_run_app_handle(app_handle.clone());
         self.app_handle = Some(app_handle);
     }
     pub fn init_toy_manager(&mut self) {
-        self.core_toy_manager = Some(ToyManager::new(self.app_handle.as_ref().unwrap().clone()));
+        let toy_manager = ToyManager::new(self.app_handle.as_ref().unwrap().clone());
+        refresh_parameter_consumers(&toy_manager);
+        self.core_toy_manager = Some(toy_manager);
     }
 
     pub fn init_ceh(&mut self) {
@@ -291,32 +313,32 @@ impl VibeCheckState {
         }
     }
 
-    pub fn osc_query_init(&mut self) {
-        let available_tcp_port =
-            find_available_tcp_port(self.config.networking.bind.ip().to_string());
-        let available_udp_port =
-            find_available_udp_port(self.config.networking.bind.ip().to_string());
+    pub fn osc_query_init(&mut self) -> Result<(), frontend::VCFeError> {
+        let Some((listener, advertised_ip)) = self.config.networking.oscquery_listener_mut() else {
+            return Err(frontend::VCFeError::OSCQueryFailure(
+                "OSCQuery requires an IPv4 listener",
+            ));
+        };
 
-        let http_net = SocketAddrV4::new(
-            *self.config.networking.bind.ip(),
-            available_tcp_port.unwrap(),
-        );
-        let osc_net = SocketAddrV4::new(
-            *self.config.networking.bind.ip(),
-            available_udp_port.unwrap(),
-        );
+        // Probe on the listener address itself (may be IPv4-mapped IPv6)
+        let available_tcp_port = find_available_tcp_port(listener.address.ip().to_string());
+        let available_udp_port = find_available_udp_port(listener.address.ip().to_string());
 
+        let http_net = SocketAddrV4::new(advertised_ip, available_tcp_port.unwrap());
+        let osc_net = SocketAddrV4::new(advertised_ip, available_udp_port.unwrap());
+
+        listener.address.set_port(available_udp_port.unwrap());
         self.osc_query_handler = Some(OSCQuery::new("VibeCheck".to_string(), http_net, osc_net));
-        self.config
-            .networking
-            .bind
-            .set_port(available_udp_port.unwrap());
+        self.osc_query_http = Some(OSCQueryHttp::new(http_net, osc_net));
+        Ok(())
     }
 
     pub fn osc_query_fini(&mut self) {
         if self.osc_query_handler.is_some() {
             let mut h = self.osc_query_handler.take().unwrap();
-            h.stop_http_json();
+            if let Some(mut http) = self.osc_query_http.take() {
+                http.stop();
+            }
             h.unregister_mdns_service();
             h.shutdown_mdns();
         }
@@ -349,7 +371,8 @@ pub enum TmSig {
     Stopped,
     */
     Listening,
-    BindError,
+    // Dry-run was switched while listening
+    DryRun(bool),
 }
 
 #[derive(Debug)]
@@ -398,37 +421,77 @@ pub async fn native_vibecheck_disable(
     info!("Sent TMHReset signal");
 
     vc_lock.running = RunningState::Stopped;
-
-    info!("Starting disabled state OSC cmd listener");
-    vc_lock.start_disabled_listener();
+    announce(ChatboxAnnouncement::VibeCheckDisabled);
+    mirror_api_state(&vc_lock);
 
     Ok(())
 }
 
+/*
+ * Binds the OSC listeners when they aren't bound from the current networking config
+ * Old listeners are stopped without holding the state lock since their threads may be waiting on it
+ * Returns true when at least one listener is bound
+ */
+pub fn ensure_osc_listeners(vc_state: &Mutex<VibeCheckState>) -> bool {
+    let (old_listeners, networking, app_handle) = {
+        let mut vc_lock = vc_state.lock();
+        if let Some(listeners) = vc_lock.osc_listeners.as_ref() {
+            if listeners.is_current(&vc_lock.config.networking) {
+                return true;
+            }
+        }
+        let Some(app_handle) = vc_lock.app_handle.clone() else {
+            logerr!("ensure_osc_listeners() called but no app_handle was set");
+            return false;
+        };
+        (
+            vc_lock.osc_listeners.take(),
+            vc_lock.config.networking.clone(),
+            app_handle,
+        )
+    };
+
+    if let Some(old_listeners) = old_listeners {
+        info!("OSC listener settings changed, rebinding");
+        old_listeners.shutdown();
+    }
+
+    let listeners = OSCListeners::bind(&networking, &app_handle);
+    let any_bound = listeners.any_bound();
+    vc_state.lock().osc_listeners = Some(listeners);
+    any_bound
+}
+
 pub async fn native_vibecheck_enable(
     vc_state: tauri::State<'_, VCStateMutex>,
 ) -> Result<(), frontend::VCFeError> {
-    // Send Start listening signal
+    {
+        let vc_lock = vc_state.0.lock();
+        if let RunningState::Running = vc_lock.running {
+            //return Err(frontend::VCFeError::EnableFailure);
+            // Don't fail if already enabled
+            return Ok(());
+        }
 
-    let mut vc_lock = vc_state.0.lock();
-    if let RunningState::Running = vc_lock.running {
-        //return Err(frontend::VCFeError::EnableFailure);
-        // Don't fail if already enabled
-        return Ok(());
+        if vc_lock.bp_client.is_none() {
+            return Err(frontend::VCFeError::EnableFailure);
+        }
     }
 
-    if vc_lock.bp_client.is_none() {
-        return Err(frontend::VCFeError::EnableFailure);
+    // Listeners are normally bound already, this only rebinds ones that failed before
+    if !ensure_osc_listeners(&vc_state.0) {
+        logerr!("No OSC listener is bound, not enabling");
+        return Err(frontend::VCFeError::EnableBindFailure);
     }
 
-    info!("Stopping DOL");
-    vc_lock.stop_disabled_listener().await;
+    let mut vc_lock = vc_state.0.lock();
 
     /* No longer disabling CEH
     vc_lock.init_ceh().await;
     info!("CEH initialized");
     */
 
+    // Send Start listening signal
     vc_lock
         .tme_send_tx
         .send(ToyManagementEvent::Sig(TmSig::StartListening(
@@ -444,6 +507,8 @@ pub async fn native_vibecheck_enable(
                     match sig {
                         TmSig::Listening => {
                             vc_lock.running = RunningState::Running;
+                            announce(ChatboxAnnouncement::VibeCheckEnabled);
+                            mirror_api_state(&vc_lock);
 
                             // Enable successful
                             // Start TUH thread
@@ -451,16 +516,6 @@ pub async fn native_vibecheck_enable(
 
                             Ok(())
                         }
-                        TmSig::BindError => {
-                            logerr!("Bind Error in TME sig: Sending shutdown signal!");
-
-                            vc_lock
-                                .tme_send_tx
-                                .send(ToyManagementEvent::Sig(TmSig::StopListening))
-                                .unwrap();
-                            vc_lock.running = RunningState::Stopped;
-                            Err(frontend::VCFeError::EnableBindFailure)
-                        }
                         _ => {
                             //Did not get the correct signal oops
                             warn!("Got incorrect TME signal.");
@@ -484,17 +539,43 @@ pub async fn native_vibecheck_enable(
 pub fn native_osc_query_start(
     vc_state: tauri::State<'_, VCStateMutex>,
 ) -> Result<(), frontend::VCFeError> {
-    let mut vc_lock = vc_state.0.lock();
+    let advertised_listener = {
+        let mut vc_lock = vc_state.0.lock();
+        if vc_lock.osc_query_handler.is_none() {
+            vc_lock.osc_query_init()?;
+        }
+        vc_lock
+            .config
+            .networking
+            .oscquery_listener_mut()
+            .map(|(listener, _)| listener.name.clone())
+    };
 
-    if vc_lock.osc_query_handler.is_none() {
-        vc_lock.osc_query_init();
+    // osc_query_init moves the advertised listener to a free port, rebind so it listens there
+    ensure_osc_listeners(&vc_state.0);
+
+    let mut vc_lock = vc_state.0.lock();
+    let advertised_bound = vc_lock.osc_listeners.as_ref().is_some_and(|listeners| {
+        !listeners
+            .bind_failures()
+            .iter()
+            .any(|failure| Some(&failure.name) == advertised_listener.as_ref())
+    });
+    if !advertised_bound {
+        logerr!("OSCQuery listener is not bound, not advertising it");
+        vc_lock.osc_query_fini();
+        return Err(frontend::VCFeError::OSCQueryFailure(
+            "Failed to bind the OSCQuery listener",
+        ));
     }
 
-    vc_lock
-        .osc_query_handler
-        .as_mut()
-        .unwrap()
-        .start_http_json();
+    // The tree is served by VibeCheck, the handler only advertises it
+    if let Err(e) = vc_lock.osc_query_http.as_mut().unwrap().start() {
+        logerr!("Failed to start OSCQuery HTTP server: {}", e);
+        return Err(frontend::VCFeError::OSCQueryFailure(
+            "Failed to start OSCQuery HTTP server",
+        ));
+    }
     vc_lock
         .osc_query_handler
         .as_ref()
@@ -539,12 +620,120 @@ pub fn native_osc_query_attempt_force(
     Ok(())
 }
 
+/*
+ * Rebuilds the OSCQuery tree from the online toys
+ * The toy management handler already does this on every toy change
+ */
 pub fn osc_query_force_populate(
     vc_state: tauri::State<'_, VCStateMutex>,
 ) -> Result<(), frontend::VCFeError> {
+    let vc_lock = vc_state.0.lock();
+    let Some(toy_manager) = vc_lock.core_toy_manager.as_ref() else {
+        return Err(frontend::VCFeError::ToyManagerNotReady);
+    };
+    update_oscquery_tree(toy_manager.online_toys.values());
     Ok(())
 }
 
+/*
+ * Reads VRChat's OSCQuery service and sets the remote to where VRChat receives OSC
+ * http skips mDNS discovery (eg. for a VRChat instance on another machine)
+ */
+pub fn native_vrchat_discover(
+    vc_state: tauri::State<'_, VCStateMutex>,
+    http: Option<String>,
+    timeout: Duration,
+) -> Result<FeVRChatDiscovery, frontend::VCFeError> {
+    let http = http
+        .map(|h| HostPort::from_str(&h))
+        .transpose()
+        .map_err(|_| frontend::VCFeError::InvalidRemoteEndpoint)?;
+
+    // The blocking HTTP client can't run on an async runtime thread
+    let query = std::thread::spawn(move || {
+        let http = match http {
+            Some(http) => http,
+            None => discover_vrchat_http(timeout)?,
+        };
+        query_vrchat(http)
+    })
+    .join()
+    .map_err(|_| frontend::VCFeError::OSCQueryRequestFailure("Discovery failed".to_string()))??;
+
+    let (missing_parameters, config) = {
+        let mut vc_lock = vc_state.0.lock();
+        info!("Setting remote to {} from OSCQuery", query.osc_remote);
+        vc_lock.config.networking.remote = query.osc_remote.clone();
+
+        let missing_parameters = match vc_lock.core_toy_manager.as_ref() {
+            Some(toy_manager) => missing_avatar_parameters(toy_manager, &query.parameters),
+            None => Vec::new(),
+        };
+        (missing_parameters, vc_lock.config.clone())
+    };
+    fe_save_config(config)?;
+
+    Ok(FeVRChatDiscovery {
+        name: query.name,
+        http: query.http.to_string(),
+        osc_remote: query.osc_remote.to_string(),
+        avatar_id: query.avatar_id,
+        parameters: query
+            .parameters
+            .into_iter()
+            .map(|(addr, osc_type)| FeAvatarParameter { addr, osc_type })
+            .collect(),
+        missing_parameters,
+    })
+}
+
+/*
+ * Avatar parameters assigned to toy features that the avatar doesn't have
+ * Only /avatar/parameters addresses are checked
+ */
+fn missing_avatar_parameters(
+    toy_manager: &ToyManager,
+    avatar_parameters: &[(String, String)],
+) -> Vec<FeMissingParameter> {
+    let online = toy_manager.online_toys.values().map(|toy| {
+        (
+            toy.toy_name.clone(),
+            Some(toy.toy_id),
+            &toy.parsed_toy_features,
+        )
+    });
+    let offline = toy_manager
+        .configs
+        .values()
+        .filter(|config| !toy_manager.check_toy_online(&config.toy_name))
+        .map(|config| (config.toy_name.clone(), None, &config.features));
+
+    let mut missing = Vec::new();
+    for (toy_name, toy_id, features) in online.chain(offline) {
+        for feature in &features.features {
+            for param in &feature.osc_parameters {
+                if !param.parameter.starts_with("/avatar/parameters/")
+                    || avatar_parameters
+                        .iter()
+                        .any(|(addr, _)| *addr == param.parameter)
+                {
+                    continue;
+                }
+                missing.push(FeMissingParameter {
+                    feature: FeToyFeatureRef {
+                        toy_id,
+                        toy_name: toy_name.clone(),
+                        feature_index: feature.feature_index,
+                        feature_type: feature.feature_type.to_fe(),
+                    },
+                    parameter: param.parameter.clone(),
+                });
+            }
+        }
+    }
+    missing
+}
+
 pub async fn native_vibecheck_start_bt_scan(
     vc_state: tauri::State<'_, VCStateMutex>,
 ) -> Result<(), frontend::VCFeError> {
@@ -595,66 +784,280 @@ pub async fn native_vibecheck_stop_bt_scan(
     Ok(())
 }
 
+pub fn native_battery_history() -> Vec<FeBatteryHistory> {
+    battery_history()
+}
+
 pub fn native_get_vibecheck_config(vc_state: tauri::State<'_, VCStateMutex>) -> FeVibeCheckConfig {
     let config = {
         let vc_lock = vc_state.0.lock();
         vc_lock.config.clone()
     };
 
-    let lc_or = config.lc_override.map(|host| host.to_string());
-
     FeVibeCheckConfig {
         networking: config.networking.to_fe(),
         scan_on_disconnect: config.scan_on_disconnect,
         minimize_on_exit: config.minimize_on_exit,
         desktop_notifications: config.desktop_notifications,
-        lc_override: lc_or,
+        lc_override: config.lc_override,
         show_toy_advanced: config.show_toy_advanced,
         show_feature_advanced: config.show_feature_advanced,
         messages_per_second: config.messages_per_second,
+        telemetry: config.telemetry.to_fe(),
+        battery_alerts: config.battery_alerts,
+        chatbox: config.chatbox,
     }
 }
 
+/*
+ * Parses the OSC listeners from the frontend
+ * At least one listener is required and names / addresses must be unique
+ */
+fn parse_listeners(
+    fe_listeners: &[FeOSCListener],
+) -> Result<Vec<OSCListener>, frontend::VCFeError> {
+    if fe_listeners.is_empty() {
+        return Err(frontend::VCFeError::NoListeners);
+    }
+
+    let mut listeners: Vec<OSCListener> = Vec::with_capacity(fe_listeners.len());
+
+    for fe_listener in fe_listeners {
+        let address = match SocketAddr::from_str(&fe_listener.address) {
+            Ok(sa) => sa,
+            Err(_e) => return Err(frontend::VCFeError::InvalidBindEndpoint),
+        };
+
+        if listeners
+            .iter()
+            .any(|l| l.name == fe_listener.name || l.address == address)
+        {
+            return Err(frontend::VCFeError::DuplicateListener(
+                fe_listener.name.clone(),
+            ));
+        }
+
+        let mut allowlist = Vec::with_capacity(fe_listener.allowlist.len());
+        for network in &fe_listener.allowlist {
+            match IpCidr::from_str(network) {
+                Ok(net) => allowlist.push(net),
+                Err(_e) => {
+                    return Err(frontend::VCFeError::InvalidListenerAllowlist(
+                        fe_listener.name.clone(),
+                    ))
+                }
+            }
+        }
+
+        // The secret is an address segment, empty secrets disable it
+        let api_secret = fe_listener
+            .api_secret
+            .as_deref()
+            .map(str::trim)
+            .filter(|secret| !secret.is_empty())
+            .map(str::to_string);
+        if api_secret
+            .as_ref()
+            .is_some_and(|secret| secret.contains(['/', ' ', '#', '*', '?', ',']))
+        {
+            return Err(frontend::VCFeError::InvalidListenerApiSecret(
+                fe_listener.name.clone(),
+            ));
+        }
+
+        listeners.push(OSCListener {
+            name: fe_listener.name.clone(),
+            address,
+            allowlist,
+            api_secret,
+            rewrite: fe_listener.rewrite.clone(),
+            transport: fe_listener.transport,
+        });
+    }
+
+    Ok(listeners)
+}
+
+/*
+ * Parses the repeater targets from the frontend
+ * Target names must be unique and a target can't point back at a listen socket
+ */
+fn parse_repeater_targets(
+    fe_targets: &[FeOSCRepeaterTarget],
+    listeners: &[OSCListener],
+) -> Result<Vec<OSCRepeaterTarget>, frontend::VCFeError> {
+    let mut targets: Vec<OSCRepeaterTarget> = Vec::with_capacity(fe_targets.len());
+
+    for fe_target in fe_targets {
+        if targets.iter().any(|t| t.name == fe_target.name) {
+            return Err(frontend::VCFeError::DuplicateRepeaterTarget(
+                fe_target.name.clone(),
+            ));
+        }
+
+        // Hostnames are resolved by the repeater which checks them again, only literal addresses can be checked here
+        let loops_back = |hp: &HostPort| {
+            hp.literal().is_some_and(|target| {
+                listeners
+                    .iter()
+                    .any(|l| l.transport == OSCTransport::Udp && reaches_bind(target, l.address))
+            })
+        };
+        let address = match HostPort::from_str(&fe_target.address) {
+            Ok(hp) if !loops_back(&hp) => hp,
+            _ => {
+                return Err(frontend::VCFeError::InvalidRepeaterEndpoint(
+                    fe_target.name.clone(),
+                ))
+            }
+        };
+
+        targets.push(OSCRepeaterTarget {
+            name: fe_target.name.clone(),
+            address,
+            enabled: fe_target.enabled,
+            filter: fe_target.filter.clone().filter(|f| !f.is_empty()),
+            rewrite: fe_target.rewrite.clone(),
+        });
+    }
+
+    Ok(targets)
+}
+
+/*
+ * Parses the telemetry config from the frontend
+ * The template has to be an OSC address with a {field} placeholder so fields don't share an address
+ * Aliases replace a path segment so they can't contain OSC address syntax
+ */
+fn parse_telemetry(
+    fe_telemetry: &FeTelemetryConfig,
+) -> Result<TelemetryConfig, frontend::VCFeError> {
+    if fe_telemetry.interval_secs == 0 {
+        return Err(frontend::VCFeError::InvalidTelemetryInterval);
+    }
+
+    let address_template = fe_telemetry.address_template.trim().to_string();
+    if !address_template.starts_with('/')
+        || !address_template.contains("{field}")
+        || address_template.contains(char::is_whitespace)
+    {
+        return Err(frontend::VCFeError::InvalidTelemetryTemplate);
+    }
+
+    let mut targets: Vec<TelemetryTarget> = Vec::with_capacity(fe_telemetry.targets.len());
+    for fe_target in &fe_telemetry.targets {
+        let Ok(address) = HostPort::from_str(&fe_target.address) else {
+            return Err(frontend::VCFeError::InvalidTelemetryEndpoint(
+                fe_target.address.clone(),
+            ));
+        };
+        let target = TelemetryTarget {
+            address,
+            transport: fe_target.transport,
+        };
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    let aliases = clean_aliases(fe_telemetry.aliases.clone());
+    if let Some((toy_name, _)) = aliases.iter().find(|(_, alias)| {
+        alias.contains(|c: char| {
+            c.is_whitespace() || matches!(c, '/' | '#' | '*' | ',' | '?' | '[' | ']' | '{' | '}')
+        })
+    }) {
+        return Err(frontend::VCFeError::InvalidTelemetryAlias(toy_name.clone()));
+    }
+
+    let mut fields = Vec::with_capacity(fe_telemetry.fields.len());
+    for field in &fe_telemetry.fields {
+        if !fields.contains(field) {
+            fields.push(*field);
+        }
+    }
+
+    Ok(TelemetryConfig {
+        interval_secs: fe_telemetry.interval_secs,
+        targets,
+        address_template,
+        fields,
+        aliases,
+    })
+}
+
+/*
+ * Thresholds are battery levels (0-1) with critical below low
+ */
+fn validate_battery_alerts(battery_alerts: &BatteryAlertConfig) -> Result<(), frontend::VCFeError> {
+    let level = |l: f64| l > 0.0 && l <= 1.0;
+
+    for (toy_name, threshold) in &battery_alerts.thresholds {
+        if !level(threshold.low)
+            || threshold
+                .critical
+                .is_some_and(|c| !level(c) || c >= threshold.low)
+            || threshold.critical_max_level.is_some_and(|m| !level(m))
+            || (threshold.critical_max_level.is_some() && threshold.critical.is_none())
+        {
+            return Err(frontend::VCFeError::InvalidBatteryThreshold(
+                toy_name.clone(),
+            ));
+        }
+    }
+    Ok(())
+}
+
 pub fn native_set_vibecheck_config(
     vc_state: tauri::State<'_, VCStateMutex>,
     fe_vc_config: FeVibeCheckConfig,
 ) -> Result<(), frontend::VCFeError> {
     info!("Got fe_vc_config: {:?}", fe_vc_config);
-    let bind = match SocketAddrV4::from_str(&fe_vc_config.networking.bind) {
-        Ok(sa) => sa,
-        Err(_e) => return Err(frontend::VCFeError::InvalidBindEndpoint),
-    };
+    let bind = parse_listeners(&fe_vc_config.networking.bind)?;
 
-    let remote = match SocketAddrV4::from_str(&fe_vc_config.networking.remote) {
+    let remote = match HostPort::from_str(&fe_vc_config.networking.remote) {
         Ok(sa) => sa,
         Err(_e) => return Err(frontend::VCFeError::InvalidRemoteEndpoint),
     };
 
+    let repeater = parse_repeater_targets(&fe_vc_config.networking.repeater, &bind)?;
+    let telemetry = parse_telemetry(&fe_vc_config.telemetry)?;
+    validate_battery_alerts(&fe_vc_config.battery_alerts)?;
+    if fe_vc_config.chatbox.cooldown_ms < MIN_CHATBOX_COOLDOWN_MS {
+        return Err(frontend::VCFeError::InvalidChatboxCooldown);
+    }
+
     let config = {
         let mut vc_lock = vc_state.0.lock();
         vc_lock.config.networking.bind = bind;
         vc_lock.config.networking.remote = remote;
+        vc_lock.config.networking.remote_transport = fe_vc_config.networking.remote_transport;
+        vc_lock.config.networking.repeater = repeater;
+        vc_lock.config.networking.feedback_rate = fe_vc_config.networking.feedback_rate;
         vc_lock.config.scan_on_disconnect = fe_vc_config.scan_on_disconnect;
         vc_lock.config.minimize_on_exit = fe_vc_config.minimize_on_exit;
         vc_lock.config.desktop_notifications = fe_vc_config.desktop_notifications;
         vc_lock.config.show_toy_advanced = fe_vc_config.show_toy_advanced;
         vc_lock.config.show_feature_advanced = fe_vc_config.show_feature_advanced;
         vc_lock.config.messages_per_second = fe_vc_config.messages_per_second;
+        configure_osc_data_addresses(&telemetry);
+        vc_lock.config.telemetry = telemetry;
+        vc_lock.config.battery_alerts = fe_vc_config.battery_alerts.clone();
+        vc_lock.config.chatbox = fe_vc_config.chatbox.clone();
+        configure_chatbox(fe_vc_config.chatbox.clone());
 
         if let Some(host) = fe_vc_config.lc_override {
-            // Is valid IPv4?
-            match Ipv4Addr::from_str(&host) {
-                Ok(sa) => {
-                    // Force port because buttplug forces non http atm
-                    std::env::set_var("VCLC_HOST_PORT", format!("{}:20010", sa).as_str());
+            // Is valid IP or hostname?
+            match lc_host_port(&host) {
+                Some(lc_host) => {
+                    std::env::set_var("VCLC_HOST_PORT", lc_host.to_string().as_str());
                     match std::env::var("VCLC_HOST_PORT") {
                         Ok(_) => {
-                            vc_lock.config.lc_override = Some(sa);
+                            vc_lock.config.lc_override = Some(host);
                         }
                         Err(_) => return Err(frontend::VCFeError::SetLCOverrideFailure),
                     }
                 }
-                Err(_e) => return Err(frontend::VCFeError::InvalidLCHost),
+                None => return Err(frontend::VCFeError::InvalidLCHost),
             };
         } else {
             std::env::remove_var("VCLC_HOST_PORT");
@@ -677,6 +1080,90 @@ pub fn native_set_vibecheck_config(
         vc_lock.config.clone()
     };
 
+    // Rebinds only when the listeners or repeater targets changed
+    if !ensure_osc_listeners(&vc_state.0) {
+        warn!("No OSC listener could be bound with the new settings");
+    }
+
+    fe_save_config(config)
+}
+
+/*
+ * Enables or disables a repeater target
+ * Applies immediately to a running listener and is saved to the config
+ */
+pub fn native_set_osc_repeater_target_enabled(
+    vc_state: tauri::State<'_, VCStateMutex>,
+    name: String,
+    enabled: bool,
+) -> Result<(), frontend::VCFeError> {
+    let config = {
+        let mut vc_lock = vc_state.0.lock();
+        let Some(target) = vc_lock
+            .config
+            .networking
+            .repeater
+            .iter_mut()
+            .find(|t| t.name == name)
+        else {
+            return Err(frontend::VCFeError::RepeaterTargetNotFound);
+        };
+        target.enabled = enabled;
+
+        if let Some(state) = REPEATER_STATE.lock().get(&name) {
+            state.set_enabled(enabled);
+        }
+
+        vc_lock.config.clone()
+    };
+
+    fe_save_config(config)
+}
+
+/*
+ * Switches dry-run at runtime
+ * Devices are stopped when enabling so nothing keeps running at its last level
+ */
+pub fn native_set_dry_run(vc_state: tauri::State<'_, VCStateMutex>, enabled: bool) {
+    let vc_lock = vc_state.0.lock();
+    set_dry_run_enabled(enabled);
+
+    if enabled {
+        // Stopped in the background so the state lock isn't held while devices respond
+        if let Some(bpc) = vc_lock.bp_client.as_ref() {
+            let devices = bpc.devices();
+            vc_lock.async_rt.spawn(async move {
+                for dev in devices {
+                    let _ = dev.stop().await;
+                }
+            });
+        }
+    }
+
+    let _ = vc_lock
+        .tme_send_tx
+        .send(ToyManagementEvent::Sig(TmSig::DryRun(enabled)));
+}
+
+pub fn native_osc_repeater_stats(
+    vc_state: tauri::State<'_, VCStateMutex>,
+) -> Vec<FeOSCRepeaterStats> {
+    let vc_lock = vc_state.0.lock();
+    repeater_stats(&vc_lock.config.networking.repeater)
+}
+
+pub fn native_osc_bind_failures(vc_state: tauri::State<'_, VCStateMutex>) -> Vec<FeOSCBindFailure> {
+    let vc_lock = vc_state.0.lock();
+    vc_lock
+        .osc_listeners
+        .as_ref()
+        .map_or_else(Vec::new, |listeners| listeners.bind_failures().to_vec())
+}
+
+/*
+ * Saves the config and maps the error for the frontend
+ */
+fn fe_save_config(config: crate::config::VibeCheckConfig) -> Result<(), frontend::VCFeError> {
     match save_config(config) {
         Ok(()) => Ok(()),
         Err(e) => match e {
@@ -723,6 +1210,9 @@ pub fn native_alter_toy(
 
     let send_res = {
         let vc_lock = vc_state.0.lock();
+        if let Some(toy_manager) = vc_lock.core_toy_manager.as_ref() {
+            refresh_parameter_consumers(toy_manager);
+        }
         vc_lock
             .tme_send_tx
             .send(ToyManagementEvent::Tu(ToyUpdate::AlterToy(altered)))
@@ -751,49 +1241,278 @@ pub fn native_alter_toy(
     }
 }
 
-pub fn native_clear_osc_config() -> Result<(), backend::VibeCheckFSError> {
-    let osc_dirs = match std::fs::read_dir(format!(
-        "{}\\AppData\\LocalLow\\VRChat\\VRChat\\OSC\\",
-        get_user_home_dir()
-    )) {
-        Ok(dirs) => dirs,
-        Err(_e) => return Err(backend::VibeCheckFSError::ReadDirFailure),
-    };
-
-    //info!("osc_dirs: {}", osc_dirs.count());
-
-    let usr_dirs = match osc_dirs
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, std::io::Error>>()
-    {
-        Ok(usr_dirs) => usr_dirs,
-        Err(_) => return Err(backend::VibeCheckFSError::ReadDirPathFailure),
-    };
+/*
+ * Assigns an OSC address to a toy feature
+ * Online toys are looked up by id and offline toys by name
+ */
+pub fn native_osc_parameter_assign(
+    vc_state: tauri::State<'_, VCStateMutex>,
+    app_handle: tauri::AppHandle,
+    feature: FeToyFeatureRef,
+    addr: String,
+    processing_mode: ProcessingMode,
+) -> Result<(), frontend::VCFeError> {
+    let FeToyFeatureRef {
+        toy_id,
+        toy_name,
+        feature_index,
+        feature_type,
+    } = feature;
+
+    alter_toy_features(
+        vc_state,
+        app_handle,
+        toy_id,
+        toy_name,
+        |features, _anatomy, no_feature| {
+            assign_feature_parameter(
+                features,
+                feature_index,
+                feature_type,
+                addr,
+                processing_mode,
+                no_feature,
+            )
+        },
+    )
+}
 
-    for dir in usr_dirs {
-        if dir.is_dir() {
-            let dir_path = dir.file_name().unwrap().to_str().unwrap();
-            info!("Got Dir: {}", dir_path);
+/*
+ * Applies a suggested avatar toy setup to a toy feature
+ * Sets the toy's anatomy and the feature's penetration system, plain parameters are assigned as Raw
+ */
+pub fn native_avatar_suggestion_accept(
+    vc_state: tauri::State<'_, VCStateMutex>,
+    app_handle: tauri::AppHandle,
+    feature: FeToyFeatureRef,
+    suggestion: FeAvatarToySuggestion,
+) -> Result<(), frontend::VCFeError> {
+    let FeToyFeatureRef {
+        toy_id,
+        toy_name,
+        feature_index,
+        feature_type,
+    } = feature;
+
+    alter_toy_features(
+        vc_state,
+        app_handle,
+        toy_id,
+        toy_name,
+        |features, anatomy, no_feature| {
+            let Some((_, toy_feature)) = find_feature_mut(features, feature_index, feature_type)
+            else {
+                return Err(frontend::VCFeError::AlterToyFailure(no_feature));
+            };
 
-            if dir
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .starts_with("usr_")
-            {
-                let delete_dir = dir.as_path().to_str().unwrap();
-                info!("Clearing dir: {}", delete_dir);
-                match std::fs::remove_dir_all(delete_dir) {
-                    Ok(()) => {}
-                    Err(_e) => return Err(backend::VibeCheckFSError::RemoveDirsFailure),
+            info!(
+                "Applying avatar suggestion {:?} to feature index {}",
+                suggestion, feature_index
+            );
+            match suggestion.pen_system_type {
+                PenetrationSystemType::NONE => {
+                    for addr in suggestion.parameters {
+                        if !toy_feature
+                            .osc_parameters
+                            .iter()
+                            .any(|p| p.parameter == addr)
+                        {
+                            toy_feature.osc_parameters.push(ToyParameter {
+                                parameter: addr,
+                                processing_mode_values: ProcessingModeValues::new_from(
+                                    &ProcessingMode::Raw,
+                                ),
+                                processing_mode: ProcessingMode::Raw,
+                                int_values: Vec::new(),
+                            });
+                        }
+                    }
+                }
+                pen_system_type => {
+                    let pen_system_processing_mode = toy_feature
+                        .penetration_system
+                        .pen_system_processing_mode
+                        .to_frontend();
+                    toy_feature
+                        .penetration_system
+                        .from_frontend(FePenetrationSystem {
+                            pen_system_type,
+                            pen_system_processing_mode,
+                        });
                 }
             }
+
+            if !matches!(suggestion.anatomy, FeVCToyAnatomy::NA) {
+                anatomy.from_fe(suggestion.anatomy);
+            }
+            Ok(())
+        },
+    )
+}
+
+/*
+ * Alters the features and anatomy of a toy and saves the result
+ * Online toys are looked up by id and sent to the toy manager, offline toy configs are looked up by name
+ * alter gets the error to return when the toy has no such feature
+ */
+fn alter_toy_features(
+    vc_state: tauri::State<'_, VCStateMutex>,
+    app_handle: tauri::AppHandle,
+    toy_id: Option<u32>,
+    toy_name: String,
+    alter: impl FnOnce(
+        &mut VCToyFeatures,
+        &mut VCToyAnatomy,
+        frontend::ToyAlterError,
+    ) -> Result<(), frontend::VCFeError>,
+) -> Result<(), frontend::VCFeError> {
+    match toy_id {
+        Some(toy_id) => {
+            let altered = {
+                let mut vc_lock = vc_state.0.lock();
+                let Some(toy) = vc_lock
+                    .core_toy_manager
+                    .as_mut()
+                    .ok_or(frontend::VCFeError::ToyManagerNotReady)?
+                    .online_toys
+                    .get_mut(&toy_id)
+                else {
+                    return Err(frontend::VCFeError::AlterToyFailure(
+                        frontend::ToyAlterError::NoToyIndex,
+                    ));
+                };
+
+                let config = toy.config.as_mut().unwrap();
+                alter(
+                    &mut toy.parsed_toy_features,
+                    &mut config.anatomy,
+                    frontend::ToyAlterError::NoFeatureIndex,
+                )?;
+                config.features = toy.parsed_toy_features.clone();
+                toy.clone()
+            };
+
+            native_alter_toy(vc_state, app_handle, altered).map_err(|_| {
+                frontend::VCFeError::AlterToyFailure(frontend::ToyAlterError::TMESendFailure)
+            })
+        }
+        None => {
+            let mut offline_toy_config = VCToyConfig::load_offline_toy_config(toy_name.clone())
+                .map_err(|_| {
+                    frontend::VCFeError::AlterToyFailure(
+                        frontend::ToyAlterError::OfflineToyNotExist,
+                    )
+                })?;
+
+            alter(
+                &mut offline_toy_config.features,
+                &mut offline_toy_config.anatomy,
+                frontend::ToyAlterError::OfflineToyNoFeatureIndex,
+            )?;
+            offline_toy_config.save_offline_toy_config();
+
+            let _ = app_handle.emit_all(
+                "fe_toy_event",
+                FeToyEvent::Update(FeVCToy {
+                    toy_id: None,
+                    toy_name,
+                    toy_anatomy: offline_toy_config.anatomy.to_fe(),
+                    toy_power: ToyPower::Offline,
+                    toy_connected: false,
+                    features: offline_toy_config.features.features.to_frontend(),
+                    listening: false,
+                    osc_data: offline_toy_config.osc_data,
+                    sub_id: 255,
+                }),
+            );
+            Ok(())
         }
     }
+}
+
+/*
+ * Finds a feature by index and frontend type
+ * Scalar rotators are shown as rotators in the frontend
+ */
+fn find_feature_mut<'a>(
+    features: &'a mut VCToyFeatures,
+    feature_index: u32,
+    feature_type: FeVCFeatureType,
+) -> Option<(usize, &'a mut VCToyFeature)> {
+    features.features.iter_mut().enumerate().find(|(_, f)| {
+        f.feature_index == feature_index
+            && (f.feature_type == feature_type
+                || f.feature_type == VCFeatureType::ScalarRotator
+                    && feature_type == FeVCFeatureType::Rotator)
+    })
+}
+
+/*
+ * Adds addr to the matching feature
+ * no_feature is the error returned when the toy has no such feature
+ */
+fn assign_feature_parameter(
+    features: &mut VCToyFeatures,
+    feature_index: u32,
+    feature_type: FeVCFeatureType,
+    addr: String,
+    processing_mode: ProcessingMode,
+    no_feature: frontend::ToyAlterError,
+) -> Result<(), frontend::VCFeError> {
+    let Some((i, feature)) = find_feature_mut(features, feature_index, feature_type) else {
+        return Err(frontend::VCFeError::AlterToyFailure(no_feature));
+    };
+
+    let path = format!(
+        "features[{}].osc_parameters[{}].parameter",
+        i,
+        feature.osc_parameters.len()
+    );
+    let kind = if !addr.starts_with('/') {
+        Some(FieldErrorKind::MissingLeadingSlash)
+    } else if feature.osc_parameters.iter().any(|p| p.parameter == addr) {
+        Some(FieldErrorKind::DuplicateAddress)
+    } else {
+        None
+    };
+    if let Some(kind) = kind {
+        return Err(frontend::VCFeError::InvalidToyAlteration(vec![
+            FieldError { path, kind },
+        ]));
+    }
+
+    info!("Assigning {} to feature index {}", addr, feature_index);
+    feature.osc_parameters.push(ToyParameter {
+        parameter: addr,
+        processing_mode_values: ProcessingModeValues::new_from(&processing_mode),
+        processing_mode,
+        int_values: Vec::new(),
+    });
     Ok(())
 }
 
+/*
+ * Reads the avatar OSC configs VRChat wrote with suggested toy setups for each avatar
+ */
+pub fn native_avatar_configs() -> Result<Vec<FeAvatarOSCConfig>, backend::VibeCheckFSError> {
+    read_avatar_configs(Path::new(&get_vrchat_osc_dir()))
+}
+
+/*
+ * Clears the OSC configs of an avatar, a user or every user into a restorable backup
+ */
+pub fn native_clear_osc_config(
+    user_id: Option<String>,
+    avatar_id: Option<String>,
+) -> Result<FeOSCConfigClearResult, frontend::VCFeError> {
+    clear_osc_configs(
+        Path::new(&get_vrchat_osc_dir()),
+        Path::new(&backups_dir()),
+        user_id.as_deref(),
+        avatar_id.as_deref(),
+    )
+}
+
 pub fn native_simulate_device_feature(
     vc_state: tauri::State<'_, VCStateMutex>,
     toy_id: u32,
@@ -837,12 +1556,13 @@ pub fn native_simulate_device_feature(
                     handle_clone.stop();
                 } else {
                     vc_lock.async_rt.spawn(command_toy(
-                        handle_clone,
+                        ToyCommandTarget::Device(handle_clone),
                         feature.feature_type,
                         float_level,
                         feature.feature_index,
                         feature.flip_input_float,
                         feature.feature_levels,
+                        feature.output_parameter.as_deref(),
                     ));
                 }
             }
@@ -850,41 +1570,3 @@ pub fn native_simulate_device_feature(
         }
     }
 }
-
-/* Leaving this here in case of future use
- *
-pub fn native_simulate_feature_osc_input(vc_state: tauri::State<'_, VCStateMutex>, simulated_param_address: String, simulated_param_value: f32) {
-
-    let osc_buf = match encoder::encode(&OscPacket::Message(OscMessage {
-        addr: simulated_param_address.clone(),
-        args: vec![OscType::Float(simulated_param_value)],
-    })) {
-        Ok(buf) => buf,
-        Err(_e) => return,
-    };
-
-    let simulation_sock = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
-        Ok(sim_sock) => sim_sock,
-        Err(_e) => return,
-    };
-
-    let self_osc_bind_address = {
-        let vc_config = vc_state.0.lock();
-        vc_config.config.networking.bind
-    };
-
-    let _ = simulation_sock.send_to(&osc_buf, self_osc_bind_address);
-    std::thread::sleep(std::time::Duration::from_secs(1));
-
-    let osc_buf = match encoder::encode(&OscPacket::Message(OscMessage {
-        addr: simulated_param_address,
-        args: vec![OscType::Float(0.0)],
-    })) {
-        Ok(buf) => buf,
-        Err(_e) => return,
-    };
-
-    let _ = simulation_sock.send_to(&osc_buf, self_osc_bind_address);
-}
- *
- */
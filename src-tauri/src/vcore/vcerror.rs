@@ -8,6 +8,7 @@ pub mod frontend {
     #[derive(Serialize)]
     pub enum VCFeError {
         AlterToyFailure(ToyAlterError),
+        InvalidToyAlteration(Vec<FieldError>),
         // App State Errors
         EnableFailure,
         EnableBindFailure,
@@ -17,8 +18,23 @@ pub mod frontend {
 
         // Config Errors
         InvalidBindEndpoint,
+        NoListeners,
+        DuplicateListener(String),
+        InvalidListenerAllowlist(String),
+        InvalidListenerApiSecret(String),
         InvalidRemoteEndpoint,
+        InvalidRepeaterEndpoint(String),
+        DuplicateRepeaterTarget(String),
+        RepeaterTargetNotFound,
+        InvalidTelemetryInterval,
+        InvalidTelemetryTemplate,
+        InvalidTelemetryEndpoint(String),
+        InvalidTelemetryAlias(String),
+        InvalidBatteryThreshold(String),
+        InvalidChatboxCooldown,
         OSCQueryFailure(&'static str),
+        OSCQueryRequestFailure(String),
+        VRChatNotFound,
         SerializeFailure,
         WriteFailure,
         //InvalidIpv4Host,
@@ -27,6 +43,33 @@ pub mod frontend {
         InvalidLCHost,
 
         ToyManagerNotReady,
+
+        // OSC Recording Errors
+        RecordingActive,
+        RecordingNotActive,
+        RecordingNotFound,
+        InvalidRecordingName,
+        InvalidReplaySpeed,
+        ReplayActive,
+        ReplayNotActive,
+
+        // OSC Injection Errors
+        InvalidInjectedMessage,
+        NotListening,
+        InjectionActive,
+        InjectionNotActive,
+
+        // Parameter Learn Errors
+        LearnActive,
+        LearnNotActive,
+        NoLearnProposal,
+
+        // OSC Avatar Config Errors
+        InvalidOSCConfigTarget,
+        OSCConfigNotFound,
+        OSCConfigMoveFailure(String),
+        InvalidOSCConfigBackup,
+        OSCConfigBackupNotFound,
     }
 
     #[derive(Serialize)]
@@ -39,6 +82,26 @@ pub mod frontend {
         OfflineToyNotExist,
         OfflineToyNoFeatureIndex,
     }
+
+    /*
+     * A single invalid field of a frontend type
+     * path is the location of the field in the altered toy (eg. features[0].feature_levels.minimum_level)
+     */
+    #[derive(Serialize, Debug, Clone, PartialEq)]
+    pub struct FieldError {
+        pub path: String,
+        pub kind: FieldErrorKind,
+    }
+
+    #[derive(Serialize, Debug, Clone, PartialEq)]
+    pub enum FieldErrorKind {
+        OutOfRange { min: f64, max: f64 },
+        MinimumAboveMaximum,
+        EmptyAddress,
+        DuplicateAddress,
+        MissingLeadingSlash,
+        DuplicateIntValue,
+    }
 }
 
 pub mod backend {
@@ -65,7 +128,6 @@ pub mod backend {
     pub enum VibeCheckFSError {
         ReadDirFailure,
         ReadDirPathFailure,
-        RemoveDirsFailure,
     }
 
     pub enum ToyAlterError {
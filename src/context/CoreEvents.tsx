@@ -1,7 +1,9 @@
 import { invoke } from "@tauri-apps/api";
 import { listen } from "@tauri-apps/api/event";
 import { createContext, useContext, useEffect, useState } from "react";
+import type { FeBatteryEvent } from "../../src-tauri/bindings/FeBatteryEvent";
 import type { FeCoreEvent } from "../../src-tauri/bindings/FeCoreEvent";
+import type { FeOSCBindFailure } from "../../src-tauri/bindings/FeOSCBindFailure";
 import { FeStateEvent } from "../../src-tauri/bindings/FeStateEvent";
 import type { FeVibeCheckConfig } from "../../src-tauri/bindings/FeVibeCheckConfig";
 import { createToast } from "../components/Toast";
@@ -116,6 +118,25 @@ export function CoreEventProvider({ children }: { children: React.ReactNode }) {
     }
   }
 
+  function handleBatteryEvent(payload: FeBatteryEvent) {
+    if (payload.alert == "Normal") return;
+    createToast(
+      payload.alert == "Critical" ? "error" : "warn",
+      `${payload.alert} battery`,
+      `${payload.toy_name} (${Math.round(payload.level * 100)}%)`,
+    );
+  }
+
+  function handleBindFailures(failures: FeOSCBindFailure[]) {
+    failures.forEach((failure) =>
+      createToast(
+        "error",
+        `Could not bind OSC listener ${failure.name}`,
+        `${failure.address}: ${failure.error}`,
+      ),
+    );
+  }
+
   function handleCoreEvent(payload: FeCoreEvent) {
     switch (payload.kind) {
       case "Scan":
@@ -124,6 +145,9 @@ export function CoreEventProvider({ children }: { children: React.ReactNode }) {
       case "State":
         handleStateEvent(payload.data);
         break;
+      case "Battery":
+        handleBatteryEvent(payload.data);
+        break;
       default:
         assertExhaustive(payload);
     }
@@ -139,6 +163,21 @@ export function CoreEventProvider({ children }: { children: React.ReactNode }) {
     };
   }, []);
 
+  useEffect(() => {
+    const unlistenPromise = listen<FeOSCBindFailure[]>(
+      LISTEN.OSC_BIND_FAILURES,
+      (event) => handleBindFailures(event.payload),
+    );
+    // The startup bind happens before this window listens
+    invoke<FeOSCBindFailure[]>(INVOKE.OSC_BIND_FAILURES)
+      .then(handleBindFailures)
+      .catch(() => null);
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
   async function refreshConfig() {
     try {
       const config = await invoke<FeVibeCheckConfig>(INVOKE.GET_CONFIG);
@@ -1,6 +1,7 @@
 export const LISTEN = {
   CORE_EVENT: "fe_core_event",
   TOY_EVENT: "fe_toy_event",
+  OSC_BIND_FAILURES: "fe_osc_bind_failures",
 } as const;
 
 export const INVOKE = {
@@ -16,6 +17,7 @@ export const INVOKE = {
   SET_CONFIG: "set_vibecheck_config",
   OPEN_BROWSER: "open_default_browser",
   OFFLINE_SYNC: "sync_offline_toys",
+  OSC_BIND_FAILURES: "osc_bind_failures",
 } as const;
 
 export const OSC = {
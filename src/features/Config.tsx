@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api";
 import { ChangeEvent, FormEvent, useState } from "react";
+import type { FeOSCConfigClearResult } from "../../src-tauri/bindings/FeOSCConfigClearResult";
 import type { FeVibeCheckConfig } from "../../src-tauri/bindings/FeVibeCheckConfig";
 import { createToast } from "../components/Toast";
 import UpdateButton from "../components/UpdateButton";
@@ -49,10 +50,23 @@ export default function Config({
     });
   };
 
+  // Only the primary listener is editable here, extra listeners are kept as is
+  const onChangePrimaryBind = (e: ChangeEvent<HTMLInputElement>) => {
+    const [primary, ...rest] = newConfig.networking.bind;
+    setNewConfig({
+      ...newConfig,
+      networking: {
+        ...newConfig.networking,
+        bind: [{ ...primary, address: e.target.value }, ...rest],
+      },
+    });
+  };
+
   async function saveConfig() {
     try {
       if (
-        newConfig.networking.bind != config.networking.bind ||
+        newConfig.networking.bind[0]?.address !=
+          config.networking.bind[0]?.address ||
         newConfig.networking.remote != config.networking.remote
       ) {
         await disableOnPortChange();
@@ -66,11 +80,15 @@ export default function Config({
 
   async function clearOsc() {
     try {
-      await invoke(INVOKE.CLEAR_OSC_CONFIG);
+      const result = await invoke<FeOSCConfigClearResult>(
+        INVOKE.CLEAR_OSC_CONFIG,
+      );
       createToast(
         "info",
         "Cleared avatar OSC configs",
-        "Removed AppData\\LocalLow\\VRChat\\VRChat\\OSC",
+        result.backup
+          ? `Moved ${result.removed.length} files to backup ${result.backup}`
+          : "No avatar OSC configs to clear",
       );
     } catch (e) {
       createToast(
@@ -100,12 +118,12 @@ export default function Config({
             <input
               name="bind"
               className="rounded-sm px-1 text-zinc-800 outline-none"
-              value={newConfig.networking.bind}
-              onChange={onChangeNetworking}
-              pattern={String.raw`^((25[0-5]|(2[0-4]|1\d|[1-9]|)\d)\.?\b){4}:\d{1,5}$`}
+              value={newConfig.networking.bind[0]?.address ?? ""}
+              onChange={onChangePrimaryBind}
+              pattern={String.raw`^(\[[0-9a-fA-F:.]+\]|((25[0-5]|(2[0-4]|1\d|[1-9]|)\d)\.?\b){4}):\d{1,5}$`}
               onInvalid={(e) =>
                 (e.target as HTMLInputElement).setCustomValidity(
-                  "Enter valid IP:HOST",
+                  "Enter valid IP:PORT or [IPv6]:PORT",
                 )
               }
               onInput={(e) =>
@@ -119,10 +137,10 @@ export default function Config({
               className="rounded-sm px-1 text-zinc-800 outline-none"
               value={newConfig.networking.remote}
               onChange={onChangeNetworking}
-              pattern={String.raw`^((25[0-5]|(2[0-4]|1\d|[1-9]|)\d)\.?\b){4}:\d{1,5}$`}
+              pattern={String.raw`^(\[[0-9a-fA-F:.]+\]|[A-Za-z0-9.\-]+):\d{1,5}$`}
               onInvalid={(e) =>
                 (e.target as HTMLInputElement).setCustomValidity(
-                  "Enter valid IP:HOST",
+                  "Enter valid HOST:PORT or [IPv6]:PORT",
                 )
               }
               onInput={(e) =>
@@ -146,10 +164,10 @@ export default function Config({
                 className="px-1 text-zinc-800"
                 value={newConfig.lc_override}
                 onChange={onChange}
-                pattern={String.raw`^((25[0-5]|(2[0-4]|1\d|[1-9]|)\d)\.?\b){4}$`}
+                pattern={String.raw`^([0-9a-fA-F:.]+|[A-Za-z0-9.\-]+)$`}
                 onInvalid={(e) =>
                   (e.target as HTMLInputElement).setCustomValidity(
-                    "Enter valid IP",
+                    "Enter valid IP or hostname",
                   )
                 }
                 onInput={(e) =>
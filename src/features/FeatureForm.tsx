@@ -18,6 +18,7 @@ import {
 } from "react";
 import { FeProcessingMode } from "src-tauri/bindings/FeProcessingMode";
 import { FeToyParameter } from "src-tauri/bindings/FeToyParameter";
+import { IntValueMapping } from "src-tauri/bindings/IntValueMapping";
 import { FeLevelTweaks } from "../../src-tauri/bindings/FeLevelTweaks";
 import { FeVCToy } from "../../src-tauri/bindings/FeVCToy";
 import type { FeVCToyFeature } from "../../src-tauri/bindings/FeVCToyFeature";
@@ -251,6 +252,7 @@ function Parameters() {
           {
             parameter: newParam,
             processing_mode: "Raw" as const,
+            int_values: [],
           },
         ],
       };
@@ -292,6 +294,65 @@ function Parameters() {
     });
   }
 
+  function alterIntValues(
+    paramIndex: number,
+    alter: (intValues: IntValueMapping[]) => IntValueMapping[],
+    commit: (f: FeVCToyFeature) => void,
+  ) {
+    setToyFeature((f) => {
+      const newParams = [...f.osc_parameters];
+      newParams[paramIndex] = {
+        ...newParams[paramIndex],
+        int_values: alter(newParams[paramIndex].int_values),
+      };
+      const newF = {
+        ...f,
+        osc_parameters: newParams,
+      };
+      commit(newF);
+      return newF;
+    });
+  }
+
+  function addIntValue(paramIndex: number) {
+    alterIntValues(
+      paramIndex,
+      (intValues) => {
+        // Next value after the highest mapped one
+        const value = intValues.reduce(
+          (acc, m) => Math.max(acc, m.value + 1),
+          0,
+        );
+        return [...intValues, { value, level: 1 }];
+      },
+      handleFeatureAlter,
+    );
+  }
+
+  function removeIntValue(paramIndex: number, mappingIndex: number) {
+    alterIntValues(
+      paramIndex,
+      (intValues) => intValues.filter((_, i) => i != mappingIndex),
+      handleFeatureAlter,
+    );
+  }
+
+  function handleIntValue(
+    paramIndex: number,
+    mappingIndex: number,
+    mapping: Partial<IntValueMapping>,
+    commit: (f: FeVCToyFeature) => void,
+  ) {
+    alterIntValues(
+      paramIndex,
+      (intValues) =>
+        intValues.map((m, i) =>
+          i == mappingIndex ? { ...m, ...mapping } : m,
+        ),
+      commit,
+    );
+  }
+
   function normalizeOscParameter(p: string) {
     return `${OSC.PARAM_PREFIX}${p.replaceAll(" ", "_")}`;
   }
@@ -326,6 +387,55 @@ function Parameters() {
             >
               <X className="h-5" />
             </button>
+            {param.int_values.map((mapping, mappingIndex) => (
+              <Fragment key={mappingIndex}>
+                <input
+                  className="col-span-1 w-full rounded-sm px-4 text-zinc-800 outline-none md:col-span-2 md:ml-8 md:w-auto"
+                  name="int_value"
+                  type="number"
+                  step={1}
+                  value={mapping.value}
+                  onChange={(e) => {
+                    const value = parseInt(e.target.value);
+                    if (isNaN(value)) return;
+                    handleIntValue(
+                      paramIndex,
+                      mappingIndex,
+                      { value },
+                      debouncedAlter,
+                    );
+                  }}
+                />
+                <Slider
+                  multiply={100}
+                  min={0}
+                  max={1}
+                  step={0.01}
+                  value={[mapping.level]}
+                  onValueChange={(e) =>
+                    handleIntValue(
+                      paramIndex,
+                      mappingIndex,
+                      { level: e[0] },
+                      () => null,
+                    )
+                  }
+                  onValueCommit={() => handleFeatureAlter(feature)}
+                />
+                <button
+                  className="flex justify-center"
+                  onClick={() => removeIntValue(paramIndex, mappingIndex)}
+                >
+                  <X className="h-4" />
+                </button>
+              </Fragment>
+            ))}
+            <button
+              className="col-span-2 text-left text-xs text-zinc-400 md:col-span-4 md:ml-8"
+              onClick={() => addIntValue(paramIndex)}
+            >
+              + Int value
+            </button>
           </Fragment>
         );
       })}